@@ -0,0 +1,201 @@
+use anyhow::{Context, bail};
+use clap::CommandFactory;
+use flux_core::repo::{config::Config, repository::Repository};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cli::Cli;
+
+/// What to run once aliases are expanded.
+pub enum Invocation {
+    /// A flux command line, to be parsed by clap.
+    Flux(Vec<String>),
+    /// A `!` alias: a shell command run with the remaining arguments appended.
+    Shell {
+        command: String,
+        args: Vec<String>,
+        dir: PathBuf,
+    },
+}
+
+/// Replaces the subcommand of `args` by its `[alias]` definition until a built-in
+/// command is reached. Built-in commands cannot be redefined by an alias.
+pub fn expand(mut args: Vec<String>) -> anyhow::Result<Invocation> {
+    let options = GlobalOptions::scan(&args);
+    let Some(position) = options.command else {
+        return Ok(Invocation::Flux(args));
+    };
+    if is_builtin(&args[position]) {
+        return Ok(Invocation::Flux(args));
+    }
+
+    // Shell aliases run from the top of the work tree, like git's.
+    let (config, dir) = match Repository::open(options.repo_path.clone()) {
+        Ok(repository) => (repository.config, repository.work_tree),
+        Err(_) => (Config::global()?, std::env::current_dir()?),
+    };
+
+    let mut chain: Vec<String> = Vec::new();
+    let mut position = position;
+    loop {
+        let name = args[position].clone();
+        if is_builtin(&name) {
+            return Ok(Invocation::Flux(args));
+        }
+        let Some(value) = options
+            .alias(&name)
+            .or_else(|| config.get_str(&format!("alias.{name}")).map(String::from))
+        else {
+            if chain.is_empty() {
+                // Let clap report the unknown command.
+                return Ok(Invocation::Flux(args));
+            }
+            bail!("Alias '{}' expands to unknown command '{name}'", chain[0]);
+        };
+
+        if chain.contains(&name) {
+            chain.push(name);
+            bail!("Alias loop detected: {}", chain.join(" -> "));
+        }
+        chain.push(name);
+
+        if let Some(command) = value.strip_prefix('!') {
+            return Ok(Invocation::Shell {
+                command: command.to_string(),
+                args: args.split_off(position + 1),
+                dir,
+            });
+        }
+
+        let words = split_words(&value)
+            .with_context(|| format!("Invalid definition of alias '{}'", chain.join(" -> ")))?;
+        if words.is_empty() {
+            bail!("Alias '{}' is empty", chain.join(" -> "));
+        }
+        args.splice(position..=position, words);
+        position = GlobalOptions::scan(&args)
+            .command
+            .context("Alias does not name a command")?;
+    }
+}
+
+/// Runs a `!` alias through `sh`, the arguments given after the alias being available
+/// as `$@` and appended to the command. Returns the exit code of the command.
+pub fn run_shell(command: &str, args: &[String], dir: &Path) -> anyhow::Result<i32> {
+    let script = if args.is_empty() {
+        command.to_string()
+    } else {
+        format!("{command} \"$@\"")
+    };
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .arg(command)
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .with_context(|| format!("Failed to run alias command '{command}'"))?;
+
+    Ok(status.code().unwrap_or(1))
+}
+
+/// The options given before the subcommand, which aliases need to know about before
+/// clap parses the command line.
+struct GlobalOptions {
+    repo_path: Option<String>,
+    /// `-c key=value` pairs, in order.
+    config: Vec<(String, String)>,
+    /// The index of the subcommand in the arguments.
+    command: Option<usize>,
+}
+
+impl GlobalOptions {
+    fn scan(args: &[String]) -> Self {
+        let mut options = GlobalOptions {
+            repo_path: None,
+            config: Vec::new(),
+            command: None,
+        };
+
+        let mut index = 1;
+        while index < args.len() {
+            let arg = args[index].as_str();
+            let value = if arg == "--repo-path" || arg == "-c" {
+                index += 1;
+                args.get(index).map(String::as_str)
+            } else if let Some(value) = arg.strip_prefix("--repo-path=") {
+                Some(value)
+            } else if let Some(value) = arg.strip_prefix("-c").filter(|_| !arg.starts_with("--")) {
+                Some(value.strip_prefix('=').unwrap_or(value))
+            } else if arg.starts_with('-') {
+                index += 1;
+                continue;
+            } else {
+                options.command = Some(index);
+                break;
+            };
+
+            if let Some(value) = value {
+                if arg.starts_with("--repo-path") {
+                    options.repo_path = Some(value.to_string());
+                } else {
+                    let (key, value) = value.split_once('=').unwrap_or((value, "true"));
+                    options.config.push((key.to_lowercase(), value.to_string()));
+                }
+            }
+            index += 1;
+        }
+
+        options
+    }
+
+    /// An alias defined with `-c alias.<name>=...` on this command line.
+    fn alias(&self, name: &str) -> Option<String> {
+        let key = format!("alias.{}", name.to_lowercase());
+        self.config
+            .iter()
+            .rev()
+            .find(|(option, _)| *option == key)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+fn is_builtin(name: &str) -> bool {
+    name == "help"
+        || Cli::command().get_subcommands().any(|command| {
+            command.get_name() == name || command.get_all_aliases().any(|alias| alias == name)
+        })
+}
+
+/// Splits an alias definition into words like a shell would: whitespace separates
+/// words, except inside single or double quotes, and a backslash escapes the next
+/// character.
+fn split_words(value: &str) -> anyhow::Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some('"') | None, '\\') => {
+                let escaped = chars.next().context("Trailing backslash")?;
+                word.get_or_insert_default().push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+
+    if quote.is_some() {
+        bail!("Unterminated quote");
+    }
+    words.extend(word);
+    Ok(words)
+}
@@ -3,10 +3,15 @@ use clap::{Parser, Subcommand};
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 pub struct Cli {
-    /// Path to the repository (defaults to current directory)
+    /// Run as if flux was started in this directory (defaults to the current directory);
+    /// the repository is looked for there and in its parent directories
     #[arg(long = "repo-path", global = true)]
     pub repo_path: Option<String>,
 
+    /// Override a configuration value for this command (`-c user.name=Someone`)
+    #[arg(short = 'c', value_name = "key=value", global = true)]
+    pub config: Vec<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -14,7 +19,14 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum BranchCommands {
     /// Show all branches
-    Show {},
+    Show {
+        /// Also show remote-tracking branches
+        #[arg(short = 'a', long = "all")]
+        all: bool,
+
+        /// Only show branches starting with this prefix (e.g. `feature/`)
+        prefix: Option<String>,
+    },
 
     /// Create a new branch with the given name
     New {
@@ -42,19 +54,120 @@ pub enum BranchCommands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum StashCommands {
+    /// Save local changes and reset the working tree to HEAD
+    Push {
+        /// Description of the stash
+        #[arg(short = 'm', long = "message")]
+        message: Option<String>,
+
+        /// Also stash untracked files
+        #[arg(short = 'u', long = "include-untracked")]
+        include_untracked: bool,
+    },
+
+    /// List the stashes, most recent first
+    List {},
+
+    /// Show the files changed by a stash
+    Show {
+        /// Stash to show, e.g. `stash@{1}` (defaults to the latest)
+        stash: Option<String>,
+    },
+
+    /// Apply a stash and remove it from the list
+    Pop { stash: Option<String> },
+
+    /// Apply a stash and keep it in the list
+    Apply { stash: Option<String> },
+
+    /// Remove a stash from the list
+    Drop { stash: Option<String> },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RemoteCommands {
+    /// Add a remote repository
+    Add {
+        name: String,
+
+        /// Path of the remote repository (optionally prefixed with `file://`), of a bundle
+        /// file, or `http://` URL
+        url: String,
+    },
+
+    /// Remove a remote and its remote-tracking branches
+    Remove { name: String },
+
+    /// List the remotes with their URLs
+    List {},
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BundleCommands {
+    /// Write the given revisions and the objects they need to a bundle file
+    Create {
+        file: String,
+
+        /// Branches, tags, `A..B` ranges, `^A` exclusions or `--all`
+        #[arg(required = true, allow_hyphen_values = true)]
+        revisions: Vec<String>,
+    },
+
+    /// Store the objects of a bundle and list the refs it carries
+    Unbundle { file: String },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Initialize a new Flux repository
     ///
     /// Creates the repository structure in the specified directory.
     /// If no path is provided, the current directory is used.
+    /// Existing `.git` repositories need no initialization: flux opens them directly.
     Init {
         /// Target directory for the repository
         path: Option<String>,
+
+        /// Use git's layout (`.git`, binary index) so git can work on the repository too
+        #[arg(long)]
+        git: bool,
+    },
+
+    /// Clone a repository into a new directory
+    ///
+    /// The source is either a path on disk (optionally prefixed with `file://`),
+    /// whose objects are hardlinked whenever possible, a bundle file or an `http://` URL.
+    Clone {
+        /// Repository to clone
+        source: String,
+
+        /// Directory to clone into (defaults to the name of the source directory)
+        directory: Option<String>,
+    },
+
+    /// Set a configuration value, such as `user.name` or `remote.origin.url`
+    Set {
+        /// Write to the global config file (`~/.fluxconfig`) instead of the repository's
+        #[arg(long)]
+        global: bool,
+
+        key: String,
+        value: String,
+    },
+
+    /// Remove a configuration value
+    Unset {
+        /// Remove from the global config file instead of the repository's
+        #[arg(long)]
+        global: bool,
+
+        key: String,
     },
 
-    /// Set a configuration value
-    Set { key: String, value: String },
+    /// Print the value a configuration key has after combining every config file
+    Get { key: String },
 
     /// Display the contents of a repository object
     ///
@@ -90,11 +203,102 @@ pub enum Commands {
         tree_hash: String,
     },
 
-    /// Add a file or directory to the staging area
-    Add { path: String },
+    /// Add files to the staging area
+    ///
+    /// Paths are pathspecs: files or directories relative to the current directory,
+    /// globs such as `*.rs` or `src/**/mod.rs`, and `:(exclude)` patterns.
+    Add {
+        /// Stage the modifications and deletions of tracked files only
+        #[arg(short = 'u', long = "update", conflicts_with = "all")]
+        update: bool,
+
+        /// Stage every change, new files included
+        #[arg(short = 'A', long = "all")]
+        all: bool,
+
+        /// Choose the hunks of the modified files to stage, one by one
+        #[arg(short = 'p', long = "patch", conflicts_with_all = ["update", "all"])]
+        patch: bool,
+
+        /// Files to add; with -u, -A or -p, all files by default
+        #[arg(required_unless_present_any = ["update", "all", "patch"])]
+        paths: Vec<String>,
+    },
+
+    /// Remove files from the staging area, keeping them in the working tree
+    Delete {
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+
+    /// Remove tracked files from the working tree and the staging area
+    ///
+    /// Files with changes that would be lost are refused unless -f is given.
+    Rm {
+        /// Only remove the files from the staging area
+        #[arg(long)]
+        cached: bool,
+
+        /// Remove the files even when they have staged or local changes
+        #[arg(short = 'f', long = "force")]
+        force: bool,
 
-    /// Remove a file or directory from the staging area
-    Delete { path: String },
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+
+    /// Rename or move tracked files and directories
+    Mv {
+        /// Overwrite the destination file if it exists
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+
+        /// Files or directories to move, then the destination. With several sources the
+        /// destination has to be a directory.
+        #[arg(required = true, num_args = 2..)]
+        paths: Vec<String>,
+    },
+
+    /// Show the files that differ between HEAD, the index and the working tree
+    Status {
+        /// Only show the files matching these pathspecs
+        paths: Vec<String>,
+    },
+
+    /// Remove untracked files from the working tree
+    ///
+    /// Files matched by .fluxignore are kept unless -x is given. Nothing is removed
+    /// without -f, unless clean.requireForce is set to false.
+    Clean {
+        /// Only list what would be removed
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Remove the files
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+
+        /// Remove untracked directories as well
+        #[arg(short = 'd')]
+        directories: bool,
+
+        /// Remove ignored files as well
+        #[arg(short = 'x')]
+        ignored: bool,
+
+        /// Only remove the files matching these pathspecs
+        paths: Vec<String>,
+    },
+
+    /// Show the changes of the working tree that are not staged, as a unified diff
+    Diff {
+        /// Show the staged changes instead, between HEAD and the index
+        #[arg(long, alias = "staged")]
+        cached: bool,
+
+        /// Only show the files matching these pathspecs
+        paths: Vec<String>,
+    },
 
     /// Create a commit object from a tree
     ///
@@ -115,14 +319,209 @@ pub enum Commands {
     WriteIndex {},
 
     /// Create a new commit from the current index
+    ///
+    /// With --amend, the last commit is replaced instead: the new commit has its
+    /// parents, and its message and author unless they are given. Without -m, the
+    /// message is edited in the editor.
     Commit {
         /// Commit message
-        #[arg(short = 'm', long = "message")]
-        message: String,
+        #[arg(short = 'm', long = "message", required_unless_present = "amend")]
+        message: Option<String>,
+
+        /// Override the commit author, given as "Name <email>"
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Replace the last commit
+        #[arg(long)]
+        amend: bool,
+
+        /// Keep the message of the amended commit without editing it
+        #[arg(long, requires = "amend", conflicts_with = "message")]
+        no_edit: bool,
+
+        /// Make the configured user the author of the amended commit
+        #[arg(long, requires = "amend", conflicts_with = "author")]
+        reset_author: bool,
     },
 
     /// Show the commit history
-    Log {},
+    Log {
+        /// Draw the history as a graph, one line per commit with its branches and tags
+        #[arg(long)]
+        graph: bool,
+
+        /// Show the history of every branch, remote-tracking branch and tag
+        #[arg(long)]
+        all: bool,
+
+        /// Revisions or ranges (`A..B`, `A...B`, `^A`) to show, HEAD by default
+        revisions: Vec<String>,
+
+        /// Only show the commits changing files matching these pathspecs
+        #[arg(last = true)]
+        paths: Vec<String>,
+    },
+
+    /// Show the commit, author and date that last changed each line of a file
+    Blame {
+        /// Output a machine-readable format, the same as `git blame --porcelain`
+        #[arg(long)]
+        porcelain: bool,
+
+        /// File to annotate, relative to the current directory
+        path: String,
+
+        /// Revision to annotate (defaults to HEAD)
+        revision: Option<String>,
+    },
+
+    /// Reset the current branch to the specified revision
+    ///
+    /// --soft only moves the branch, --mixed (the default) also resets the index,
+    /// --hard resets the index and the working tree.
+    /// With paths (`flux reset <rev> -- <paths>`) only those index entries are reset.
+    Reset {
+        /// Only move the branch ref
+        #[arg(long, conflicts_with_all = ["mixed", "hard"])]
+        soft: bool,
+
+        /// Move the branch ref and reset the index
+        #[arg(long, conflicts_with = "hard")]
+        mixed: bool,
+
+        /// Move the branch ref, reset the index and the working tree
+        #[arg(long)]
+        hard: bool,
+
+        /// Target revision (defaults to HEAD)
+        revision: Option<String>,
+
+        /// Paths to unstage
+        #[arg(last = true, conflicts_with_all = ["soft", "hard"])]
+        paths: Vec<String>,
+    },
+
+    /// Restore files in the working tree or the index
+    ///
+    /// By default the working tree is restored from the index.
+    /// With --staged the index is restored from HEAD (or from --source).
+    Restore {
+        /// Revision to restore the files from
+        #[arg(short = 's', long = "source")]
+        source: Option<String>,
+
+        /// Restore the index
+        #[arg(short = 'S', long = "staged")]
+        staged: bool,
+
+        /// Restore the working tree (default unless --staged is given)
+        #[arg(short = 'W', long = "worktree")]
+        worktree: bool,
+
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+
+    /// Apply the changes introduced by an existing commit
+    CherryPick {
+        #[arg(required_unless_present_any = ["cont", "abort"])]
+        revision: Option<String>,
+
+        /// Commit after the conflicts have been resolved
+        #[arg(long = "continue", conflicts_with = "abort")]
+        cont: bool,
+
+        /// Cancel the operation and go back to the previous state
+        #[arg(long = "abort")]
+        abort: bool,
+    },
+
+    /// Create a commit that undoes the changes of an existing commit
+    Revert {
+        #[arg(required_unless_present_any = ["cont", "abort"])]
+        revision: Option<String>,
+
+        /// Commit after the conflicts have been resolved
+        #[arg(long = "continue", conflicts_with = "abort")]
+        cont: bool,
+
+        /// Cancel the operation and go back to the previous state
+        #[arg(long = "abort")]
+        abort: bool,
+    },
+
+    /// Replay the commits of the current branch on top of another base
+    Rebase {
+        /// Branch or commit the current branch should be rebased on
+        #[arg(required_unless_present_any = ["cont", "skip", "abort"])]
+        upstream: Option<String>,
+
+        /// Replay the commits onto this base instead of the upstream
+        #[arg(long = "onto", value_name = "newbase")]
+        onto: Option<String>,
+
+        /// Go on after resolving the conflicts
+        #[arg(long = "continue", conflicts_with_all = ["skip", "abort"])]
+        cont: bool,
+
+        /// Drop the commit that caused the conflicts and go on
+        #[arg(long = "skip", conflicts_with = "abort")]
+        skip: bool,
+
+        /// Stop rebasing and restore the original branch
+        #[arg(long = "abort")]
+        abort: bool,
+    },
+
+    /// Move all loose refs into the packed-refs file
+    PackRefs {},
+
+    /// Manage the remote repositories
+    Remote {
+        #[command(subcommand)]
+        subcommand: RemoteCommands,
+    },
+
+    /// Download the branches and tags of a remote
+    Fetch {
+        #[arg(default_value = "origin")]
+        remote: String,
+    },
+
+    /// Update a branch of a remote with the local one
+    ///
+    /// The update must be a fast-forward unless --force is given.
+    Push {
+        remote: String,
+
+        #[arg(value_name = "branch-name")]
+        branch: String,
+
+        /// Overwrite the remote branch even if it is not an ancestor of the local one
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+    },
+
+    /// Stash local changes away (`push` when no subcommand is given)
+    Stash {
+        #[command(subcommand)]
+        subcommand: Option<StashCommands>,
+    },
+
+    /// Move history between repositories without a network, with git-compatible bundles
+    Bundle {
+        #[command(subcommand)]
+        subcommand: BundleCommands,
+    },
+
+    /// Write every branch and tag as a `git fast-import` stream on stdout
+    FastExport {},
+
+    /// Read a `git fast-import` stream from stdin into the repository
+    ///
+    /// The index and the working tree are not updated.
+    FastImport {},
 
     /// Manage branches
     Branch {
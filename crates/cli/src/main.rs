@@ -1,19 +1,96 @@
-use crate::cli::{BranchCommands, Cli, Commands};
+use crate::alias::Invocation;
+use crate::cli::{BranchCommands, BundleCommands, Cli, Commands, RemoteCommands, StashCommands};
 use clap::Parser;
-use flux_core::{commands, repo::repository::Repository};
+use flux_core::{
+    commands,
+    error::Error,
+    repo::{config, repository::Repository},
+    shared::types::reset_mode::ResetMode,
+};
+use std::process::ExitCode;
 
+mod alias;
 pub mod cli;
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:#}");
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+/// Distinct exit codes for the failures scripts may want to handle. 1 is any other
+/// error, 2 is left to clap for invalid usage.
+fn exit_code(error: &anyhow::Error) -> u8 {
+    let Some(error) = error.downcast_ref::<Error>() else {
+        return 1;
+    };
+    match error.root() {
+        Error::NotARepository(_) => 3,
+        Error::ObjectNotFound(_) => 4,
+        Error::CorruptObject { .. } => 5,
+        Error::RevisionNotFound(_) => 6,
+        Error::RefNotFound(_) => 7,
+        Error::RefExists(_) => 8,
+        Error::RefConflict { .. } => 9,
+        Error::DirtyWorkTree(_) => 10,
+        Error::NothingToCommit => 11,
+        Error::Conflict(_) => 12,
+        Error::OperationInProgress(_) => 13,
+        Error::ConfigMissing(_) => 14,
+        Error::InvalidConfig(_) => 15,
+        Error::Identity(_) => 16,
+        Error::Remote(_) => 17,
+        Error::Io(_) | Error::Context { .. } | Error::Other(_) => 1,
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let args = match alias::expand(std::env::args().collect())? {
+        Invocation::Flux(args) => args,
+        Invocation::Shell { command, args, dir } => {
+            std::process::exit(alias::run_shell(&command, &args, &dir)?);
+        }
+    };
+    let cli = Cli::parse_from(args);
     let repo_path = cli.repo_path.clone();
 
+    let overrides = cli
+        .config
+        .iter()
+        .map(|option| {
+            let (key, value) = option.split_once('=').unwrap_or((option, "true"));
+            (key.to_string(), value.to_string())
+        })
+        .collect();
+    config::set_command_line_overrides(overrides)?;
+
     match cli.command {
-        Commands::Init { path } => {
-            Repository::init(path, false)?;
+        Commands::Init { path, git } => {
+            if git {
+                Repository::init_git(path, false)?;
+            } else {
+                Repository::init(path, false)?;
+            }
+        }
+        Commands::Clone { source, directory } => {
+            commands::clone(source, directory)?;
+        }
+        Commands::Set { global, key, value } => {
+            if global {
+                commands::set_global(key, value)?;
+            } else {
+                commands::set(repo_path, key, value)?;
+            }
+        }
+        Commands::Unset { global, key } => {
+            commands::unset(repo_path, key, global)?;
         }
-        Commands::Set { key, value } => {
-            commands::set(repo_path, key, value)?;
+        Commands::Get { key } => {
+            commands::get(repo_path, key)?;
         }
         Commands::CatFile { object_hash, .. } => {
             commands::cat_file(repo_path, object_hash)?;
@@ -31,24 +108,202 @@ fn main() -> anyhow::Result<()> {
         } => {
             commands::commit_tree(repo_path, tree_hash, message, parent_hash)?;
         }
-        Commands::Add { path } => {
-            commands::add(repo_path, path)?;
+        Commands::Add {
+            update,
+            all,
+            patch,
+            paths,
+        } => {
+            if patch {
+                commands::add_patch(repo_path, paths)?;
+            } else if update || all {
+                commands::add_update(repo_path, paths, all)?;
+            } else {
+                commands::add(repo_path, paths)?;
+            }
         }
-        Commands::Delete { path } => {
-            commands::remove(repo_path, path)?;
+        Commands::Delete { paths } => {
+            commands::remove(repo_path, paths)?;
+        }
+        Commands::Rm {
+            cached,
+            force,
+            paths,
+        } => {
+            commands::rm(repo_path, paths, cached, force)?;
+        }
+        Commands::Mv { force, mut paths } => {
+            let destination = paths.pop().unwrap_or_default();
+            commands::mv(repo_path, paths, destination, force)?;
+        }
+        Commands::Status { paths } => {
+            commands::status(repo_path, paths)?;
+        }
+        Commands::Clean {
+            dry_run,
+            force,
+            directories,
+            ignored,
+            paths,
+        } => {
+            commands::clean(repo_path, paths, dry_run, force, directories, ignored)?;
+        }
+        Commands::Diff { cached, paths } => {
+            commands::diff(repo_path, cached, paths)?;
         }
         Commands::WriteIndex {} => {
             commands::write_index(repo_path)?;
         }
-        Commands::Commit { message } => {
-            commands::commit(repo_path, message)?;
+        Commands::Commit {
+            message,
+            author,
+            amend,
+            no_edit,
+            reset_author,
+        } => {
+            if amend {
+                commands::commit_amend(repo_path, message, no_edit, author, reset_author)?;
+            } else {
+                commands::commit(repo_path, message.unwrap_or_default(), author)?;
+            }
+        }
+        Commands::Log {
+            graph,
+            all,
+            revisions,
+            paths,
+        } => {
+            commands::log(repo_path, revisions, paths, all, graph)?;
+        }
+        Commands::Blame {
+            porcelain,
+            path,
+            revision,
+        } => {
+            commands::blame(repo_path, path, revision, porcelain)?;
+        }
+        Commands::Reset {
+            soft,
+            hard,
+            revision,
+            paths,
+            ..
+        } => {
+            let mode = if soft {
+                ResetMode::Soft
+            } else if hard {
+                ResetMode::Hard
+            } else {
+                ResetMode::Mixed
+            };
+            commands::reset(repo_path, mode, revision, paths)?;
+        }
+        Commands::Restore {
+            source,
+            staged,
+            worktree,
+            paths,
+        } => {
+            commands::restore(repo_path, source, staged, worktree, paths)?;
+        }
+        Commands::CherryPick {
+            revision,
+            cont,
+            abort,
+        } => {
+            commands::cherry_pick(repo_path, revision, cont, abort)?;
+        }
+        Commands::Revert {
+            revision,
+            cont,
+            abort,
+        } => {
+            commands::revert(repo_path, revision, cont, abort)?;
+        }
+        Commands::Rebase {
+            upstream,
+            onto,
+            cont,
+            skip,
+            abort,
+        } => {
+            if cont {
+                commands::rebase_continue(repo_path)?;
+            } else if skip {
+                commands::rebase_skip(repo_path)?;
+            } else if abort {
+                commands::rebase_abort(repo_path)?;
+            } else {
+                commands::rebase(repo_path, upstream.unwrap_or_default(), onto)?;
+            }
+        }
+        Commands::PackRefs {} => {
+            commands::pack_refs(repo_path)?;
+        }
+        Commands::Remote { subcommand } => match subcommand {
+            RemoteCommands::Add { name, url } => {
+                commands::add_remote(repo_path, name, url)?;
+            }
+            RemoteCommands::Remove { name } => {
+                commands::remove_remote(repo_path, name)?;
+            }
+            RemoteCommands::List {} => {
+                commands::list_remotes(repo_path)?;
+            }
+        },
+        Commands::Fetch { remote } => {
+            commands::fetch(repo_path, remote)?;
+        }
+        Commands::Push {
+            remote,
+            branch,
+            force,
+        } => {
+            commands::push(repo_path, remote, branch, force)?;
+        }
+        Commands::Stash { subcommand } => match subcommand {
+            None => {
+                commands::stash_push(repo_path, None, false)?;
+            }
+            Some(StashCommands::Push {
+                message,
+                include_untracked,
+            }) => {
+                commands::stash_push(repo_path, message, include_untracked)?;
+            }
+            Some(StashCommands::List {}) => {
+                commands::stash_list(repo_path)?;
+            }
+            Some(StashCommands::Show { stash }) => {
+                commands::stash_show(repo_path, stash)?;
+            }
+            Some(StashCommands::Pop { stash }) => {
+                commands::stash_apply(repo_path, stash, true)?;
+            }
+            Some(StashCommands::Apply { stash }) => {
+                commands::stash_apply(repo_path, stash, false)?;
+            }
+            Some(StashCommands::Drop { stash }) => {
+                commands::stash_drop(repo_path, stash)?;
+            }
+        },
+        Commands::Bundle { subcommand } => match subcommand {
+            BundleCommands::Create { file, revisions } => {
+                commands::bundle_create(repo_path, file, revisions)?;
+            }
+            BundleCommands::Unbundle { file } => {
+                commands::unbundle(repo_path, file)?;
+            }
+        },
+        Commands::FastExport {} => {
+            commands::fast_export(repo_path)?;
         }
-        Commands::Log {} => {
-            commands::log(repo_path)?;
+        Commands::FastImport {} => {
+            commands::fast_import(repo_path)?;
         }
         Commands::Branch { subcommand } => match subcommand {
-            BranchCommands::Show {} => {
-                commands::show_branches(repo_path)?;
+            BranchCommands::Show { all, prefix } => {
+                commands::show_branches(repo_path, all, prefix)?;
             }
             BranchCommands::New { name } => {
                 commands::create_branch(repo_path, name)?;
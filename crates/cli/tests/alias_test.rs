@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn flux(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_flux"))
+        .args(args)
+        .current_dir(dir)
+        .env("FLUX_CONFIG_NOSYSTEM", "1")
+        .env("FLUX_CONFIG_GLOBAL", dir.join(".fluxconfig"))
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn repository() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path();
+    flux(dir, &["init"]);
+    for (key, value) in [
+        ("user.name", "Alias User"),
+        ("user.email", "alias@example.com"),
+        ("alias.ci", "commit -m \"two words\""),
+        ("alias.last", "log"),
+        ("alias.l", "last"),
+        ("alias.ping", "pong"),
+        ("alias.pong", "ping"),
+        ("alias.commit", "log"),
+        ("alias.where", "!pwd"),
+        ("alias.fail", "!exit 3"),
+    ] {
+        assert!(flux(dir, &["set", key, value]).status.success());
+    }
+    fs::write(dir.join("file.txt"), "content").unwrap();
+    flux(dir, &["add", "file.txt"]);
+    temp
+}
+
+#[test]
+fn alias_expansion_test() {
+    let temp = repository();
+    let dir = temp.path();
+
+    let output = flux(dir, &["ci"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    // aliases can name other aliases, and keep the arguments given after them
+    let log = stdout(&flux(dir, &["l"]));
+    assert!(log.contains("two words"), "{log}");
+    assert_eq!(stdout(&flux(dir, &["--repo-path", ".", "last"])), log);
+
+    // built-in commands cannot be redefined
+    let output = flux(dir, &["commit", "-m", "again"]);
+    assert!(stderr(&output).contains("Nothing to commit"));
+
+    let output = flux(dir, &["ping"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Alias loop detected: ping -> pong -> ping"));
+
+    let output = flux(dir, &["-c", "alias.hist=log", "hist"]);
+    assert_eq!(stdout(&output), log);
+}
+
+#[test]
+fn shell_alias_test() {
+    let temp = repository();
+    let dir = temp.path();
+    fs::create_dir(dir.join("sub")).unwrap();
+
+    // shell aliases run from the top of the work tree, with the remaining arguments
+    let output = flux(&dir.join("sub"), &["--repo-path", "..", "where"]);
+    assert_eq!(
+        Path::new(stdout(&output).trim()).canonicalize().unwrap(),
+        dir.canonicalize().unwrap()
+    );
+
+    let output = flux(dir, &["-c", "alias.say=!echo", "say", "hello", "world"]);
+    assert_eq!(stdout(&output), "hello world\n");
+
+    assert_eq!(flux(dir, &["fail"]).status.code(), Some(3));
+}
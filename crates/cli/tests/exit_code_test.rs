@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn flux(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_flux"))
+        .args(args)
+        .current_dir(dir)
+        .env("FLUX_CONFIG_NOSYSTEM", "1")
+        .env("FLUX_CONFIG_GLOBAL", dir.join(".fluxconfig"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn exit_code_test() {
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path();
+
+    let output = flux(dir, &["log"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error: Not a repository"));
+
+    flux(dir, &["init"]);
+    fs::write(dir.join("file.txt"), "content").unwrap();
+    flux(dir, &["add", "file.txt"]);
+    assert_eq!(
+        flux(dir, &["commit", "-m", "First"]).status.code(),
+        Some(16)
+    );
+
+    flux(dir, &["set", "user.name", "Exit User"]);
+    flux(dir, &["set", "user.email", "exit@example.com"]);
+    assert_eq!(flux(dir, &["commit", "-m", "First"]).status.code(), Some(0));
+    assert_eq!(
+        flux(dir, &["commit", "-m", "Again"]).status.code(),
+        Some(11)
+    );
+    assert_eq!(flux(dir, &["branch", "new", "main"]).status.code(), Some(8));
+    assert_eq!(
+        flux(dir, &["branch", "switch", "nope"]).status.code(),
+        Some(7)
+    );
+    assert_eq!(flux(dir, &["get", "core.editor"]).status.code(), Some(14));
+    assert_eq!(flux(dir, &["reset", "nowhere"]).status.code(), Some(6));
+
+    // usage errors keep clap's code
+    assert_eq!(flux(dir, &["commit"]).status.code(), Some(2));
+}
@@ -1,25 +1,67 @@
+use crate::diff::Hunk;
+use crate::error::{Error, Result, bail};
+use crate::objects::commit;
+use crate::repo::config::{Config, ConfigScope};
+use crate::repo::stash::parse_stash_index;
 use crate::repo::{branch::Branch, repository::Repository};
+use crate::shared::types::apply_outcome::ApplyOutcome;
+use crate::shared::types::reset_mode::ResetMode;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
 
-pub fn set(repo_path: Option<String>, key: String, value: String) -> anyhow::Result<()> {
+pub fn clone(source: String, directory: Option<String>) -> Result<()> {
+    let repository = Repository::clone_from(&source, directory.as_deref())?;
+    println!("Cloned into {:?}", repository.work_tree);
+    Ok(())
+}
+
+pub fn set(repo_path: Option<String>, key: String, value: String) -> Result<()> {
     let mut repository = Repository::open(repo_path)?;
     repository.set(key, value)?;
     Ok(())
 }
 
-pub fn cat_file(repo_path: Option<String>, hash: String) -> anyhow::Result<()> {
+/// Sets a value in the global config file (`~/.fluxconfig`), no repository needed.
+pub fn set_global(key: String, value: String) -> Result<()> {
+    Config::global()?.set_in(ConfigScope::Global, &key, &value)
+}
+
+pub fn unset(repo_path: Option<String>, key: String, global: bool) -> Result<()> {
+    if global {
+        return Config::global()?.unset_in(ConfigScope::Global, &key);
+    }
+    let mut repository = Repository::open(repo_path)?;
+    repository.unset(&key)
+}
+
+/// Prints the value `key` has for the repository, or outside of one the global value.
+pub fn get(repo_path: Option<String>, key: String) -> Result<()> {
+    let config = match Repository::open(repo_path) {
+        Ok(repository) => repository.config,
+        Err(_) => Config::global()?,
+    };
+    let value = config
+        .get_str(&key)
+        .ok_or_else(|| Error::ConfigMissing(key.clone()))?;
+    println!("{value}");
+    Ok(())
+}
+
+pub fn cat_file(repo_path: Option<String>, hash: String) -> Result<()> {
     let repository = Repository::open(repo_path)?;
     repository.cat_file(&hash)?;
     Ok(())
 }
 
-pub fn hash_object(repo_path: Option<String>, path: String, write: bool) -> anyhow::Result<String> {
+pub fn hash_object(repo_path: Option<String>, path: String, write: bool) -> Result<String> {
     let repository = Repository::open(repo_path)?;
     let hash = repository.hash_object(path, write)?;
     println!("{hash}");
     Ok(hash)
 }
 
-pub fn ls_tree(repo_path: Option<String>, hash: String) -> anyhow::Result<()> {
+pub fn ls_tree(repo_path: Option<String>, hash: String) -> Result<()> {
     let repository = Repository::open(repo_path)?;
     repository.ls_tree(&hash)?;
     Ok(())
@@ -30,77 +72,621 @@ pub fn commit_tree(
     tree_hash: String,
     message: String,
     parent_hash: Option<String>,
-) -> anyhow::Result<()> {
+) -> Result<()> {
     let repository = Repository::open(repo_path)?;
     let hash = repository.commit_tree(tree_hash, message, parent_hash)?;
     println!("{hash}");
     Ok(())
 }
 
-pub fn add(repo_path: Option<String>, path: String) -> anyhow::Result<()> {
+pub fn add(repo_path: Option<String>, paths: Vec<String>) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    repository.add(&paths)?;
+    println!("Added {} to index", paths.join(" "));
+    Ok(())
+}
+
+/// Stages the modifications and deletions of tracked files, and new files as well
+/// when `all` is set. Without paths the whole working tree is considered.
+pub fn add_update(repo_path: Option<String>, paths: Vec<String>, all: bool) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    if all {
+        repository.add(&paths)?;
+    } else {
+        repository.add_tracked(&paths)?;
+    }
+    Ok(())
+}
+
+/// Asks about each hunk of the modified files selected by `paths`, and stages the
+/// accepted ones.
+pub fn add_patch(repo_path: Option<String>, paths: Vec<String>) -> Result<()> {
+    add_patch_with(repo_path, paths, &mut io::stdin().lock(), &mut io::stdout())
+}
+
+/// [`add_patch`] reading the answers from `input` and writing the hunks to `output`.
+pub fn add_patch_with(
+    repo_path: Option<String>,
+    paths: Vec<String>,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let pathspec = repository.pathspec(&paths)?;
+    let patches = repository.unstaged_patches(&pathspec)?;
+    if patches.is_empty() {
+        writeln!(output, "No changes.")?;
+        return Ok(());
+    }
+
+    for patch in &patches {
+        let (old, new) = (patch.old_lines(), patch.new_lines());
+        let path = &patch.path;
+        write!(
+            output,
+            "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n"
+        )?;
+
+        let mut queue: VecDeque<Hunk> = patch.hunks.iter().cloned().collect();
+        let mut edits = Vec::new();
+        let mut quit = false;
+        while let Some(hunk) = queue.pop_front() {
+            let parts = hunk.split();
+            let options = if parts.len() > 1 {
+                "y,n,s,e,q,?"
+            } else {
+                "y,n,e,q,?"
+            };
+            write!(
+                output,
+                "{}Stage this hunk [{options}]? ",
+                hunk.render(&old, &new)
+            )?;
+            output.flush()?;
+
+            let mut answer = String::new();
+            if input.read_line(&mut answer)? == 0 {
+                quit = true;
+                break;
+            }
+            match answer.trim() {
+                "y" => edits.extend(hunk.edits(&new)),
+                "n" => {}
+                "q" => {
+                    quit = true;
+                    break;
+                }
+                "s" if parts.len() > 1 => {
+                    writeln!(output, "Split into {} hunks.", parts.len())?;
+                    for part in parts.into_iter().rev() {
+                        queue.push_front(part);
+                    }
+                }
+                "e" => {
+                    let text = format!("{}{EDIT_HELP}", hunk.render(&old, &new));
+                    let edited = repository.edit_text("ADD_EDIT.patch", &text)?;
+                    match hunk.parse_edited(&old, &edited) {
+                        Some(hunk_edits) => edits.extend(hunk_edits),
+                        None => {
+                            writeln!(output, "Your edited hunk does not apply.")?;
+                            queue.push_front(hunk);
+                        }
+                    }
+                }
+                _ => {
+                    write!(output, "{PATCH_HELP}")?;
+                    queue.push_front(hunk);
+                }
+            }
+        }
+
+        if !edits.is_empty() {
+            repository.stage_edits(patch, &edits)?;
+        }
+        if quit {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+const PATCH_HELP: &str = "\
+y - stage this hunk
+n - do not stage this hunk
+s - split the hunk into smaller hunks
+e - edit the hunk by hand
+q - quit, staging the hunks accepted so far
+? - print help
+";
+
+const EDIT_HELP: &str = "\
+# To remove '-' lines, make them ' ' lines (context).
+# To remove '+' lines, delete them.
+# Lines starting with # will be removed.
+";
+
+pub fn rm(repo_path: Option<String>, paths: Vec<String>, cached: bool, force: bool) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    for path in repository.remove_files(&paths, cached, force)? {
+        println!("rm '{path}'");
+    }
+    Ok(())
+}
+
+pub fn mv(
+    repo_path: Option<String>,
+    sources: Vec<String>,
+    destination: String,
+    force: bool,
+) -> Result<()> {
     let mut repository = Repository::open(repo_path)?;
-    repository.add(&path)?;
-    println!("Added {path} to index");
+    for (from, to) in repository.move_paths(&sources, &destination, force)? {
+        println!("Renamed {from} to {to}");
+    }
     Ok(())
 }
 
-pub fn remove(repo_path: Option<String>, path: String) -> anyhow::Result<()> {
+pub fn remove(repo_path: Option<String>, paths: Vec<String>) -> Result<()> {
     let mut repository = Repository::open(repo_path)?;
-    repository.delete(&path)?;
-    println!("Deleted {path} from index");
+    repository.delete(&paths)?;
+    println!("Deleted {} from index", paths.join(" "));
+    Ok(())
+}
+
+/// Prints the changed and untracked files in git's short format, relative to the
+/// current directory.
+pub fn status(repo_path: Option<String>, paths: Vec<String>) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    let pathspec = repository.pathspec(&paths)?;
+    for status in repository.status(&pathspec)? {
+        println!(
+            "{} {}",
+            status.short(),
+            repository.relative_path(&status.path)
+        );
+    }
+    Ok(())
+}
+
+/// Removes the untracked files selected by `paths`, or only lists them with
+/// `dry_run`. Unless `clean.requireForce` is turned off, `force` is needed to remove.
+pub fn clean(
+    repo_path: Option<String>,
+    paths: Vec<String>,
+    dry_run: bool,
+    force: bool,
+    directories: bool,
+    ignored: bool,
+) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    let pathspec = repository.pathspec(&paths)?;
+    let display = |path: &str| match path.strip_suffix('/') {
+        Some(dir) => format!("{}/", repository.relative_path(dir)),
+        None => repository.relative_path(path),
+    };
+    if dry_run {
+        for path in repository.untracked_paths(&pathspec, directories, ignored)? {
+            println!("Would remove {}", display(&path));
+        }
+        return Ok(());
+    }
+
+    let require_force = repository
+        .config
+        .get_bool("clean.requireForce")?
+        .unwrap_or(true);
+    if require_force && !force {
+        bail!("clean.requireForce is true; refusing to clean without -n or -f");
+    }
+    for path in repository.clean(&pathspec, directories, ignored)? {
+        println!("Removing {}", display(&path));
+    }
     Ok(())
 }
 
-pub fn write_index(repo_path: Option<String>) -> anyhow::Result<()> {
+pub fn diff(repo_path: Option<String>, cached: bool, paths: Vec<String>) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    let pathspec = repository.pathspec(&paths)?;
+    print!("{}", repository.diff(cached, &pathspec)?);
+    Ok(())
+}
+
+pub fn write_index(repo_path: Option<String>) -> Result<()> {
     let repository = Repository::open(repo_path)?;
     let hash = repository.tree_from_index()?;
     println!("{hash}");
     Ok(())
 }
 
-pub fn commit(repo_path: Option<String>, message: String) -> anyhow::Result<String> {
+/// Replaces the HEAD commit with one of the current index. Without `message` the
+/// previous one is opened in the editor, or kept as is with `no_edit`. The author is
+/// kept unless `author` is given, or `reset_author` makes it the configured user.
+pub fn commit_amend(
+    repo_path: Option<String>,
+    message: Option<String>,
+    no_edit: bool,
+    author: Option<String>,
+    reset_author: bool,
+) -> Result<String> {
+    let mut repository = Repository::open(repo_path)?;
+    let message = match message {
+        Some(message) => Some(message),
+        None if no_edit => None,
+        None => {
+            let head = repository.resolve_revision("HEAD")?;
+            let previous = commit::parse_commit(&repository.store_dir, &head)?.message;
+            Some(repository.edit_message(&previous)?)
+        }
+    };
+    let author = match author {
+        Some(author) => Some(repository.config.author(Some(&author))?),
+        None if reset_author => Some(repository.config.author(None)?),
+        None => None,
+    };
+
+    let hash = repository.amend(message, author)?;
+    println!("{hash}");
+    Ok(hash)
+}
+
+pub fn commit(
+    repo_path: Option<String>,
+    message: String,
+    author: Option<String>,
+) -> Result<String> {
     let mut repository = Repository::open(repo_path)?;
-    let hash = repository.commit(message)?;
+    let hash = match author {
+        Some(author) => repository.commit_by(&author, message)?,
+        None => repository.commit(message)?,
+    };
     println!("{hash}");
     Ok(hash)
 }
 
-pub fn log(repo_path: Option<String>) -> anyhow::Result<()> {
+pub fn reset(
+    repo_path: Option<String>,
+    mode: ResetMode,
+    revision: Option<String>,
+    paths: Vec<String>,
+) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+
+    if !paths.is_empty() {
+        repository.reset_paths(revision.as_deref(), &paths)?;
+        return Ok(());
+    }
+
+    let hash = repository.reset(mode, revision.as_deref())?;
+    println!("HEAD is now at {}", &hash[..7]);
+    Ok(())
+}
+
+pub fn restore(
+    repo_path: Option<String>,
+    source: Option<String>,
+    staged: bool,
+    worktree: bool,
+    paths: Vec<String>,
+) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    repository.restore(source.as_deref(), staged, worktree, &paths)?;
+    Ok(())
+}
+
+pub fn cherry_pick(
+    repo_path: Option<String>,
+    revision: Option<String>,
+    cont: bool,
+    abort: bool,
+) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+
+    if abort {
+        return repository.pick_abort();
+    }
+    if cont {
+        let hash = repository.pick_continue()?;
+        println!("{hash}");
+        return Ok(());
+    }
+
+    let revision = revision.unwrap_or_default();
+    let outcome = repository.cherry_pick(&revision)?;
+    report_outcome(outcome, &revision, "cherry-pick")
+}
+
+pub fn revert(
+    repo_path: Option<String>,
+    revision: Option<String>,
+    cont: bool,
+    abort: bool,
+) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+
+    if abort {
+        return repository.pick_abort();
+    }
+    if cont {
+        let hash = repository.pick_continue()?;
+        println!("{hash}");
+        return Ok(());
+    }
+
+    let revision = revision.unwrap_or_default();
+    let outcome = repository.revert(&revision)?;
+    report_outcome(outcome, &revision, "revert")
+}
+
+fn report_outcome(outcome: ApplyOutcome, revision: &str, command: &str) -> Result<()> {
+    match outcome {
+        ApplyOutcome::Committed(hash) => {
+            println!("{hash}");
+            Ok(())
+        }
+        ApplyOutcome::Conflicts(paths) => {
+            for path in paths {
+                println!("CONFLICT in {path}");
+            }
+            Err(Error::Conflict(format!(
+                "Could not apply {revision}. Resolve the conflicts, add the files and run `flux {command} --continue`"
+            )))
+        }
+    }
+}
+
+pub fn rebase(repo_path: Option<String>, upstream: String, onto: Option<String>) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let outcome = repository.rebase(&upstream, onto.as_deref())?;
+    report_rebase(outcome)
+}
+
+pub fn rebase_continue(repo_path: Option<String>) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let outcome = repository.rebase_continue()?;
+    report_rebase(outcome)
+}
+
+pub fn rebase_skip(repo_path: Option<String>) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let outcome = repository.rebase_skip()?;
+    report_rebase(outcome)
+}
+
+pub fn rebase_abort(repo_path: Option<String>) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    repository.rebase_abort()
+}
+
+fn report_rebase(outcome: ApplyOutcome) -> Result<()> {
+    match outcome {
+        ApplyOutcome::Committed(hash) => {
+            println!("Successfully rebased, HEAD is now at {}", &hash[..7]);
+            Ok(())
+        }
+        ApplyOutcome::Conflicts(paths) => {
+            for path in paths {
+                println!("CONFLICT in {path}");
+            }
+            Err(Error::Conflict(
+                "Resolve the conflicts, add the files and run `flux rebase --continue`, or use --skip / --abort"
+                    .to_string(),
+            ))
+        }
+    }
+}
+
+pub fn add_remote(repo_path: Option<String>, name: String, url: String) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    repository.add_remote(&name, &url)
+}
+
+pub fn remove_remote(repo_path: Option<String>, name: String) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    repository.remove_remote(&name)
+}
+
+pub fn list_remotes(repo_path: Option<String>) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    for (name, url) in repository.list_remotes() {
+        println!("{name}\t{url}");
+    }
+    Ok(())
+}
+
+pub fn fetch(repo_path: Option<String>, remote: String) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    for line in repository.fetch(&remote)? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+pub fn push(repo_path: Option<String>, remote: String, branch: String, force: bool) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    println!("{}", repository.push(&remote, &branch, force)?);
+    Ok(())
+}
+
+pub fn stash_push(
+    repo_path: Option<String>,
+    message: Option<String>,
+    include_untracked: bool,
+) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let message = repository.stash_push(message.as_deref(), include_untracked)?;
+    println!("Saved working directory and index state {message}");
+    Ok(())
+}
+
+pub fn stash_list(repo_path: Option<String>) -> Result<()> {
     let repository = Repository::open(repo_path)?;
-    repository.log(None)?;
+    for line in repository.stash_list()? {
+        println!("{line}");
+    }
     Ok(())
 }
 
-pub fn split(repo_path: Option<String>, name: String) -> anyhow::Result<()> {
+pub fn stash_show(repo_path: Option<String>, stash: Option<String>) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    print!(
+        "{}",
+        repository.stash_show(parse_stash_index(stash.as_deref())?)?
+    );
+    Ok(())
+}
+
+/// Applies a stash; `pop` also drops it once it applied without conflicts.
+pub fn stash_apply(repo_path: Option<String>, stash: Option<String>, pop: bool) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let n = parse_stash_index(stash.as_deref())?;
+    let conflicts = if pop {
+        repository.stash_pop(n)?
+    } else {
+        repository.stash_apply(n)?
+    };
+
+    if !conflicts.is_empty() {
+        for path in conflicts {
+            println!("CONFLICT in {path}");
+        }
+        bail!(
+            Error::Conflict,
+            "Resolve the conflicts by hand, the stash was kept"
+        );
+    }
+    Ok(())
+}
+
+pub fn stash_drop(repo_path: Option<String>, stash: Option<String>) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let n = parse_stash_index(stash.as_deref())?;
+    let hash = repository.stash_drop(n)?;
+    println!("Dropped stash@{{{n}}} ({hash})");
+    Ok(())
+}
+
+pub fn bundle_create(
+    repo_path: Option<String>,
+    file: String,
+    revisions: Vec<String>,
+) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    let count = repository.bundle_create(Path::new(&file), &revisions)?;
+    println!("Wrote {count} objects to {file}");
+    Ok(())
+}
+
+pub fn unbundle(repo_path: Option<String>, file: String) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    for (name, hash) in repository.unbundle(Path::new(&file))? {
+        println!("{hash} {name}");
+    }
+    Ok(())
+}
+
+pub fn fast_export(repo_path: Option<String>) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    let stream = repository.fast_export()?;
+    io::stdout().write_all(&stream)?;
+    Ok(())
+}
+
+pub fn fast_import(repo_path: Option<String>) -> Result<()> {
+    let mut repository = Repository::open(repo_path)?;
+    let mut stream = Vec::new();
+    io::stdin().read_to_end(&mut stream)?;
+
+    for (name, hash) in repository.fast_import(&stream)? {
+        println!("{} {name}", &hash[..7]);
+    }
+    Ok(())
+}
+
+pub fn log(
+    repo_path: Option<String>,
+    mut revisions: Vec<String>,
+    paths: Vec<String>,
+    all: bool,
+    graph: bool,
+) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    if all {
+        revisions.push("--all".to_string());
+    }
+    if graph {
+        if !paths.is_empty() {
+            bail!("log --graph cannot be limited to paths");
+        }
+        println!("{}", repository.log_graph(&revisions)?);
+    } else {
+        repository.log(&revisions, &repository.pathspec(&paths)?)?;
+    }
+    Ok(())
+}
+
+pub fn blame(
+    repo_path: Option<String>,
+    path: String,
+    revision: Option<String>,
+    porcelain: bool,
+) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    let blame = repository.blame(&path, revision.as_deref())?;
+    if porcelain {
+        print!("{}", blame.porcelain());
+    } else {
+        print!("{}", blame.format());
+    }
+    Ok(())
+}
+
+pub fn split(repo_path: Option<String>, name: String) -> Result<()> {
     let mut repository = Repository::open(repo_path)?;
     repository.new_branch(&name)?;
     Ok(())
 }
 
-pub fn show_branches(repo_path: Option<String>) -> anyhow::Result<()> {
+pub fn show_branches(repo_path: Option<String>, all: bool, prefix: Option<String>) -> Result<()> {
     let repository = Repository::open(repo_path)?;
-    let output = repository.show_branches()?;
+    let output = repository.show_branches(all, prefix.as_deref())?;
     println!("{output}");
     Ok(())
 }
 
-pub fn create_branch(repo_path: Option<String>, name: String) -> anyhow::Result<()> {
+pub fn pack_refs(repo_path: Option<String>) -> Result<()> {
+    let repository = Repository::open(repo_path)?;
+    repository.pack_refs()?;
+    Ok(())
+}
+
+pub fn create_branch(repo_path: Option<String>, name: String) -> Result<()> {
     let mut repository = Repository::open(repo_path)?;
     repository.new_branch(&name)?;
     Ok(())
 }
 
-pub fn delete_branch(_repo_path: Option<String>, _name: String) -> anyhow::Result<()> {
+pub fn delete_branch(_repo_path: Option<String>, _name: String) -> Result<()> {
     todo!()
 }
 
-pub fn switch_branch(repo_path: Option<String>, name: String, force: bool) -> anyhow::Result<()> {
+pub fn switch_branch(repo_path: Option<String>, name: String, force: bool) -> Result<()> {
     let mut repository = Repository::open(repo_path)?;
     repository.switch_branch(&name, force)?;
     Ok(())
 }
 
-pub fn get_branches(repo_path: Option<String>) -> anyhow::Result<Vec<Branch>> {
+pub fn get_branches(repo_path: Option<String>) -> Result<Vec<Branch>> {
     let repository = Repository::open(repo_path)?;
     Ok(repository.branches)
 }
+
+pub fn get_tags(repo_path: Option<String>) -> Result<Vec<String>> {
+    let repository = Repository::open(repo_path)?;
+    repository.list_refs("refs/tags/", None)
+}
+
+pub fn get_remote_branches(repo_path: Option<String>) -> Result<Vec<String>> {
+    let repository = Repository::open(repo_path)?;
+    repository.list_refs("refs/remotes/", None)
+}
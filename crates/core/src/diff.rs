@@ -0,0 +1,522 @@
+/// A single step of an edit script turning `old` into `new`.
+/// Indices refer to lines of the respective sides.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffOp {
+    Equal { old: usize, new: usize },
+    Delete { old: usize },
+    Insert { new: usize },
+}
+
+/// A group of changes together with the surrounding context lines.
+/// Ranges are half-open line ranges on each side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub ops: Vec<DiffOp>,
+}
+
+/// Splits content into lines, keeping the line terminators so the lines can be joined back verbatim.
+pub fn split_lines(content: &str) -> Vec<&str> {
+    content.split_inclusive('\n').collect()
+}
+
+/// Computes a shortest edit script between two sequences of lines (Myers' algorithm).
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    // Common prefix and suffix are cheap to strip and keep the search space small.
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<DiffOp> = (0..prefix)
+        .map(|i| DiffOp::Equal { old: i, new: i })
+        .collect();
+
+    for op in myers(old_mid, new_mid) {
+        ops.push(match op {
+            DiffOp::Equal { old, new } => DiffOp::Equal {
+                old: old + prefix,
+                new: new + prefix,
+            },
+            DiffOp::Delete { old } => DiffOp::Delete { old: old + prefix },
+            DiffOp::Insert { new } => DiffOp::Insert { new: new + prefix },
+        });
+    }
+
+    for i in 0..suffix {
+        ops.push(DiffOp::Equal {
+            old: old.len() - suffix + i,
+            new: new.len() - suffix + i,
+        });
+    }
+
+    ops
+}
+
+/// Myers' algorithm in linear space: the middle snake of the edit graph splits the
+/// problem in two halves, which are solved recursively. Only the furthest reaching
+/// paths of the current step are kept, so memory stays proportional to the input.
+fn myers<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, 0, 0, &mut ops);
+
+    // Within a block of changes, list the deletions before the insertions.
+    let mut start = 0;
+    while start < ops.len() {
+        if matches!(ops[start], DiffOp::Equal { .. }) {
+            start += 1;
+            continue;
+        }
+        let end = ops[start..]
+            .iter()
+            .position(|op| matches!(op, DiffOp::Equal { .. }))
+            .map_or(ops.len(), |len| start + len);
+        ops[start..end].sort_by_key(|op| matches!(op, DiffOp::Insert { .. }));
+        start = end;
+    }
+    ops
+}
+
+/// Appends the ops turning `old` into `new`, whose first lines are at `old_base` and
+/// `new_base` on each side.
+fn diff_range<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    old_base: usize,
+    new_base: usize,
+    ops: &mut Vec<DiffOp>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    ops.extend((0..prefix).map(|i| DiffOp::Equal {
+        old: old_base + i,
+        new: new_base + i,
+    }));
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let (old_base, new_base) = (old_base + prefix, new_base + prefix);
+
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    if old.is_empty() || new.is_empty() {
+        ops.extend((0..old.len()).map(|i| DiffOp::Delete { old: old_base + i }));
+        ops.extend((0..new.len()).map(|i| DiffOp::Insert { new: new_base + i }));
+    } else if let Some((x, y)) = middle_snake(old, new) {
+        diff_range(&old[..x], &new[..y], old_base, new_base, ops);
+        diff_range(&old[x..], &new[y..], old_base + x, new_base + y, ops);
+    } else {
+        ops.extend((0..old.len()).map(|i| DiffOp::Delete { old: old_base + i }));
+        ops.extend((0..new.len()).map(|i| DiffOp::Insert { new: new_base + i }));
+    }
+
+    ops.extend((0..suffix).map(|i| DiffOp::Equal {
+        old: old_base + old.len() + i,
+        new: new_base + new.len() + i,
+    }));
+}
+
+/// A point of a shortest edit path between `old` and `new`, found by searching
+/// forwards from the start and backwards from the end until the paths overlap.
+/// `None` when the sides have nothing in common.
+fn middle_snake<T: PartialEq>(old: &[T], new: &[T]) -> Option<(usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d;
+    let len = 2 * max_d + 2;
+    let mut forward = vec![-1isize; len as usize];
+    let mut backward = vec![-1isize; len as usize];
+    forward[(offset + 1) as usize] = 0;
+    backward[(offset + 1) as usize] = 0;
+
+    let delta = n - m;
+    // With an odd delta the paths meet while extending forwards, otherwise backwards.
+    let front = delta % 2 != 0;
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+
+    for d in 0..max_d {
+        let mut k1 = -d + k1_start;
+        while k1 <= d - k1_end {
+            let i = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && old[x1 as usize] == new[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[i] = x1;
+
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let j = offset + delta - k1;
+                if (0..len).contains(&j) && backward[j as usize] != -1 {
+                    let x2 = n - backward[j as usize];
+                    if x1 >= x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k1 += 2;
+        }
+
+        let mut k2 = -d + k2_start;
+        while k2 <= d - k2_end {
+            let i = (offset + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && backward[i - 1] < backward[i + 1]) {
+                backward[i + 1]
+            } else {
+                backward[i - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && old[(n - x2 - 1) as usize] == new[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            backward[i] = x2;
+
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let j = offset + delta - k2;
+                if (0..len).contains(&j) && forward[j as usize] != -1 {
+                    let x1 = forward[j as usize];
+                    let y1 = offset + x1 - j;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k2 += 2;
+        }
+    }
+
+    None
+}
+
+/// For every line of `old`, the index of the line it stays as in `new` (if it is kept).
+pub fn line_matches(ops: &[DiffOp], old_len: usize) -> Vec<Option<usize>> {
+    let mut matches = vec![None; old_len];
+    for op in ops {
+        if let DiffOp::Equal { old, new } = op {
+            matches[*old] = Some(*new);
+        }
+    }
+    matches
+}
+
+/// Groups an edit script into hunks with up to `context` unchanged lines around each change.
+pub fn hunks(ops: &[DiffOp], context: usize) -> Vec<Hunk> {
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal { .. }))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(ops.len());
+        match groups.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => groups.push((start, end)),
+        }
+    }
+
+    // Line positions on both sides right before each op.
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for op in ops {
+        positions.push((old_pos, new_pos));
+        match op {
+            DiffOp::Equal { .. } => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            DiffOp::Delete { .. } => old_pos += 1,
+            DiffOp::Insert { .. } => new_pos += 1,
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(start, end)| {
+            let ops = ops[start..end].to_vec();
+            let (old_start, new_start) = positions[start];
+            let old_len = ops
+                .iter()
+                .filter(|op| !matches!(op, DiffOp::Insert { .. }))
+                .count();
+            let new_len = ops
+                .iter()
+                .filter(|op| !matches!(op, DiffOp::Delete { .. }))
+                .count();
+
+            Hunk {
+                old_start,
+                old_len,
+                new_start,
+                new_len,
+                ops,
+            }
+        })
+        .collect()
+}
+
+/// Renders the changes turning `old` into `new` as the hunks of a unified diff, with up
+/// to `context` unchanged lines around each change.
+pub fn unified(old: &str, new: &str, context: usize) -> String {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let mut res = String::new();
+
+    for hunk in hunks(&diff_lines(&old_lines, &new_lines), context) {
+        res.push_str(&hunk.render(&old_lines, &new_lines));
+    }
+    res
+}
+
+impl Hunk {
+    /// The `@@ -old +new @@` line of the hunk, with 1-based line numbers. An empty side
+    /// is numbered by the line before it, as in git.
+    pub fn header(&self) -> String {
+        let range = |start: usize, len: usize| {
+            let start = if len == 0 { start } else { start + 1 };
+            if len == 1 {
+                start.to_string()
+            } else {
+                format!("{start},{len}")
+            }
+        };
+        format!(
+            "@@ -{} +{} @@\n",
+            range(self.old_start, self.old_len),
+            range(self.new_start, self.new_len)
+        )
+    }
+}
+
+/// A replacement of `old_len` lines of the old side, starting at line `old_start`, by
+/// `lines`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub lines: Vec<String>,
+}
+
+impl Hunk {
+    /// Splits the hunk where unchanged lines separate its changes. Each part keeps the
+    /// unchanged lines around its change, so neighbouring parts share them.
+    pub fn split(&self) -> Vec<Hunk> {
+        let blocks = change_blocks(&self.ops);
+        if blocks.len() < 2 {
+            return vec![self.clone()];
+        }
+
+        let mut parts = Vec::with_capacity(blocks.len());
+        for i in 0..blocks.len() {
+            let from = if i == 0 { 0 } else { blocks[i - 1].1 };
+            let to = blocks.get(i + 1).map_or(self.ops.len(), |next| next.0);
+            let (old_before, new_before) = counts(&self.ops[..from]);
+            let ops = self.ops[from..to].to_vec();
+            let (old_len, new_len) = counts(&ops);
+            parts.push(Hunk {
+                old_start: self.old_start + old_before,
+                old_len,
+                new_start: self.new_start + new_before,
+                new_len,
+                ops,
+            });
+        }
+        parts
+    }
+
+    /// The changes of the hunk as edits of the old side, `new` being the lines of the
+    /// new side the ops refer to.
+    pub fn edits(&self, new: &[&str]) -> Vec<Edit> {
+        let mut edits = Vec::new();
+        let mut old_pos = self.old_start;
+        let mut current: Option<Edit> = None;
+
+        for op in &self.ops {
+            match *op {
+                DiffOp::Equal { .. } => {
+                    edits.extend(current.take());
+                    old_pos += 1;
+                }
+                DiffOp::Delete { .. } => {
+                    current.get_or_insert_with(|| Edit::at(old_pos)).old_len += 1;
+                    old_pos += 1;
+                }
+                DiffOp::Insert { new: line } => current
+                    .get_or_insert_with(|| Edit::at(old_pos))
+                    .lines
+                    .push(new[line].to_string()),
+            }
+        }
+        edits.extend(current);
+        edits
+    }
+
+    /// The hunk in the unified format, header included.
+    pub fn render(&self, old: &[&str], new: &[&str]) -> String {
+        let mut res = self.header();
+        for op in &self.ops {
+            let (marker, line) = match *op {
+                DiffOp::Equal { old: line, .. } => (' ', old[line]),
+                DiffOp::Delete { old: line } => ('-', old[line]),
+                DiffOp::Insert { new: line } => ('+', new[line]),
+            };
+            res.push(marker);
+            res.push_str(line);
+            if !line.ends_with('\n') {
+                res.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        res
+    }
+
+    /// The edits made by a version of this hunk that was changed by hand: the ` ` and
+    /// `-` lines must still be the old lines of the hunk, `+` and ` ` lines make the new
+    /// side. Lines starting with `#` are ignored. `None` when the old side was changed.
+    pub fn parse_edited(&self, old: &[&str], text: &str) -> Option<Vec<Edit>> {
+        let mut old_side: Vec<String> = Vec::new();
+        let mut new_side: Vec<String> = Vec::new();
+        let mut previous = "";
+        for line in text.split_inclusive('\n') {
+            let (marker, content) = line.split_at(line.len().min(1));
+            let (to_old, to_new) = match marker {
+                " " => (true, true),
+                "-" => (true, false),
+                "+" => (false, true),
+                // an empty line stands for an empty context line, as editors strip spaces
+                "\n" => {
+                    old_side.push("\n".to_string());
+                    new_side.push("\n".to_string());
+                    previous = " ";
+                    continue;
+                }
+                // `\ No newline at end of file` applies to the previous line
+                "\\" => {
+                    let (to_old, to_new) = (previous != "+", previous != "-");
+                    for (side, applies) in [(&mut old_side, to_old), (&mut new_side, to_new)] {
+                        if let Some(last) = side.last_mut().filter(|_| applies)
+                            && last.ends_with('\n')
+                        {
+                            last.pop();
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if to_old {
+                old_side.push(content.to_string());
+            }
+            if to_new {
+                new_side.push(content.to_string());
+            }
+            previous = marker;
+        }
+
+        let original = &old[self.old_start..self.old_start + self.old_len];
+        if old_side.len() != original.len() || old_side.iter().zip(original).any(|(a, b)| a != b) {
+            return None;
+        }
+
+        let new_lines: Vec<&str> = new_side.iter().map(String::as_str).collect();
+        // `edits` only needs the position of the hunk and the new lines.
+        let ops = diff_lines(original, &new_lines);
+        let (old_len, new_len) = counts(&ops);
+        let edited = Hunk {
+            old_start: self.old_start,
+            old_len,
+            new_start: self.new_start,
+            new_len,
+            ops,
+        };
+        Some(edited.edits(&new_lines))
+    }
+}
+
+impl Edit {
+    fn at(old_start: usize) -> Self {
+        Edit {
+            old_start,
+            old_len: 0,
+            lines: Vec::new(),
+        }
+    }
+}
+
+/// `old` with the `edits`, which must not overlap, applied.
+pub fn apply_edits(old: &[&str], edits: &[Edit]) -> String {
+    let mut edits: Vec<&Edit> = edits.iter().collect();
+    edits.sort_by_key(|edit| edit.old_start);
+
+    let mut res = String::new();
+    let mut pos = 0;
+    for edit in edits {
+        for line in &old[pos..edit.old_start.max(pos)] {
+            res.push_str(line);
+        }
+        for line in &edit.lines {
+            res.push_str(line);
+        }
+        pos = pos.max(edit.old_start + edit.old_len);
+    }
+    for line in &old[pos.min(old.len())..] {
+        res.push_str(line);
+    }
+    res
+}
+
+/// The `(start, end)` op ranges of the runs of changes.
+fn change_blocks(ops: &[DiffOp]) -> Vec<(usize, usize)> {
+    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        if matches!(op, DiffOp::Equal { .. }) {
+            continue;
+        }
+        match blocks.last_mut() {
+            Some(last) if last.1 == i => last.1 = i + 1,
+            _ => blocks.push((i, i + 1)),
+        }
+    }
+    blocks
+}
+
+/// The number of old and new lines the ops span.
+fn counts(ops: &[DiffOp]) -> (usize, usize) {
+    let old = ops
+        .iter()
+        .filter(|op| !matches!(op, DiffOp::Insert { .. }))
+        .count();
+    let new = ops
+        .iter()
+        .filter(|op| !matches!(op, DiffOp::Delete { .. }))
+        .count();
+    (old, new)
+}
@@ -0,0 +1,255 @@
+use crate::repo::identity::IdentityError;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong in flux. Callers that need to tell failures apart
+/// should look at [`Error::root`], since most errors get wrapped in a
+/// [`Error::Context`] on their way up.
+#[derive(Debug)]
+pub enum Error {
+    /// Neither `.flux` nor a git repository was found at the path or above it.
+    NotARepository(PathBuf),
+    ObjectNotFound(String),
+    /// An object, pack or index that cannot be read back.
+    CorruptObject {
+        object: String,
+        reason: String,
+    },
+    /// A revision that names no commit.
+    RevisionNotFound(String),
+    /// A branch, tag or other reference, by its full name, that does not exist.
+    RefNotFound(String),
+    /// A reference, by its full name, that already exists and would be overwritten.
+    RefExists(String),
+    /// A reference that cannot be created because `existing` is a directory of it,
+    /// or it is one of `existing`.
+    RefConflict {
+        name: String,
+        existing: String,
+    },
+    /// Local changes that an operation would lose.
+    DirtyWorkTree(String),
+    NothingToCommit,
+    /// A merge stopped on conflicts that have to be resolved by hand.
+    Conflict(String),
+    /// A rebase, cherry-pick or revert is in progress, or should be and is not.
+    OperationInProgress(String),
+    ConfigMissing(String),
+    InvalidConfig(String),
+    Identity(IdentityError),
+    /// A remote that cannot be reached or does not answer as expected.
+    Remote(String),
+    Io(io::Error),
+    /// A message describing what was being done when `source` happened.
+    Context {
+        message: String,
+        source: Box<Error>,
+    },
+    Other(String),
+}
+
+impl Error {
+    /// The error underneath every [`Error::Context`].
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// A stable name for the kind of the root error, for the desktop app.
+    pub fn kind(&self) -> &'static str {
+        match self.root() {
+            Error::NotARepository(_) => "not_a_repository",
+            Error::ObjectNotFound(_) => "object_not_found",
+            Error::CorruptObject { .. } => "corrupt_object",
+            Error::RevisionNotFound(_) => "revision_not_found",
+            Error::RefNotFound(_) => "ref_not_found",
+            Error::RefExists(_) => "ref_exists",
+            Error::RefConflict { .. } => "ref_conflict",
+            Error::DirtyWorkTree(_) => "dirty_work_tree",
+            Error::NothingToCommit => "nothing_to_commit",
+            Error::Conflict(_) => "conflict",
+            Error::OperationInProgress(_) => "operation_in_progress",
+            Error::ConfigMissing(_) => "config_missing",
+            Error::InvalidConfig(_) => "invalid_config",
+            Error::Identity(_) => "identity",
+            Error::Remote(_) => "remote",
+            Error::Io(_) => "io",
+            Error::Context { .. } | Error::Other(_) => "other",
+        }
+    }
+
+    /// The message of this error followed by the messages of its causes.
+    pub fn full_message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            message.push_str(&format!(": {error}"));
+            source = error.source();
+        }
+        message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotARepository(path) => {
+                write!(
+                    f,
+                    "Not a repository (or any parent up to the filesystem boundary): {}",
+                    path.display()
+                )
+            }
+            Error::ObjectNotFound(hash) => write!(f, "Object {hash} not found"),
+            Error::CorruptObject { object, reason } => write!(f, "Corrupt {object}: {reason}"),
+            Error::RevisionNotFound(revision) => write!(f, "Unknown revision '{revision}'"),
+            Error::RefNotFound(name) => write!(f, "{} does not exist", describe_ref(name)),
+            Error::RefExists(name) => write!(f, "{} already exists", describe_ref(name)),
+            Error::RefConflict { name, existing } => {
+                write!(f, "'{name}' conflicts with existing ref '{existing}'")
+            }
+            Error::NothingToCommit => write!(f, "Nothing to commit"),
+            Error::ConfigMissing(key) => write!(f, "Config key '{key}' is not set"),
+            Error::Identity(error) => write!(f, "{error}"),
+            Error::Io(error) => write!(f, "{error}"),
+            Error::DirtyWorkTree(message)
+            | Error::Conflict(message)
+            | Error::OperationInProgress(message)
+            | Error::InvalidConfig(message)
+            | Error::Remote(message)
+            | Error::Context { message, .. }
+            | Error::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// `Branch 'main'` for `refs/heads/main`, `Reference 'refs/stash'` for other refs.
+fn describe_ref(name: &str) -> String {
+    for (prefix, kind) in [
+        ("refs/heads/", "Branch"),
+        ("refs/tags/", "Tag"),
+        ("refs/remotes/", "Remote-tracking branch"),
+    ] {
+        if let Some(short) = name.strip_prefix(prefix) {
+            return format!("{kind} '{short}'");
+        }
+    }
+    format!("Reference '{name}'")
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Context { source, .. } => Some(source.as_ref()),
+            Error::Identity(error) => Some(error),
+            Error::Io(error) => error.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Serialized as `{ "kind": ..., "message": ... }` so the desktop app can react to
+/// the kind and show the message.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Error", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.full_message())?;
+        state.end()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<IdentityError> for Error {
+    fn from(error: IdentityError) -> Self {
+        Error::Identity(error)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Error::InvalidConfig(error.to_string())
+    }
+}
+
+/// Errors of parsers flux uses on data it reads, reported by their message.
+macro_rules! from_message {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::Other(error.to_string())
+                }
+            }
+        )*
+    };
+}
+
+from_message!(
+    std::array::TryFromSliceError,
+    std::env::VarError,
+    std::num::ParseIntError,
+    std::path::StripPrefixError,
+    std::str::Utf8Error,
+    std::string::FromUtf8Error,
+    hex::FromHexError,
+    json::JsonError,
+);
+
+/// Returns early with an error built from a format string: an [`Error::Other`], or
+/// the variant given first (`bail!(Error::Remote, "...")`).
+macro_rules! bail {
+    ($variant:path, $($arg:tt)+) => {
+        return Err($variant(format!($($arg)+)))
+    };
+    ($($arg:tt)*) => {
+        return Err($crate::error::Error::Other(format!($($arg)*)))
+    };
+}
+
+pub(crate) use bail;
+
+/// Adds a message saying what was being done to the error of a `Result`, or turns
+/// a `None` into an error.
+pub trait Context<T> {
+    fn context<C: fmt::Display>(self, context: C) -> Result<T>;
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, context: F) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> Context<T> for std::result::Result<T, E> {
+    fn context<C: fmt::Display>(self, context: C) -> Result<T> {
+        self.map_err(|error| Error::Context {
+            message: context.to_string(),
+            source: Box::new(error.into()),
+        })
+    }
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.map_err(|error| Error::Context {
+            message: context().to_string(),
+            source: Box::new(error.into()),
+        })
+    }
+}
+
+impl<T> Context<T> for Option<T> {
+    fn context<C: fmt::Display>(self, context: C) -> Result<T> {
+        self.ok_or_else(|| Error::Other(context.to_string()))
+    }
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, context: F) -> Result<T> {
+        self.ok_or_else(|| Error::Other(context().to_string()))
+    }
+}
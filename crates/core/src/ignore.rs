@@ -0,0 +1,138 @@
+use crate::error::Result;
+use crate::pathspec;
+use std::fs;
+use std::path::Path;
+
+/// The name of the files listing the untracked paths flux leaves alone.
+pub const IGNORE_FILE: &str = ".fluxignore";
+
+/// The rules of the `.fluxignore` files of a work tree, which use the gitignore format:
+///
+/// - blank lines and lines starting with `#` are skipped,
+/// - a pattern without a `/` matches names at any depth below its file, one with a
+///   `/` is relative to the directory of its file,
+/// - a trailing `/` only matches directories,
+/// - a leading `!` includes again what an earlier pattern ignored,
+/// - `*`, `?`, `[...]` and `**` work as in pathspecs.
+///
+/// The last matching rule wins, rules of deeper files coming after those of their
+/// parents, and everything under an ignored directory is ignored.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// The directory of the ignore file, relative to the top of the work tree.
+    base: String,
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    /// Reads the ignore files among `files`, paths relative to `work_tree`.
+    pub fn load<'a, I>(work_tree: &Path, files: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let mut ignore_files: Vec<(&str, &String)> = files
+            .into_iter()
+            .filter_map(|file| match file.rsplit_once('/') {
+                Some((base, IGNORE_FILE)) => Some((base, file)),
+                None if file == IGNORE_FILE => Some(("", file)),
+                _ => None,
+            })
+            .collect();
+        ignore_files.sort_by_key(|(_, file)| file.matches('/').count());
+
+        let mut rules = Self::default();
+        for (base, file) in ignore_files {
+            rules.add(base, &fs::read_to_string(work_tree.join(file))?);
+        }
+        Ok(rules)
+    }
+
+    /// Adds the rules of an ignore file found in the directory `base`.
+    pub fn add(&mut self, base: &str, content: &str) {
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            self.rules.push(Rule {
+                base: base.to_string(),
+                pattern: line.trim_start_matches('/').to_string(),
+                negated,
+                dir_only,
+                anchored,
+            });
+        }
+    }
+
+    /// Whether `path`, relative to the top of the work tree, is ignored.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        path.match_indices('/')
+            .any(|(i, _)| self.matches(&path[..i], true))
+            || self.matches(path, is_dir)
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl Rule {
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        let path = if self.base.is_empty() {
+            path
+        } else {
+            match path
+                .strip_prefix(&self.base)
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                Some(rest) => rest,
+                None => return false,
+            }
+        };
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        if self.anchored {
+            let pattern: Vec<&str> = self.pattern.split('/').collect();
+            let path: Vec<&str> = path.split('/').collect();
+            match_all(&pattern, &path)
+        } else {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            pathspec::match_name(&self.pattern, name)
+        }
+    }
+}
+
+/// Whether `pattern` matches all of `path`, `**` standing for any number of components.
+fn match_all(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_all(rest, &path[skip..])),
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(name, path)| pathspec::match_name(first, name) && match_all(rest, path)),
+    }
+}
@@ -1,5 +1,11 @@
 pub mod commands;
+pub mod diff;
+pub mod error;
+pub mod ignore;
+pub mod merge;
 pub mod objects;
+pub mod pathspec;
+pub mod protocol;
 pub mod repo;
 pub mod shared;
 pub mod utils;
@@ -0,0 +1,214 @@
+use crate::diff::{self, split_lines};
+use crate::error::Result;
+use crate::objects::blob;
+use crate::shared::types::index_entry::IndexEntry;
+use crate::utils;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Result of merging three versions of a file's content.
+pub struct MergedContent {
+    pub content: String,
+    pub conflicts: bool,
+}
+
+/// Result of merging three flat file listings (see `tree::flatten_tree`).
+pub struct TreeMerge {
+    /// Entries to stage. Conflicted paths keep the `ours` side (or `theirs` if ours deleted it).
+    pub files: BTreeMap<String, IndexEntry>,
+    /// Working tree content with conflict markers for the conflicted text files.
+    pub conflict_content: BTreeMap<String, Vec<u8>>,
+    pub conflicts: Vec<String>,
+}
+
+/// Three-way line merge (diff3). Regions changed on only one side are taken from that side,
+/// regions changed identically on both sides are taken once, anything else becomes a conflict.
+pub fn merge_lines(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    ours_label: &str,
+    theirs_label: &str,
+) -> MergedContent {
+    let base_lines = split_lines(base);
+    let our_lines = split_lines(ours);
+    let their_lines = split_lines(theirs);
+
+    let ours_matches =
+        diff::line_matches(&diff::diff_lines(&base_lines, &our_lines), base_lines.len());
+    let theirs_matches = diff::line_matches(
+        &diff::diff_lines(&base_lines, &their_lines),
+        base_lines.len(),
+    );
+
+    let mut content = String::new();
+    let mut conflicts = false;
+    let (mut i, mut j, mut k) = (0, 0, 0);
+
+    loop {
+        // Next base line kept by both sides: it anchors the end of the current chunk.
+        let stable = (i..base_lines.len())
+            .find(|&b| ours_matches[b].is_some() && theirs_matches[b].is_some());
+
+        if let Some(b) = stable
+            && b == i
+            && ours_matches[b] == Some(j)
+            && theirs_matches[b] == Some(k)
+        {
+            content.push_str(base_lines[i]);
+            i += 1;
+            j += 1;
+            k += 1;
+            continue;
+        }
+
+        let (b, o, t) = match stable {
+            Some(b) => (b, ours_matches[b].unwrap(), theirs_matches[b].unwrap()),
+            None => (base_lines.len(), our_lines.len(), their_lines.len()),
+        };
+
+        let base_chunk = &base_lines[i..b];
+        let our_chunk = &our_lines[j..o];
+        let their_chunk = &their_lines[k..t];
+
+        if our_chunk == base_chunk || our_chunk == their_chunk {
+            content.extend(their_chunk.iter().copied());
+        } else if their_chunk == base_chunk {
+            content.extend(our_chunk.iter().copied());
+        } else {
+            conflicts = true;
+            content.push_str(&format!("<<<<<<< {ours_label}\n"));
+            push_chunk(&mut content, our_chunk);
+            content.push_str("=======\n");
+            push_chunk(&mut content, their_chunk);
+            content.push_str(&format!(">>>>>>> {theirs_label}\n"));
+        }
+
+        if stable.is_none() {
+            break;
+        }
+        (i, j, k) = (b, o, t);
+    }
+
+    MergedContent { content, conflicts }
+}
+
+fn push_chunk(content: &mut String, lines: &[&str]) {
+    for line in lines {
+        content.push_str(line);
+    }
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+}
+
+/// Merges `ours` and `theirs` file listings against their common `base`.
+/// Merged blobs are written to the object store.
+pub fn merge_trees(
+    store_dir: &Path,
+    base: &BTreeMap<String, IndexEntry>,
+    ours: &BTreeMap<String, IndexEntry>,
+    theirs: &BTreeMap<String, IndexEntry>,
+    ours_label: &str,
+    theirs_label: &str,
+) -> Result<TreeMerge> {
+    let paths: BTreeSet<&String> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .collect();
+
+    let mut result = TreeMerge {
+        files: BTreeMap::new(),
+        conflict_content: BTreeMap::new(),
+        conflicts: Vec::new(),
+    };
+
+    for path in paths {
+        let (b, o, t) = (base.get(path), ours.get(path), theirs.get(path));
+
+        let merged = if o == t || b == t {
+            o.cloned()
+        } else if b == o {
+            t.cloned()
+        } else {
+            match (b, o, t) {
+                (_, Some(o), Some(t)) => {
+                    let base_content = match b {
+                        Some(b) => read_blob(store_dir, &b.hash)?,
+                        None => Vec::new(),
+                    };
+                    let merged =
+                        merge_blobs(store_dir, &base_content, o, t, ours_label, theirs_label)?;
+                    match merged {
+                        Ok(entry) => Some(entry),
+                        Err(content) => {
+                            result.conflicts.push(path.clone());
+                            if let Some(content) = content {
+                                result.conflict_content.insert(path.clone(), content);
+                            }
+                            Some(o.clone())
+                        }
+                    }
+                }
+                // Modified on one side, deleted on the other: keep the modified version around.
+                (_, Some(kept), None) | (_, None, Some(kept)) => {
+                    result.conflicts.push(path.clone());
+                    Some(kept.clone())
+                }
+                (_, None, None) => None,
+            }
+        };
+
+        if let Some(entry) = merged {
+            result.files.insert(path.clone(), entry);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns the merged entry, or `Err` with the conflicted content (`None` for binary files).
+fn merge_blobs(
+    store_dir: &Path,
+    base: &[u8],
+    ours: &IndexEntry,
+    theirs: &IndexEntry,
+    ours_label: &str,
+    theirs_label: &str,
+) -> Result<Result<IndexEntry, Option<Vec<u8>>>> {
+    let our_content = read_blob(store_dir, &ours.hash)?;
+    let their_content = read_blob(store_dir, &theirs.hash)?;
+
+    let (Ok(base), Ok(our_text), Ok(their_text)) = (
+        std::str::from_utf8(base),
+        std::str::from_utf8(&our_content),
+        std::str::from_utf8(&their_content),
+    ) else {
+        return Ok(Err(None));
+    };
+
+    let merged = merge_lines(base, our_text, their_text, ours_label, theirs_label);
+    if merged.conflicts {
+        return Ok(Err(Some(merged.content.into_bytes())));
+    }
+
+    let blob = blob::hash_blob(merged.content.into_bytes())?;
+    utils::store_object(store_dir, &blob.object_hash, &blob.compressed_content)?;
+
+    // A mode change on their side wins over an unchanged mode on ours.
+    let mode = if ours.mode == theirs.mode {
+        ours.mode.clone()
+    } else {
+        theirs.mode.clone()
+    };
+
+    Ok(Ok(IndexEntry {
+        hash: blob.object_hash,
+        mode,
+    }))
+}
+
+fn read_blob(store_dir: &Path, hash: &str) -> Result<Vec<u8>> {
+    Ok(utils::read_object(store_dir, hash)?.decompressed_content)
+}
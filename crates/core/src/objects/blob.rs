@@ -1,6 +1,7 @@
+use crate::error::Result;
 use crate::{shared::types::hash_result::HashResult, utils};
 
-pub fn hash_blob(content: Vec<u8>) -> anyhow::Result<HashResult> {
+pub fn hash_blob(content: Vec<u8>) -> Result<HashResult> {
     let header = format!("blob {}\0", content.len());
     let mut store = Vec::new();
     store.extend_from_slice(header.as_bytes());
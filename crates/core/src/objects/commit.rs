@@ -1,36 +1,29 @@
-use crate::{shared::{self, types::object_type::ObjectType}, utils};
-use anyhow::bail;
-use chrono::Local;
+use crate::error::{Context, Result, bail};
+use crate::shared::types::{commit_info::CommitInfo, signature::Signature};
+use crate::{
+    shared::{self, types::object_type::ObjectType},
+    utils,
+};
 use std::path::Path;
 
-pub fn commit_tree(
+/// Writes a commit object with explicit author and committer, e.g. to preserve
+/// the original author of a cherry-picked commit.
+pub fn write_commit(
     store_dir: &Path,
-    user_name: String,
-    user_email: String,
-    tree_hash: String,
-    parent_hash: Option<String>,
-    message: String,
-) -> anyhow::Result<String> {
-    let now = Local::now();
-
-    let parent_line = match parent_hash {
-        Some(h) => format!("parent {}\n", h),
-        None => String::new(),
-    };
+    tree_hash: &str,
+    parents: &[String],
+    author: &Signature,
+    committer: &Signature,
+    message: &str,
+) -> Result<String> {
+    let parent_lines: String = parents
+        .iter()
+        .map(|parent| format!("parent {}\n", parent))
+        .collect();
 
     let commit_content = format!(
-        "tree {}\n{}author {} <{}> {} {}\ncommitter {} <{}> {} {}\n\n{}",
-        tree_hash,
-        parent_line,
-        user_name,
-        user_email,
-        now.timestamp(),
-        now.format("%z"),
-        user_name,
-        user_email,
-        now.timestamp(),
-        now.format("%z"),
-        message
+        "tree {}\n{}author {}\ncommitter {}\n\n{}",
+        tree_hash, parent_lines, author, committer, message
     );
 
     let size = commit_content.len();
@@ -44,24 +37,70 @@ pub fn commit_tree(
     Ok(object_hash)
 }
 
-pub fn show_commit(store_dir: &Path, commit_hash: &str) -> anyhow::Result<()> {
-    let commit = utils::read_object(store_dir, &commit_hash)?;
+/// Reads and parses a commit object.
+pub fn parse_commit(store_dir: &Path, commit_hash: &str) -> Result<CommitInfo> {
+    let commit = utils::read_object(store_dir, commit_hash)?;
+
+    if commit.object_type != ObjectType::Commit {
+        bail!("Object {commit_hash} is not a commit");
+    }
+
+    let content = String::from_utf8(commit.decompressed_content)?;
+    let (headers, message) = content.split_once("\n\n").unwrap_or((&content, ""));
+
+    let mut tree = None;
+    let mut parents = Vec::new();
+    let mut author = None;
+    let mut committer = None;
+
+    for line in headers.lines() {
+        if let Some(rest) = line.strip_prefix("tree ") {
+            tree = Some(rest.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("parent ") {
+            parents.push(rest.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("author ") {
+            author = Some(Signature::parse(rest)?);
+        } else if let Some(rest) = line.strip_prefix("committer ") {
+            committer = Some(Signature::parse(rest)?);
+        }
+    }
+
+    Ok(CommitInfo {
+        hash: commit_hash.to_string(),
+        tree: tree.context("Commit has no tree")?,
+        parents,
+        author: author.context("Commit has no author")?,
+        committer: committer.context("Commit has no committer")?,
+        message: message.to_string(),
+    })
+}
+
+pub fn show_commit(store_dir: &Path, commit_hash: &str) -> Result<()> {
+    let commit = utils::read_object(store_dir, commit_hash)?;
     println!("{}\n\n", String::from_utf8(commit.decompressed_content)?);
     Ok(())
 }
 
-pub fn get_parent_hash(store_dir: &Path, commit_hash: String) -> anyhow::Result<Option<String>> {
-    let commit = utils::read_object(store_dir, &commit_hash)?;
+pub fn get_parent_hash(store_dir: &Path, commit_hash: String) -> Result<Option<String>> {
+    Ok(get_parent_hashes(store_dir, &commit_hash)?
+        .into_iter()
+        .next())
+}
+
+/// Returns all the parents of a commit, in the order they are recorded (first parent first).
+pub fn get_parent_hashes(store_dir: &Path, commit_hash: &str) -> Result<Vec<String>> {
+    let commit = utils::read_object(store_dir, commit_hash)?;
 
     match commit.object_type {
-        ObjectType::Commit => {},
-        _ => bail!("Parent of a commit must be itself a commit")
+        ObjectType::Commit => {}
+        _ => bail!("Parent of a commit must be itself a commit"),
     };
 
     let content = String::from_utf8(commit.decompressed_content)?;
+    let mut parents = Vec::new();
     for line in content.lines() {
         if let Some(rest) = line.strip_prefix("parent ") {
-            return Ok(Some(rest.trim().to_string()));
+            parents.push(rest.trim().to_string());
         }
 
         if line.is_empty() {
@@ -69,10 +108,12 @@ pub fn get_parent_hash(store_dir: &Path, commit_hash: String) -> anyhow::Result<
         }
     }
 
-    Ok(None)
+    Ok(parents)
 }
 
-pub fn get_tree_hash(commit_obj: shared::types::generic_object::GenericObject) -> anyhow::Result<Option<String>> {
+pub fn get_tree_hash(
+    commit_obj: shared::types::generic_object::GenericObject,
+) -> Result<Option<String>> {
     if commit_obj.object_type != ObjectType::Commit {
         bail!("Expected commit object");
     }
@@ -90,4 +131,4 @@ pub fn get_tree_hash(commit_obj: shared::types::generic_object::GenericObject) -
     }
 
     Ok(None)
-}
\ No newline at end of file
+}
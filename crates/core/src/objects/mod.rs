@@ -1,3 +1,5 @@
 pub mod blob;
 pub mod commit;
 pub mod tree;
+pub mod walk;
+pub mod pack;
@@ -0,0 +1,591 @@
+use crate::error::{Context, Error, Result, bail};
+use crate::shared::types::object_type::ObjectType;
+use crate::utils;
+use flate2::bufread::ZlibDecoder;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use std::time::SystemTime;
+
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_TAG: u8 = 4;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+const IDX_SIGNATURE: &[u8] = b"\xfftOc";
+
+/// An object read from a packfile, with its deltas already applied.
+pub struct PackObject {
+    pub hash: String,
+    pub object_type: ObjectType,
+    pub content: Vec<u8>,
+}
+
+/// A packfile entry as stored: either a whole object or a delta against a base object.
+enum RawEntry {
+    Full(ObjectType, Vec<u8>),
+    OfsDelta { base_offset: usize, delta: Vec<u8> },
+    RefDelta { base: String, delta: Vec<u8> },
+}
+
+/// Parses a version 2 packfile and resolves its deltas.
+///
+/// Delta bases are looked up in the pack first, then in `store_dir` (thin packs).
+pub fn parse_pack(data: &[u8], store_dir: &Path) -> Result<Vec<PackObject>> {
+    let corrupt = |reason: &str| Error::CorruptObject {
+        object: "packfile".to_string(),
+        reason: reason.to_string(),
+    };
+    if data.len() < 32 || &data[..4] != b"PACK" {
+        return Err(corrupt("missing PACK signature"));
+    }
+    let version = u32::from_be_bytes(data[4..8].try_into()?);
+    if version != 2 && version != 3 {
+        bail!("Unsupported packfile version {version}");
+    }
+    let count = u32::from_be_bytes(data[8..12].try_into()?) as usize;
+
+    let (body, checksum) = data.split_at(data.len() - 20);
+    if utils::hash(body)? != hex::encode(checksum) {
+        return Err(corrupt("checksum mismatch"));
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    let mut offsets = HashMap::new();
+    let mut pos = 12;
+    for index in 0..count {
+        offsets.insert(pos, index);
+        let (entry, next) = read_entry(body, pos)?;
+        entries.push(entry);
+        pos = next;
+    }
+
+    resolve_entries(entries, &offsets, store_dir)
+}
+
+/// Parses a packfile and writes all its objects to the loose object store.
+/// Returns the number of objects unpacked.
+pub fn unpack(store_dir: &Path, data: &[u8]) -> Result<usize> {
+    let objects = parse_pack(data, store_dir)?;
+    for object in &objects {
+        utils::write_raw_object(store_dir, object.object_type, &object.content)?;
+    }
+    Ok(objects.len())
+}
+
+/// Writes a version 2 packfile holding `hashes` as whole objects, without deltas.
+pub fn write_pack(store_dir: &Path, hashes: &[String]) -> Result<Vec<u8>> {
+    let mut data = b"PACK".to_vec();
+    data.extend(2u32.to_be_bytes());
+    data.extend((hashes.len() as u32).to_be_bytes());
+
+    for hash in hashes {
+        let object = utils::read_object(store_dir, hash)?;
+        let type_id = match object.object_type {
+            ObjectType::Commit => OBJ_COMMIT,
+            ObjectType::Tree => OBJ_TREE,
+            ObjectType::Blob => OBJ_BLOB,
+            ObjectType::Tag => OBJ_TAG,
+        };
+
+        // The type and the size share the first byte, the size continues 7 bits at a time.
+        let mut size = object.decompressed_content.len();
+        let mut byte = (type_id << 4) | (size & 0x0f) as u8;
+        size >>= 4;
+        while size > 0 {
+            data.push(byte | 0x80);
+            byte = (size & 0x7f) as u8;
+            size >>= 7;
+        }
+        data.push(byte);
+        data.extend(utils::compress(&object.decompressed_content)?);
+    }
+
+    let checksum = hex::decode(utils::hash(&data)?)?;
+    data.extend(checksum);
+    Ok(data)
+}
+
+/// Reads the entry starting at `pos`, returning it with the offset of the next entry.
+fn read_entry(data: &[u8], mut pos: usize) -> Result<(RawEntry, usize)> {
+    let entry_offset = pos;
+    let mut byte = *data.get(pos).context("Truncated packfile")?;
+    pos += 1;
+
+    let type_id = (byte >> 4) & 0x7;
+    let mut size = (byte & 0x0f) as usize;
+    let mut shift = 4;
+    while byte & 0x80 != 0 {
+        byte = *data.get(pos).context("Truncated packfile")?;
+        pos += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+    }
+
+    let entry = match type_id {
+        OBJ_OFS_DELTA => {
+            let mut byte = *data.get(pos).context("Truncated packfile")?;
+            pos += 1;
+            let mut distance = (byte & 0x7f) as usize;
+            while byte & 0x80 != 0 {
+                byte = *data.get(pos).context("Truncated packfile")?;
+                pos += 1;
+                distance = ((distance + 1) << 7) | (byte & 0x7f) as usize;
+            }
+            let base_offset = entry_offset
+                .checked_sub(distance)
+                .context("Invalid delta base offset")?;
+
+            let (delta, next) = inflate(data, pos, size)?;
+            pos = next;
+            RawEntry::OfsDelta { base_offset, delta }
+        }
+        OBJ_REF_DELTA => {
+            let base = data.get(pos..pos + 20).context("Truncated packfile")?;
+            pos += 20;
+
+            let (delta, next) = inflate(data, pos, size)?;
+            pos = next;
+            RawEntry::RefDelta {
+                base: hex::encode(base),
+                delta,
+            }
+        }
+        _ => {
+            let object_type = match type_id {
+                OBJ_COMMIT => ObjectType::Commit,
+                OBJ_TREE => ObjectType::Tree,
+                OBJ_BLOB => ObjectType::Blob,
+                OBJ_TAG => ObjectType::Tag,
+                _ => bail!("Unknown packfile object type {type_id}"),
+            };
+
+            let (content, next) = inflate(data, pos, size)?;
+            pos = next;
+            RawEntry::Full(object_type, content)
+        }
+    };
+
+    Ok((entry, pos))
+}
+
+/// Inflates the zlib stream starting at `pos`, returning the data and the offset right after it.
+fn inflate(data: &[u8], pos: usize, size: usize) -> Result<(Vec<u8>, usize)> {
+    let mut decoder = ZlibDecoder::new(&data[pos..]);
+    let mut content = Vec::with_capacity(size);
+    decoder
+        .read_to_end(&mut content)
+        .context("Invalid packfile: corrupt compressed data")?;
+
+    if content.len() != size {
+        bail!(
+            "Invalid packfile: expected {size} bytes, inflated {}",
+            content.len()
+        );
+    }
+    Ok((content, pos + decoder.total_in() as usize))
+}
+
+/// A version 2 pack index (`.idx`): the sorted hashes of a pack's objects and their offsets.
+struct PackIndex {
+    pack: PathBuf,
+    data: Vec<u8>,
+    count: usize,
+}
+
+impl PackIndex {
+    fn open(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("Could not read pack index {path:?}"))?;
+        if data.len() < 8 + 256 * 4 || &data[..4] != IDX_SIGNATURE {
+            bail!("Unsupported pack index {path:?}: only version 2 is supported");
+        }
+        let version = u32::from_be_bytes(data[4..8].try_into()?);
+        if version != 2 {
+            bail!("Unsupported pack index version {version}");
+        }
+
+        let count = read_u32(&data, 8 + 255 * 4)? as usize;
+        if data.len() < 8 + 256 * 4 + count * 28 {
+            return Err(Error::CorruptObject {
+                object: format!("pack index {path:?}"),
+                reason: "truncated".to_string(),
+            });
+        }
+
+        Ok(Self {
+            pack: path.with_extension("pack"),
+            data,
+            count,
+        })
+    }
+
+    fn hash(&self, position: usize) -> &[u8] {
+        let start = 8 + 256 * 4 + position * 20;
+        &self.data[start..start + 20]
+    }
+
+    /// Offsets past 2 GiB live in a separate table of 64-bit values, pointed to by the MSB.
+    fn offset(&self, position: usize) -> Result<u64> {
+        let offsets = 8 + 256 * 4 + self.count * 24;
+        let offset = read_u32(&self.data, offsets + position * 4)?;
+        if offset & 0x8000_0000 == 0 {
+            return Ok(offset as u64);
+        }
+
+        let large = offsets + self.count * 4 + (offset & 0x7fff_ffff) as usize * 8;
+        let bytes = self
+            .data
+            .get(large..large + 8)
+            .context("Truncated pack index")?;
+        Ok(u64::from_be_bytes(bytes.try_into()?))
+    }
+
+    /// The range of positions whose hash starts with `first_byte`, from the fanout table.
+    fn bucket(&self, first_byte: u8) -> Result<std::ops::Range<usize>> {
+        let end = read_u32(&self.data, 8 + first_byte as usize * 4)? as usize;
+        let start = match first_byte {
+            0 => 0,
+            byte => read_u32(&self.data, 8 + (byte as usize - 1) * 4)? as usize,
+        };
+        if start > end || end > self.count {
+            return Err(Error::CorruptObject {
+                object: format!("pack index {:?}", self.pack.with_extension("idx")),
+                reason: "invalid fanout table".to_string(),
+            });
+        }
+        Ok(start..end)
+    }
+
+    fn find(&self, hash: &[u8]) -> Result<Option<u64>> {
+        let std::ops::Range {
+            start: mut low,
+            end: mut high,
+        } = self.bucket(hash[0])?;
+
+        while low < high {
+            let middle = (low + high) / 2;
+            match self.hash(middle).cmp(hash) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Ok(Some(self.offset(middle)?)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    let bytes = data.get(pos..pos + 4).context("Truncated pack index")?;
+    Ok(u32::from_be_bytes(bytes.try_into()?))
+}
+
+/// The loaded pack indexes of each `objects/pack` directory, with the modification
+/// time of the directory they were read at.
+type PackIndexCache = HashMap<PathBuf, (SystemTime, Arc<Vec<PackIndex>>)>;
+
+static PACK_INDEXES: LazyLock<Mutex<PackIndexCache>> = LazyLock::new(Default::default);
+
+/// The indexes of every pack under `objects/pack`. They are read once and kept until
+/// a pack is added to or removed from the directory, e.g. by a fetch or a `git gc`.
+fn pack_indexes(store_dir: &Path) -> Result<Arc<Vec<PackIndex>>> {
+    let pack_dir = store_dir.join("objects").join("pack");
+    let Ok(modified) = fs::metadata(&pack_dir).and_then(|metadata| metadata.modified()) else {
+        return Ok(Arc::default());
+    };
+
+    let mut cache = PACK_INDEXES.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((loaded_at, indexes)) = cache.get(&pack_dir)
+        && *loaded_at == modified
+    {
+        return Ok(Arc::clone(indexes));
+    }
+
+    let mut indexes = Vec::new();
+    for entry in fs::read_dir(&pack_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("idx") {
+            indexes.push(PackIndex::open(&path)?);
+        }
+    }
+    let indexes = Arc::new(indexes);
+    cache.insert(pack_dir, (modified, Arc::clone(&indexes)));
+    Ok(indexes)
+}
+
+/// Reads an object from the packs of `store_dir`, if one of them contains it.
+pub fn read_packed_object(store_dir: &Path, hash: &str) -> Result<Option<(ObjectType, Vec<u8>)>> {
+    let Ok(raw_hash) = hex::decode(hash) else {
+        return Ok(None);
+    };
+    if raw_hash.len() != 20 {
+        return Ok(None);
+    }
+
+    for index in pack_indexes(store_dir)?.iter() {
+        if let Some(offset) = index.find(&raw_hash)? {
+            let mut pack = BufReader::new(File::open(&index.pack)?);
+            return read_at(&mut pack, offset, store_dir).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+pub fn has_packed_object(store_dir: &Path, hash: &str) -> bool {
+    let Ok(raw_hash) = hex::decode(hash) else {
+        return false;
+    };
+    raw_hash.len() == 20
+        && pack_indexes(store_dir).is_ok_and(|indexes| {
+            indexes
+                .iter()
+                .any(|index| index.find(&raw_hash).is_ok_and(|offset| offset.is_some()))
+        })
+}
+
+/// Hashes of the packed objects starting with `prefix`, for abbreviated revisions.
+pub fn packed_hashes(store_dir: &Path, prefix: &str) -> Result<Vec<String>> {
+    let mut hashes = Vec::new();
+    for index in pack_indexes(store_dir)?.iter() {
+        for position in 0..index.count {
+            let hash = hex::encode(index.hash(position));
+            if hash.starts_with(prefix) {
+                hashes.push(hash);
+            }
+        }
+    }
+    Ok(hashes)
+}
+
+/// Reads the object stored at `offset` of a pack, following its delta chain.
+/// Bases of `REF_DELTA` entries may live anywhere in the store.
+fn read_at(
+    pack: &mut BufReader<File>,
+    offset: u64,
+    store_dir: &Path,
+) -> Result<(ObjectType, Vec<u8>)> {
+    pack.seek(SeekFrom::Start(offset))?;
+    let mut byte = read_byte(pack)?;
+    let type_id = (byte >> 4) & 0x7;
+    let mut size = (byte & 0x0f) as usize;
+    let mut shift = 4;
+    while byte & 0x80 != 0 {
+        byte = read_byte(pack)?;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+    }
+
+    match type_id {
+        OBJ_OFS_DELTA => {
+            let mut byte = read_byte(pack)?;
+            let mut distance = (byte & 0x7f) as u64;
+            while byte & 0x80 != 0 {
+                byte = read_byte(pack)?;
+                distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+            }
+            let delta = inflate_stream(pack, size)?;
+            let base_offset = offset
+                .checked_sub(distance)
+                .context("Invalid delta base offset")?;
+
+            let (object_type, base) = read_at(pack, base_offset, store_dir)?;
+            Ok((object_type, apply_delta(&base, &delta)?))
+        }
+        OBJ_REF_DELTA => {
+            let mut base_hash = [0; 20];
+            pack.read_exact(&mut base_hash)?;
+            let delta = inflate_stream(pack, size)?;
+
+            let base = utils::read_object(store_dir, &hex::encode(base_hash))?;
+            Ok((
+                base.object_type,
+                apply_delta(&base.decompressed_content, &delta)?,
+            ))
+        }
+        _ => {
+            let object_type = match type_id {
+                OBJ_COMMIT => ObjectType::Commit,
+                OBJ_TREE => ObjectType::Tree,
+                OBJ_BLOB => ObjectType::Blob,
+                OBJ_TAG => ObjectType::Tag,
+                _ => bail!("Unknown packfile object type {type_id}"),
+            };
+            Ok((object_type, inflate_stream(pack, size)?))
+        }
+    }
+}
+
+fn read_byte(reader: &mut impl Read) -> Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte).context("Truncated packfile")?;
+    Ok(byte[0])
+}
+
+fn inflate_stream(reader: &mut impl BufRead, size: usize) -> Result<Vec<u8>> {
+    let mut content = Vec::with_capacity(size);
+    ZlibDecoder::new(reader)
+        .read_to_end(&mut content)
+        .context("Invalid packfile: corrupt compressed data")?;
+    if content.len() != size {
+        bail!(
+            "Invalid packfile: expected {size} bytes, inflated {}",
+            content.len()
+        );
+    }
+    Ok(content)
+}
+
+/// Applies deltas until every entry is resolved. Bases may appear after the deltas
+/// referring to them, so this goes over the pending entries until nothing changes.
+fn resolve_entries(
+    entries: Vec<RawEntry>,
+    offsets: &HashMap<usize, usize>,
+    store_dir: &Path,
+) -> Result<Vec<PackObject>> {
+    let mut resolved: Vec<Option<(ObjectType, Vec<u8>)>> = Vec::with_capacity(entries.len());
+    let mut by_hash = HashMap::new();
+    let mut pending = Vec::new();
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        match entry {
+            RawEntry::Full(object_type, content) => {
+                by_hash.insert(object_hash(object_type, &content)?, index);
+                resolved.push(Some((object_type, content)));
+            }
+            delta => {
+                resolved.push(None);
+                pending.push((index, delta));
+            }
+        }
+    }
+
+    while !pending.is_empty() {
+        let pending_count = pending.len();
+        let mut still_pending = Vec::new();
+
+        for (index, entry) in pending {
+            let base = match &entry {
+                RawEntry::OfsDelta { base_offset, .. } => {
+                    let base_index = offsets
+                        .get(base_offset)
+                        .context("Invalid delta base offset")?;
+                    resolved[*base_index].clone()
+                }
+                RawEntry::RefDelta { base, .. } => match by_hash.get(base) {
+                    Some(base_index) => resolved[*base_index].clone(),
+                    None if utils::has_object(store_dir, base) => {
+                        let object = utils::read_object(store_dir, base)?;
+                        Some((object.object_type, object.decompressed_content))
+                    }
+                    None => None,
+                },
+                RawEntry::Full(..) => unreachable!("whole objects are resolved up front"),
+            };
+
+            let Some((object_type, base_content)) = base else {
+                still_pending.push((index, entry));
+                continue;
+            };
+
+            let (RawEntry::OfsDelta { delta, .. } | RawEntry::RefDelta { delta, .. }) = &entry
+            else {
+                unreachable!("only deltas are pending");
+            };
+            let content = apply_delta(&base_content, delta)?;
+            by_hash.insert(object_hash(object_type, &content)?, index);
+            resolved[index] = Some((object_type, content));
+        }
+
+        if still_pending.len() == pending_count {
+            bail!("Invalid packfile: missing delta base");
+        }
+        pending = still_pending;
+    }
+
+    resolved
+        .into_iter()
+        .map(|entry| {
+            let (object_type, content) = entry.context("Unresolved packfile entry")?;
+            Ok(PackObject {
+                hash: object_hash(object_type, &content)?,
+                object_type,
+                content,
+            })
+        })
+        .collect()
+}
+
+/// Applies a git delta (source size, target size, then copy/insert instructions) to `base`.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let mut pos = 0;
+    let source_size = read_size(delta, &mut pos)?;
+    let target_size = read_size(delta, &mut pos)?;
+    if source_size != base.len() {
+        bail!("Delta base size mismatch");
+    }
+
+    let mut result = Vec::with_capacity(target_size);
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+
+        if op & 0x80 != 0 {
+            let mut offset = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos).context("Truncated delta")? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            let mut size = 0usize;
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= (*delta.get(pos).context("Truncated delta")? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            result.extend_from_slice(
+                base.get(offset..offset + size)
+                    .context("Delta copies outside of its base")?,
+            );
+        } else if op != 0 {
+            let size = op as usize;
+            result.extend_from_slice(delta.get(pos..pos + size).context("Truncated delta")?);
+            pos += size;
+        } else {
+            bail!("Invalid delta instruction");
+        }
+    }
+
+    if result.len() != target_size {
+        bail!("Delta result size mismatch");
+    }
+    Ok(result)
+}
+
+fn read_size(data: &[u8], pos: &mut usize) -> Result<usize> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos).context("Truncated delta")?;
+        *pos += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+fn object_hash(object_type: ObjectType, content: &[u8]) -> Result<String> {
+    let mut store = format!("{} {}\0", object_type.as_str(), content.len()).into_bytes();
+    store.extend_from_slice(content);
+    utils::hash(&store)
+}
@@ -1,8 +1,10 @@
+use crate::error::{Context, Error, Result, bail};
 use crate::objects::blob;
+use crate::shared::types::index_entry::IndexEntry;
 use crate::shared::types::object_type::ObjectType;
 use crate::shared::types::{hash_result::HashResult, tree_entry::TreeEntry};
 use crate::utils::{self, read_object};
-use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -42,7 +44,7 @@ impl<'a> TreeBuilder<'a> {
             let name = dir_entry
                 .file_name()
                 .into_string()
-                .map_err(|_| anyhow::anyhow!("Invalid filename"))?;
+                .map_err(|_| Error::Other("Invalid filename".to_string()))?;
 
             let metadata = fs::metadata(&entry_path)?;
 
@@ -62,7 +64,7 @@ impl<'a> TreeBuilder<'a> {
                 let subtree = self.write_tree(&entry_path)?;
 
                 entries.push(TreeEntry {
-                    mode: "040000".into(),
+                    mode: "40000".into(),
                     entry_type: "tree".into(),
                     hash: subtree.object_hash,
                     name,
@@ -92,13 +94,13 @@ pub fn hash_tree(tree_content: Vec<u8>) -> Result<HashResult> {
 
 pub fn build_tree_content(mut entries: Vec<TreeEntry>) -> Vec<u8> {
     entries.sort_by(|a, b| {
-        let a_name = if a.mode == "040000" {
+        let a_name = if is_tree_mode(&a.mode) {
             format!("{}/", a.name)
         } else {
             a.name.clone()
         };
 
-        let b_name = if b.mode == "040000" {
+        let b_name = if is_tree_mode(&b.mode) {
             format!("{}/", b.name)
         } else {
             b.name.clone()
@@ -120,63 +122,172 @@ pub fn build_tree_content(mut entries: Vec<TreeEntry>) -> Vec<u8> {
     tree_content
 }
 
-pub fn ls_tree(store_path: &Path, tree_hash: &str) -> anyhow::Result<String> {
+pub fn ls_tree(store_path: &Path, tree_hash: &str) -> Result<String> {
     let entries = parse_tree(store_path, tree_hash)?;
     let mut result = String::new();
-    
+
     for entry in entries {
-        let object_type = if entry.mode.starts_with("040") {
+        let object_type = if is_tree_mode(&entry.mode) {
             "tree"
         } else {
             "blob"
         };
-        
+
         result.push_str(&format!(
             "{} {} {} {}\n",
             entry.mode, object_type, entry.hash, entry.name
         ));
     }
-    
+
     Ok(result)
 }
 
 pub fn parse_tree(store_path: &Path, tree_hash: &str) -> Result<Vec<TreeEntry>> {
-    let object = read_object(&store_path, tree_hash)?;
-    
+    let object = read_object(store_path, tree_hash)?;
+
     match object.object_type {
-        ObjectType::Tree => {},
-        _ => bail!("Unsupported object type")
+        ObjectType::Tree => {}
+        _ => bail!("Unsupported object type"),
     };
-    
+
     let mut entries = Vec::new();
     let mut pos = 0;
     let content = object.decompressed_content;
-    
+
     while pos < content.len() {
         let space_pos = content[pos..]
             .iter()
             .position(|&b| b == b' ')
             .context("Invalid tree format")?;
-        
+
         let mode = String::from_utf8_lossy(&content[pos..pos + space_pos]).to_string();
         pos += space_pos + 1;
-        
+
         let null_pos = content[pos..]
             .iter()
             .position(|&b| b == 0)
             .context("Invalid tree format")?;
-        
+
         let name = String::from_utf8_lossy(&content[pos..pos + null_pos]).to_string();
         pos += null_pos + 1;
-        
+
         let hash_bytes = &content[pos..pos + 20];
-        let hash = hash_bytes.iter()
+        let hash = hash_bytes
+            .iter()
             .map(|b| format!("{:02x}", b))
             .collect::<String>();
         pos += 20;
-        
-        entries.push(TreeEntry { mode, name, hash, entry_type: "tree".to_string() });
+
+        entries.push(TreeEntry {
+            mode,
+            name,
+            hash,
+            entry_type: "tree".to_string(),
+        });
     }
 
     Ok(entries)
-}
\ No newline at end of file
+}
+/// Whether a tree entry mode denotes a subtree. Git writes `40000`, older flux trees `040000`.
+pub fn is_tree_mode(mode: &str) -> bool {
+    mode == "40000" || mode == "040000"
+}
+
+/// Finds the entry at a `/`-separated path inside a tree, descending one `parse_tree` per component.
+pub fn lookup_path(store_path: &Path, tree_hash: &str, path: &str) -> Result<Option<TreeEntry>> {
+    let mut components = path.split('/').filter(|c| !c.is_empty()).peekable();
+    let mut current = tree_hash.to_string();
+
+    while let Some(component) = components.next() {
+        let Some(entry) = parse_tree(store_path, &current)?
+            .into_iter()
+            .find(|entry| entry.name == component)
+        else {
+            return Ok(None);
+        };
+
+        if components.peek().is_none() {
+            return Ok(Some(entry));
+        }
+        if !is_tree_mode(&entry.mode) {
+            return Ok(None);
+        }
+        current = entry.hash;
+    }
+
+    Ok(None)
+}
+
+/// Recursively lists every file reachable from a tree, keyed by its `/`-separated path.
+pub fn flatten_tree(store_path: &Path, tree_hash: &str) -> Result<BTreeMap<String, IndexEntry>> {
+    let mut files = BTreeMap::new();
+    flatten_into(store_path, tree_hash, "", &mut files)?;
+    Ok(files)
+}
+
+fn flatten_into(
+    store_path: &Path,
+    tree_hash: &str,
+    prefix: &str,
+    files: &mut BTreeMap<String, IndexEntry>,
+) -> Result<()> {
+    for entry in parse_tree(store_path, tree_hash)? {
+        let path = format!("{}{}", prefix, entry.name);
+
+        if is_tree_mode(&entry.mode) {
+            flatten_into(store_path, &entry.hash, &format!("{path}/"), files)?;
+        } else {
+            files.insert(
+                path,
+                IndexEntry {
+                    hash: entry.hash,
+                    mode: entry.mode,
+                },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds (and stores) the nested tree objects for a flat list of file entries.
+/// Returns the hash of the root tree.
+pub fn write_tree_from_entries(
+    store_path: &Path,
+    files: &BTreeMap<String, IndexEntry>,
+) -> Result<String> {
+    let mut entries = Vec::new();
+    let mut subdirs: BTreeMap<&str, BTreeMap<String, IndexEntry>> = BTreeMap::new();
+
+    for (path, file) in files {
+        match path.split_once('/') {
+            Some((dir, rest)) => {
+                subdirs
+                    .entry(dir)
+                    .or_default()
+                    .insert(rest.to_string(), file.clone());
+            }
+            None => entries.push(TreeEntry {
+                mode: file.mode.clone(),
+                entry_type: "blob".into(),
+                hash: file.hash.clone(),
+                name: path.clone(),
+            }),
+        }
+    }
+
+    for (dir, files) in subdirs {
+        let hash = write_tree_from_entries(store_path, &files)?;
+        entries.push(TreeEntry {
+            mode: "40000".into(),
+            entry_type: "tree".into(),
+            hash,
+            name: dir.to_string(),
+        });
+    }
+
+    let result = hash_tree(build_tree_content(entries))?;
+    utils::store_object(store_path, &result.object_hash, &result.compressed_content)?;
+
+    Ok(result.object_hash)
+}
@@ -0,0 +1,101 @@
+use crate::error::{Context, Result};
+use crate::objects::{commit, tree};
+use crate::shared::types::object_type::ObjectType;
+use crate::utils;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+enum Pending {
+    /// A ref target, which is usually a commit but can also be an annotated tag.
+    Tip(String),
+    Commit(String),
+    Tree(String),
+}
+
+/// Collects the objects reachable from `tips`: commits (or annotated tags pointing at them),
+/// their trees and their blobs.
+///
+/// Objects for which `known` returns true are neither collected nor descended into: an
+/// object is only ever stored together with everything it references, so a known commit
+/// means its whole history is known as well.
+pub fn collect_objects(
+    store_dir: &Path,
+    tips: &[String],
+    known: impl Fn(&str) -> bool,
+) -> Result<Vec<String>> {
+    let mut objects = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pending: Vec<Pending> = tips.iter().cloned().map(Pending::Tip).collect();
+
+    while let Some(next) = pending.pop() {
+        match next {
+            Pending::Tip(hash) => {
+                if seen.contains(&hash) || known(&hash) {
+                    continue;
+                }
+                let object = utils::read_object(store_dir, &hash)?;
+                match object.object_type {
+                    ObjectType::Tag => {
+                        seen.insert(hash.clone());
+                        pending.push(Pending::Tip(tag_target(&object.decompressed_content)?));
+                        objects.push(hash);
+                    }
+                    _ => pending.push(Pending::Commit(hash)),
+                }
+            }
+            Pending::Commit(hash) => {
+                if !seen.insert(hash.clone()) || known(&hash) {
+                    continue;
+                }
+                let info = commit::parse_commit(store_dir, &hash)?;
+                pending.push(Pending::Tree(info.tree));
+                pending.extend(info.parents.into_iter().map(Pending::Commit));
+                objects.push(hash);
+            }
+            Pending::Tree(hash) => {
+                if !seen.insert(hash.clone()) || known(&hash) {
+                    continue;
+                }
+                for entry in tree::parse_tree(store_dir, &hash)? {
+                    if tree::is_tree_mode(&entry.mode) {
+                        pending.push(Pending::Tree(entry.hash));
+                    } else if seen.insert(entry.hash.clone()) && !known(&entry.hash) {
+                        objects.push(entry.hash);
+                    }
+                }
+                objects.push(hash);
+            }
+        }
+    }
+
+    Ok(objects)
+}
+
+/// The object an annotated tag points to, from its `object <hash>` header line.
+pub fn tag_target(content: &[u8]) -> Result<String> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .find_map(|line| line.strip_prefix("object "))
+        .map(String::from)
+        .context("Invalid tag object")
+}
+
+/// Copies objects from one object store to another. Loose objects are copied as they
+/// are, packed ones are extracted into loose objects.
+pub fn copy_objects(source_store: &Path, target_store: &Path, hashes: &[String]) -> Result<()> {
+    for hash in hashes {
+        let loose = utils::object_path(source_store, hash);
+        if loose.is_file() {
+            utils::store_object(target_store, hash, &fs::read(loose)?)?;
+        } else {
+            let object = utils::read_object(source_store, hash)?;
+            utils::write_raw_object(
+                target_store,
+                object.object_type,
+                &object.decompressed_content,
+            )?;
+        }
+    }
+    Ok(())
+}
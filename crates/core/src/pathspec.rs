@@ -0,0 +1,240 @@
+use crate::error::{Result, bail};
+use crate::repo::repository::Repository;
+
+/// A set of patterns selecting paths of the repository, as given to `add`, `restore`,
+/// `log` and the like.
+///
+/// A pattern selects the path it names and everything under it. `*`, `?` and `[...]`
+/// match within a single path component, `**` matches any number of components, so
+/// `*.rs` selects the Rust files of the current directory and `**/*.rs` those of all
+/// its subdirectories. Patterns can be prefixed with magic:
+///
+/// - `:(exclude)`, `:!` or `:^` drops the paths the pattern matches,
+/// - `:(top)` or `:/` makes the pattern relative to the top of the work tree,
+/// - `:(literal)` turns off the wildcards.
+///
+/// With no patterns, or only excluding ones, everything is selected.
+#[derive(Debug, Clone, Default)]
+pub struct Pathspec {
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    /// The pattern as given, to report it when it matches nothing.
+    original: String,
+    /// The pattern relative to the top of the work tree, `/`-separated.
+    pattern: String,
+    exclude: bool,
+    literal: bool,
+}
+
+impl Pathspec {
+    /// Parses `patterns`, resolving each with `resolve` from where flux runs to a
+    /// path relative to the top of the work tree. Patterns with `:(top)` are not
+    /// resolved.
+    pub fn parse<F>(patterns: &[String], resolve: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Result<String>,
+    {
+        let mut items = Vec::with_capacity(patterns.len());
+        for original in patterns {
+            let (magic, rest) = split_magic(original)?;
+            let (mut exclude, mut top, mut literal) = (false, false, false);
+            for word in magic {
+                match word {
+                    "exclude" | "!" | "^" => exclude = true,
+                    "top" | "/" => top = true,
+                    "literal" => literal = true,
+                    _ => bail!("Unsupported pathspec magic '{word}' in '{original}'"),
+                }
+            }
+
+            let pattern = if top {
+                rest.trim_matches('/').to_string()
+            } else {
+                resolve(if rest.is_empty() { "." } else { rest })?
+            };
+            items.push(Item {
+                original: original.clone(),
+                pattern,
+                exclude,
+                literal,
+            });
+        }
+
+        Ok(Self { items })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Whether `path`, relative to the top of the work tree, is selected.
+    pub fn matches(&self, path: &str) -> bool {
+        let mut includes = self.items.iter().filter(|item| !item.exclude).peekable();
+        let included = includes.peek().is_none() || includes.any(|item| item.matches(path));
+        included
+            && !self
+                .items
+                .iter()
+                .any(|item| item.exclude && item.matches(path))
+    }
+
+    /// The paths the including patterns name, when none of them has wildcards, so
+    /// that only these paths have to be looked at. `None` when a pattern has
+    /// wildcards, or when there is no including pattern and everything is selected.
+    pub fn literal_paths(&self) -> Option<Vec<&str>> {
+        let includes: Vec<&Item> = self.items.iter().filter(|item| !item.exclude).collect();
+        if includes.is_empty()
+            || includes
+                .iter()
+                .any(|item| !item.literal && has_wildcards(&item.pattern))
+        {
+            return None;
+        }
+        Some(includes.iter().map(|item| item.pattern.as_str()).collect())
+    }
+
+    /// Fails with the first including pattern that selects none of `paths`, as it was
+    /// given on the command line.
+    pub fn check_matched<'a, I>(&self, paths: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a String>,
+        I::IntoIter: Clone,
+    {
+        let paths = paths.into_iter();
+        for item in self.items.iter().filter(|item| !item.exclude) {
+            if !paths.clone().any(|path| item.matches(path)) {
+                bail!("Pathspec '{}' did not match any file", item.original);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Item {
+    fn matches(&self, path: &str) -> bool {
+        if self.literal || !has_wildcards(&self.pattern) {
+            return self.pattern.is_empty()
+                || path == self.pattern
+                || path
+                    .strip_prefix(&self.pattern)
+                    .is_some_and(|rest| rest.starts_with('/'));
+        }
+
+        let pattern: Vec<&str> = self.pattern.split('/').collect();
+        let path: Vec<&str> = path.split('/').collect();
+        match_components(&pattern, &path)
+    }
+}
+
+impl Repository {
+    /// Parses pathspec `patterns` given from where flux was run.
+    pub fn pathspec(&self, patterns: &[String]) -> Result<Pathspec> {
+        Pathspec::parse(patterns, |path| self.resolve_path(path))
+    }
+}
+
+/// Splits `:(word,word)rest` and the short forms `:!rest`, `:^rest` and `:/rest`
+/// into the magic words and the pattern.
+fn split_magic(pattern: &str) -> Result<(Vec<&str>, &str)> {
+    let Some(rest) = pattern.strip_prefix(':') else {
+        return Ok((Vec::new(), pattern));
+    };
+
+    if let Some(long) = rest.strip_prefix('(') {
+        let Some((magic, rest)) = long.split_once(')') else {
+            bail!("Unterminated pathspec magic in '{pattern}'");
+        };
+        let words = magic
+            .split(',')
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .collect();
+        return Ok((words, rest));
+    }
+
+    let short = rest
+        .find(|c| !matches!(c, '!' | '^' | '/'))
+        .unwrap_or(rest.len());
+    let (magic, rest) = rest.split_at(short);
+    let words = magic
+        .char_indices()
+        .map(|(i, _)| &magic[i..i + 1])
+        .collect();
+    // `:` alone ends the magic, so `::name` names `:name`.
+    Ok((words, rest.strip_prefix(':').unwrap_or(rest)))
+}
+
+fn has_wildcards(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Whether `pattern` matches `path` or one of its leading directories.
+fn match_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => true,
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_components(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((name, path)) => match_name(first, name) && match_components(rest, path),
+            None => false,
+        },
+    }
+}
+
+/// Whether the path component `name` matches the wildcard `pattern`.
+pub(crate) fn match_name(pattern: &str, name: &str) -> bool {
+    wildcard(
+        &pattern.chars().collect::<Vec<_>>(),
+        &name.chars().collect::<Vec<_>>(),
+    )
+}
+
+/// Matches a single path component against `*`, `?`, `[abc]`, `[a-z]` and `[!...]`,
+/// a backslash making the next character literal.
+fn wildcard(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| wildcard(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && wildcard(rest, &name[1..]),
+        Some(('[', rest)) => match (name.split_first(), class_end(rest)) {
+            (Some((c, name)), Some(end)) => {
+                in_class(&rest[..end], *c) && wildcard(&rest[end + 1..], name)
+            }
+            (Some((c, name)), None) => *c == '[' && wildcard(rest, name),
+            (None, _) => false,
+        },
+        Some(('\\', rest)) if !rest.is_empty() => {
+            name.first() == Some(&rest[0]) && wildcard(&rest[1..], &name[1..])
+        }
+        Some((p, rest)) => name.first() == Some(p) && wildcard(rest, &name[1..]),
+    }
+}
+
+/// The index of the `]` closing a class whose content starts at `class[0]`. A `]`
+/// right at the start (after an optional `!`) is part of the class.
+fn class_end(class: &[char]) -> Option<usize> {
+    let start = if class.first() == Some(&'!') { 1 } else { 0 };
+    (start + 1..class.len()).find(|&i| class[i] == ']')
+}
+
+fn in_class(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.split_first() {
+        Some(('!', rest)) => (true, rest),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
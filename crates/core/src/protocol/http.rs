@@ -0,0 +1,136 @@
+use crate::error::{Context, Error, Result, bail};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Sends a single HTTP/1.1 request and reads the whole response.
+///
+/// This is deliberately small: plain `http://` only, one connection per request
+/// (`Connection: close`), with support for both sized and chunked response bodies.
+pub fn request(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Option<&[u8]>,
+) -> Result<Response> {
+    let (host, path) = split_url(url)?;
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let mut stream = TcpStream::connect(&address)
+        .map_err(|error| Error::Remote(format!("Could not connect to {host}: {error}")))?;
+
+    let mut request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: flux/{}\r\nConnection: close\r\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if let Some(body) = body {
+        request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    request.push_str("\r\n");
+
+    stream.write_all(request.as_bytes())?;
+    if let Some(body) = body {
+        stream.write_all(body)?;
+    }
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    parse_response(&raw)
+}
+
+/// Splits `http://host[:port]/path` into the host and the path.
+fn split_url(url: &str) -> Result<(&str, &str)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        if url.starts_with("https://") {
+            bail!(Error::Remote, "HTTPS remotes are not supported yet");
+        }
+        bail!(Error::Remote, "Invalid HTTP URL '{url}'");
+    };
+
+    Ok(match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    })
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response> {
+    let header_end = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .context("Invalid HTTP response")?;
+    let head = std::str::from_utf8(&raw[..header_end]).context("Invalid HTTP response")?;
+    let mut lines = head.split("\r\n");
+
+    let status_line = lines.next().context("Invalid HTTP response")?;
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .context("Invalid HTTP status line")?;
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut response = Response {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+
+    let body = &raw[header_end + 4..];
+    response.body = if response
+        .header("Transfer-Encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        dechunk(body)?
+    } else if let Some(length) = response.header("Content-Length") {
+        let length: usize = length.parse().context("Invalid Content-Length")?;
+        body.get(..length).context("Truncated HTTP body")?.to_vec()
+    } else {
+        body.to_vec()
+    };
+
+    Ok(response)
+}
+
+fn dechunk(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .context("Invalid chunked body")?;
+        let size_field = std::str::from_utf8(&data[..line_end])?;
+        let size = usize::from_str_radix(size_field.split(';').next().unwrap_or("").trim(), 16)
+            .context("Invalid chunk size")?;
+        data = &data[line_end + 2..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+        body.extend_from_slice(data.get(..size).context("Truncated chunk")?);
+        data = data.get(size + 2..).context("Truncated chunk")?;
+    }
+}
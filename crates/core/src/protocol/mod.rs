@@ -0,0 +1,184 @@
+pub mod http;
+pub mod pkt_line;
+
+use crate::error::{Context, Error, Result, bail};
+use pkt_line::Packet;
+
+const UPLOAD_PACK: &str = "git-upload-pack";
+
+/// A ref advertised by `ls-refs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteRef {
+    pub name: String,
+    /// `None` for an unborn HEAD.
+    pub hash: Option<String>,
+    /// Where a symbolic ref such as `HEAD` points to, e.g. `refs/heads/main`.
+    pub symref_target: Option<String>,
+}
+
+pub fn is_http_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Lists the refs of the remote whose names start with one of `prefixes`
+/// (`HEAD` included, with its symref target).
+pub fn ls_refs(url: &str, prefixes: &[&str]) -> Result<Vec<RemoteRef>> {
+    let capabilities = advertised_capabilities(url)?;
+    let mut args = vec!["symrefs".to_string()];
+    if capabilities
+        .iter()
+        .any(|capability| capability.starts_with("ls-refs") && capability.contains("unborn"))
+    {
+        args.push("unborn".to_string());
+    }
+    args.extend(prefixes.iter().map(|prefix| format!("ref-prefix {prefix}")));
+
+    let response = command(url, "ls-refs", &args)?;
+    let mut refs = Vec::new();
+
+    for packet in pkt_line::decode(&response)? {
+        let Packet::Data(data) = packet else {
+            break;
+        };
+        let line = pkt_line::text(&data);
+        check_error(&line)?;
+
+        let mut fields = line.split(' ');
+        let hash = fields.next().context("Invalid ls-refs line")?;
+        let name = fields.next().context("Invalid ls-refs line")?;
+        let symref_target = fields
+            .find_map(|attribute| attribute.strip_prefix("symref-target:"))
+            .map(String::from);
+
+        refs.push(RemoteRef {
+            name: name.to_string(),
+            hash: (hash != "unborn").then(|| hash.to_string()),
+            symref_target,
+        });
+    }
+
+    Ok(refs)
+}
+
+/// Asks the remote for a packfile containing `wants` and everything they reference,
+/// minus what is reachable from `haves`. Returns the raw packfile.
+///
+/// Negotiation is done in a single round: all haves are sent along with `done`, so the
+/// server answers with the packfile straight away.
+pub fn fetch_pack(url: &str, wants: &[String], haves: &[String]) -> Result<Vec<u8>> {
+    let mut args = vec!["ofs-delta".to_string(), "no-progress".to_string()];
+    args.extend(wants.iter().map(|hash| format!("want {hash}")));
+    args.extend(haves.iter().map(|hash| format!("have {hash}")));
+    args.push("done".to_string());
+
+    let response = command(url, "fetch", &args)?;
+    let mut packets = pkt_line::decode(&response)?.into_iter();
+
+    // Skip the sections preceding the packfile (acknowledgments, shallow-info, ...).
+    loop {
+        match packets.next() {
+            Some(Packet::Data(data)) => {
+                let line = pkt_line::text(&data);
+                check_error(&line)?;
+                if line == "packfile" {
+                    break;
+                }
+            }
+            Some(_) => continue,
+            None => bail!(Error::Remote, "The remote did not send a packfile"),
+        }
+    }
+
+    let mut pack = Vec::new();
+    for packet in packets {
+        let Packet::Data(data) = packet else {
+            break;
+        };
+        match data.first() {
+            Some(1) => pack.extend_from_slice(&data[1..]),
+            Some(2) => {}
+            Some(3) => bail!(
+                Error::Remote,
+                "Remote error: {}",
+                pkt_line::text(&data[1..])
+            ),
+            _ => bail!(Error::Remote, "Invalid sideband packet"),
+        }
+    }
+
+    Ok(pack)
+}
+
+/// Fetches the capability advertisement and checks the server speaks protocol v2.
+fn advertised_capabilities(url: &str) -> Result<Vec<String>> {
+    let response = http::request(
+        "GET",
+        &format!(
+            "{}/info/refs?service={UPLOAD_PACK}",
+            url.trim_end_matches('/')
+        ),
+        &[("Git-Protocol", "version=2")],
+        None,
+    )?;
+    if response.status != 200 {
+        bail!(Error::Remote, "{url} returned HTTP {}", response.status);
+    }
+
+    let mut lines = pkt_line::decode(&response.body)?
+        .into_iter()
+        .filter_map(|packet| match packet {
+            Packet::Data(data) => Some(pkt_line::text(&data)),
+            _ => None,
+        })
+        // Some servers still announce the service like in protocol v0.
+        .skip_while(|line| line.starts_with("# service="));
+
+    if lines.next().as_deref() != Some("version 2") {
+        bail!(
+            Error::Remote,
+            "The server at {url} does not support Git protocol version 2"
+        );
+    }
+    Ok(lines.collect())
+}
+
+/// Sends a protocol v2 command and returns the raw response body. Over smart HTTP every
+/// command is a stateless `POST` to `<url>/git-upload-pack`.
+fn command(url: &str, command: &str, args: &[String]) -> Result<Vec<u8>> {
+    let mut body = pkt_line::encode_line(&format!("command={command}"));
+    body.extend(pkt_line::encode_line(&format!(
+        "agent=flux/{}",
+        env!("CARGO_PKG_VERSION")
+    )));
+    body.extend_from_slice(pkt_line::DELIM);
+    for arg in args {
+        body.extend(pkt_line::encode_line(arg));
+    }
+    body.extend_from_slice(pkt_line::FLUSH);
+
+    let response = http::request(
+        "POST",
+        &format!("{}/{UPLOAD_PACK}", url.trim_end_matches('/')),
+        &[
+            ("Content-Type", "application/x-git-upload-pack-request"),
+            ("Accept", "application/x-git-upload-pack-result"),
+            ("Git-Protocol", "version=2"),
+        ],
+        Some(&body),
+    )?;
+    if response.status != 200 {
+        bail!(
+            Error::Remote,
+            "{url} returned HTTP {} for {command}",
+            response.status
+        );
+    }
+    Ok(response.body)
+}
+
+fn check_error(line: &str) -> Result<()> {
+    if let Some(message) = line.strip_prefix("ERR ") {
+        bail!(Error::Remote, "Remote error: {message}");
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use crate::error::{Context, Result, bail};
+
+/// A single pkt-line: data prefixed with its 4 hex digit length, or one of the
+/// special zero-length packets.
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+    Data(Vec<u8>),
+    /// `0000`, ends a message.
+    Flush,
+    /// `0001`, separates the sections of a protocol v2 request.
+    Delim,
+    /// `0002`, ends a response in stateless connections.
+    ResponseEnd,
+}
+
+pub const FLUSH: &[u8] = b"0000";
+pub const DELIM: &[u8] = b"0001";
+
+/// Encodes `data` as a pkt-line.
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut line = format!("{:04x}", data.len() + 4).into_bytes();
+    line.extend_from_slice(data);
+    line
+}
+
+/// Encodes a text line, adding the trailing newline.
+pub fn encode_line(line: &str) -> Vec<u8> {
+    encode(format!("{line}\n").as_bytes())
+}
+
+/// Splits a byte stream into pkt-lines.
+pub fn decode(mut data: &[u8]) -> Result<Vec<Packet>> {
+    let mut packets = Vec::new();
+
+    while !data.is_empty() {
+        let length = data.get(..4).context("Truncated pkt-line")?;
+        let length = usize::from_str_radix(std::str::from_utf8(length)?, 16)
+            .context("Invalid pkt-line length")?;
+
+        let packet = match length {
+            0 => Packet::Flush,
+            1 => Packet::Delim,
+            2 => Packet::ResponseEnd,
+            3 => bail!("Invalid pkt-line length"),
+            _ => Packet::Data(data.get(4..length).context("Truncated pkt-line")?.to_vec()),
+        };
+        packets.push(packet);
+        data = &data[length.max(4)..];
+    }
+
+    Ok(packets)
+}
+
+/// The text of a data packet, without its trailing newline.
+pub fn text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches('\n')
+        .to_string()
+}
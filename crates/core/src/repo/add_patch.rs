@@ -0,0 +1,78 @@
+use crate::diff::{self, Edit, Hunk};
+use crate::error::{Context, Result};
+use crate::pathspec::Pathspec;
+use crate::repo::repository::Repository;
+use crate::shared::types::file_status::Change;
+use crate::shared::types::object_type::ObjectType;
+use crate::utils;
+
+/// Unchanged lines shown around each hunk offered for staging.
+const CONTEXT_LINES: usize = 3;
+
+/// A tracked text file whose working tree content differs from the index, cut into
+/// the hunks `add -p` offers.
+pub struct FilePatch {
+    pub path: String,
+    /// The content of the file in the index.
+    pub old: String,
+    /// The content of the file in the working tree.
+    pub new: String,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    pub fn old_lines(&self) -> Vec<&str> {
+        diff::split_lines(&self.old)
+    }
+
+    pub fn new_lines(&self) -> Vec<&str> {
+        diff::split_lines(&self.new)
+    }
+}
+
+impl Repository {
+    /// The modified text files selected by `pathspec`, with the hunks between their
+    /// index and working tree content. New, deleted and binary files are left out.
+    pub fn unstaged_patches(&self, pathspec: &Pathspec) -> Result<Vec<FilePatch>> {
+        let mut patches = Vec::new();
+        for status in self.status(pathspec)? {
+            if status.unstaged != Some(Change::Modified) {
+                continue;
+            }
+
+            let entry = self.index.get(&status.path).context("File is not staged")?;
+            let old = utils::read_object(&self.store_dir, &entry.hash)?.decompressed_content;
+            let new = utils::read_worktree_file(&self.work_tree.join(&status.path))?;
+            let (Ok(old), Ok(new)) = (String::from_utf8(old), String::from_utf8(new)) else {
+                continue;
+            };
+            if old.contains('\0') || new.contains('\0') {
+                continue;
+            }
+
+            let ops = diff::diff_lines(&diff::split_lines(&old), &diff::split_lines(&new));
+            patches.push(FilePatch {
+                hunks: diff::hunks(&ops, CONTEXT_LINES),
+                path: status.path,
+                old,
+                new,
+            });
+        }
+
+        Ok(patches)
+    }
+
+    /// Stages the index content of the file of `patch` with `edits` applied.
+    pub fn stage_edits(&mut self, patch: &FilePatch, edits: &[Edit]) -> Result<()> {
+        let content = diff::apply_edits(&patch.old_lines(), edits);
+        let hash = utils::write_raw_object(&self.store_dir, ObjectType::Blob, content.as_bytes())?;
+        let mode = self
+            .index
+            .get(&patch.path)
+            .map(|entry| entry.mode.clone())
+            .context("File is not staged")?;
+
+        self.index.add(patch.path.clone(), hash, mode)?;
+        self.index.flush()
+    }
+}
@@ -0,0 +1,257 @@
+use crate::diff::{self, DiffOp};
+use crate::error::{Context, Result};
+use crate::objects::{commit, tree};
+use crate::repo::repository::Repository;
+use crate::shared::types::commit_info::CommitInfo;
+use crate::utils;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// A line of the blamed file and the commit that introduced it.
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    pub commit: String,
+    /// 1-based line number in the file as of `commit`.
+    pub original_line: usize,
+    /// 1-based line number in the blamed revision.
+    pub final_line: usize,
+    pub content: String,
+}
+
+/// The result of blaming a file: its lines in order and the commits they point to.
+pub struct Blame {
+    pub path: String,
+    pub lines: Vec<BlameLine>,
+    pub commits: HashMap<String, CommitInfo>,
+    /// For each blamed commit, the parent its version of the file was compared with.
+    pub previous: HashMap<String, String>,
+}
+
+impl Repository {
+    /// Attributes every line of `path` at `revision` (HEAD by default) to the commit
+    /// that introduced it. Commits are visited newest first; each one hands the lines
+    /// it shares with a parent's version of the file down to that parent and keeps
+    /// the rest. Renames are not followed.
+    pub fn blame(&self, path: &str, revision: Option<&str>) -> Result<Blame> {
+        let path = &self.resolve_path(path)?;
+        let revision = revision.unwrap_or("HEAD");
+        let start = self.resolve_revision(revision)?;
+        let blob = self
+            .blob_in_commit(&start, path)?
+            .with_context(|| format!("No such path '{path}' in {revision}"))?;
+        let content = self.blob_text(&blob)?;
+        let final_lines = diff::split_lines(&content);
+
+        let mut blame = Blame {
+            path: path.to_string(),
+            lines: Vec::with_capacity(final_lines.len()),
+            commits: HashMap::new(),
+            previous: HashMap::new(),
+        };
+        let mut attributed: Vec<Option<(String, usize)>> = vec![None; final_lines.len()];
+
+        // Lines still looking for their origin, as (final line, line in that commit).
+        let mut pending: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        pending.insert(
+            start.clone(),
+            (0..final_lines.len()).map(|i| (i, i)).collect(),
+        );
+        let mut queue = BinaryHeap::new();
+        let mut blobs: HashMap<String, Option<String>> = HashMap::new();
+        blobs.insert(start.clone(), Some(blob));
+        let info = commit::parse_commit(&self.store_dir, &start)?;
+        queue.push((info.committer.timestamp, start.clone()));
+        blame.commits.insert(start, info);
+
+        while let Some((_, hash)) = queue.pop() {
+            let Some(mut lines) = pending.remove(&hash) else {
+                continue;
+            };
+            let info = blame.commits[&hash].clone();
+            let blob = blobs[&hash]
+                .clone()
+                .context("Blamed commit lost the file")?;
+            let text = self.blob_text(&blob)?;
+            let current = diff::split_lines(&text);
+
+            for parent in &info.parents {
+                if lines.is_empty() {
+                    break;
+                }
+                if !blobs.contains_key(parent) {
+                    blobs.insert(parent.clone(), self.blob_in_commit(parent, path)?);
+                }
+                let Some(parent_blob) = blobs[parent].clone() else {
+                    continue;
+                };
+                blame
+                    .previous
+                    .entry(hash.clone())
+                    .or_insert_with(|| parent.clone());
+
+                // For each line of this commit's version, the parent line it came from.
+                let origins: Vec<Option<usize>> = if parent_blob == blob {
+                    (0..current.len()).map(Some).collect()
+                } else {
+                    let parent_text = self.blob_text(&parent_blob)?;
+                    let parent_lines = diff::split_lines(&parent_text);
+                    let mut origins = vec![None; current.len()];
+                    for op in diff::diff_lines(&parent_lines, &current) {
+                        if let DiffOp::Equal { old, new } = op {
+                            origins[new] = Some(old);
+                        }
+                    }
+                    origins
+                };
+
+                let mut passed = Vec::new();
+                let mut kept = Vec::new();
+                for (final_line, line) in lines {
+                    match origins[line] {
+                        Some(origin) => passed.push((final_line, origin)),
+                        None => kept.push((final_line, line)),
+                    }
+                }
+                lines = kept;
+                if passed.is_empty() {
+                    continue;
+                }
+
+                if !blame.commits.contains_key(parent) {
+                    let parent_info = commit::parse_commit(&self.store_dir, parent)?;
+                    blame.commits.insert(parent.clone(), parent_info);
+                }
+                pending.entry(parent.clone()).or_default().extend(passed);
+                queue.push((blame.commits[parent].committer.timestamp, parent.clone()));
+            }
+
+            for (final_line, line) in lines {
+                attributed[final_line] = Some((hash.clone(), line));
+            }
+        }
+
+        for (index, origin) in attributed.into_iter().enumerate() {
+            let (commit, line) = origin.context("Line left unattributed by blame")?;
+            blame.lines.push(BlameLine {
+                commit,
+                original_line: line + 1,
+                final_line: index + 1,
+                content: final_lines[index].to_string(),
+            });
+        }
+
+        let blamed: HashSet<&String> = blame.lines.iter().map(|line| &line.commit).collect();
+        blame.commits.retain(|hash, _| blamed.contains(hash));
+        blame.previous.retain(|hash, _| blamed.contains(hash));
+        Ok(blame)
+    }
+
+    fn blob_in_commit(&self, commit_hash: &str, path: &str) -> Result<Option<String>> {
+        let tree_hash = self.commit_tree_hash(commit_hash)?;
+        Ok(tree::lookup_path(&self.store_dir, &tree_hash, path)?
+            .filter(|entry| !tree::is_tree_mode(&entry.mode))
+            .map(|entry| entry.hash))
+    }
+
+    fn blob_text(&self, hash: &str) -> Result<String> {
+        let object = utils::read_object(&self.store_dir, hash)?;
+        Ok(String::from_utf8_lossy(&object.decompressed_content).into_owned())
+    }
+}
+
+impl Blame {
+    /// Root commits are shown as boundaries, like git does by default.
+    fn is_boundary(&self, hash: &str) -> bool {
+        self.commits[hash].parents.is_empty()
+    }
+
+    /// `hash (Author date line) content` for every line, with the columns aligned.
+    pub fn format(&self) -> String {
+        let name_width = self
+            .commits
+            .values()
+            .map(|info| info.author.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let number_width = self.lines.len().to_string().len();
+
+        let mut out = String::new();
+        for line in &self.lines {
+            let info = &self.commits[&line.commit];
+            let hash = if self.is_boundary(&line.commit) {
+                format!("^{}", &line.commit[..7])
+            } else {
+                line.commit[..8].to_string()
+            };
+            let date = info
+                .author
+                .time()
+                .map(|time| time.format("%Y-%m-%d %H:%M:%S %z").to_string())
+                .unwrap_or_else(|| format!("{} {}", info.author.timestamp, info.author.timezone));
+            out.push_str(&format!(
+                "{hash} ({:<name_width$} {date} {:>number_width$}) {}\n",
+                info.author.name,
+                line.final_line,
+                line.content.trim_end_matches('\n')
+            ));
+        }
+        out
+    }
+
+    /// The `git blame --porcelain` format: a header per run of lines from one commit,
+    /// the commit's details the first time it appears and every line prefixed by a tab.
+    pub fn porcelain(&self) -> String {
+        let mut out = String::new();
+        let mut described = HashSet::new();
+
+        let mut index = 0;
+        while index < self.lines.len() {
+            let first = &self.lines[index];
+            let run = self.lines[index..]
+                .iter()
+                .enumerate()
+                .take_while(|(offset, line)| {
+                    line.commit == first.commit
+                        && line.original_line == first.original_line + offset
+                })
+                .count();
+
+            for (offset, line) in self.lines[index..index + run].iter().enumerate() {
+                out.push_str(&format!(
+                    "{} {} {}",
+                    line.commit, line.original_line, line.final_line
+                ));
+                if offset == 0 {
+                    out.push_str(&format!(" {run}\n"));
+                    if described.insert(&line.commit) {
+                        self.describe(&mut out, &line.commit);
+                    }
+                } else {
+                    out.push('\n');
+                }
+                out.push('\t');
+                out.push_str(line.content.trim_end_matches('\n'));
+                out.push('\n');
+            }
+            index += run;
+        }
+        out
+    }
+
+    fn describe(&self, out: &mut String, hash: &str) {
+        let info = &self.commits[hash];
+        for (role, signature) in [("author", &info.author), ("committer", &info.committer)] {
+            out.push_str(&format!(
+                "{role} {}\n{role}-mail <{}>\n{role}-time {}\n{role}-tz {}\n",
+                signature.name, signature.email, signature.timestamp, signature.timezone
+            ));
+        }
+        out.push_str(&format!("summary {}\n", info.summary()));
+        if self.is_boundary(hash) {
+            out.push_str("boundary\n");
+        }
+        if let Some(previous) = self.previous.get(hash) {
+            out.push_str(&format!("previous {previous} {}\n", self.path));
+        }
+        out.push_str(&format!("filename {}\n", self.path));
+    }
+}
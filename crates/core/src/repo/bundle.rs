@@ -0,0 +1,179 @@
+use crate::error::{Context, Result, bail};
+use crate::objects::{commit, pack, walk};
+use crate::repo::repository::Repository;
+use crate::utils;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+const SIGNATURE_V2: &str = "# v2 git bundle";
+const SIGNATURE_V3: &str = "# v3 git bundle";
+
+/// A git bundle: the commits a receiver must already have, the refs it carries
+/// and a packfile with the objects needed on top of the prerequisites.
+pub(crate) struct Bundle {
+    pub prerequisites: Vec<String>,
+    pub refs: Vec<(String, String)>,
+    pub pack: Vec<u8>,
+}
+
+impl Bundle {
+    pub fn is_bundle(path: &Path) -> bool {
+        let Ok(file) = fs::File::open(path) else {
+            return false;
+        };
+        let mut first_line = String::new();
+        BufReader::new(file).read_line(&mut first_line).is_ok()
+            && [SIGNATURE_V2, SIGNATURE_V3].contains(&first_line.trim_end())
+    }
+
+    /// Reads a v2 bundle, or a v3 one whose capabilities do not go beyond v2.
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("Could not read bundle {path:?}"))?;
+        let header_end = data
+            .windows(2)
+            .position(|window| window == b"\n\n")
+            .context("Invalid bundle: missing end of header")?;
+        let header = std::str::from_utf8(&data[..header_end]).context("Invalid bundle header")?;
+
+        let mut lines = header.lines();
+        let signature = lines.next().unwrap_or_default();
+        if signature != SIGNATURE_V2 && signature != SIGNATURE_V3 {
+            bail!("{path:?} is not a git bundle");
+        }
+
+        let mut bundle = Bundle {
+            prerequisites: Vec::new(),
+            refs: Vec::new(),
+            pack: data[header_end + 2..].to_vec(),
+        };
+        for line in lines {
+            if let Some(capability) = line.strip_prefix('@') {
+                if capability != "object-format=sha1" {
+                    bail!("Unsupported bundle capability '{capability}'");
+                }
+            } else if let Some(prerequisite) = line.strip_prefix('-') {
+                let hash = prerequisite.split(' ').next().unwrap_or_default();
+                bundle.prerequisites.push(hash.to_string());
+            } else {
+                let (hash, name) = line
+                    .split_once(' ')
+                    .with_context(|| format!("Invalid bundle ref line '{line}'"))?;
+                bundle.refs.push((name.to_string(), hash.to_string()));
+            }
+        }
+
+        Ok(bundle)
+    }
+}
+
+impl Repository {
+    /// Writes a v2 bundle of `revisions` to `file`. Revisions are branch, tag or commit
+    /// names, `A..B` ranges, `^A` exclusions or `--all` for every branch and tag.
+    /// Excluded commits become prerequisites. Returns the number of bundled objects.
+    pub fn bundle_create(&self, file: &Path, revisions: &[String]) -> Result<usize> {
+        let mut included: Vec<(Option<String>, String)> = Vec::new();
+        let mut excluded = Vec::new();
+
+        for revision in revisions {
+            if revision == "--all" {
+                let mut references = self.refs.iter("refs/heads/")?;
+                references.extend(self.refs.iter("refs/tags/")?);
+                for reference in references {
+                    if let Some(target) = reference.target {
+                        included.push((Some(reference.name), target));
+                    }
+                }
+            } else if let Some(revision) = revision.strip_prefix('^') {
+                excluded.push(self.resolve_revision(revision)?);
+            } else if let Some((from, to)) = revision.split_once("..") {
+                let from = if from.is_empty() { "HEAD" } else { from };
+                let to = if to.is_empty() { "HEAD" } else { to };
+                excluded.push(self.resolve_revision(from)?);
+                included.push(self.bundle_tip(to)?);
+            } else {
+                included.push(self.bundle_tip(revision)?);
+            }
+        }
+
+        let refs: Vec<(String, String)> = included
+            .iter()
+            .filter_map(|(name, hash)| Some((name.clone()?, hash.clone())))
+            .collect();
+        if refs.is_empty() {
+            bail!("Refusing to create a bundle without refs, name a branch or a tag");
+        }
+
+        let known: HashSet<String> = walk::collect_objects(&self.store_dir, &excluded, |_| false)?
+            .into_iter()
+            .collect();
+        let tips: Vec<String> = included.into_iter().map(|(_, hash)| hash).collect();
+        let objects = walk::collect_objects(&self.store_dir, &tips, |hash| known.contains(hash))?;
+
+        let mut header = format!("{SIGNATURE_V2}\n");
+        for hash in &excluded {
+            let info = commit::parse_commit(&self.store_dir, hash)?;
+            let subject = info.message.lines().next().unwrap_or_default();
+            header.push_str(&format!("-{hash} {subject}\n"));
+        }
+        for (name, hash) in &refs {
+            header.push_str(&format!("{hash} {name}\n"));
+        }
+        header.push('\n');
+
+        let mut data = header.into_bytes();
+        data.extend(pack::write_pack(&self.store_dir, &objects)?);
+        fs::write(file, data).with_context(|| format!("Could not write bundle {file:?}"))?;
+
+        Ok(objects.len())
+    }
+
+    /// Stores the objects of a bundle, whose prerequisites must already be present.
+    /// Refs are not updated: the bundled refs are returned instead.
+    pub fn unbundle(&self, file: &Path) -> Result<Vec<(String, String)>> {
+        let bundle = Bundle::read(file)?;
+
+        let missing: Vec<&String> = bundle
+            .prerequisites
+            .iter()
+            .filter(|hash| !utils::has_object(&self.store_dir, hash))
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "The repository lacks these prerequisite commits: {}",
+                missing
+                    .iter()
+                    .map(|hash| hash.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        pack::unpack(&self.store_dir, &bundle.pack)?;
+        Ok(bundle.refs)
+    }
+
+    /// A revision to bundle with the full name of the ref it names, if it names one.
+    /// Tags keep their tag object, everything else is resolved to a commit.
+    fn bundle_tip(&self, revision: &str) -> Result<(Option<String>, String)> {
+        if revision == "HEAD" {
+            return Ok((Some("HEAD".to_string()), self.resolve_revision(revision)?));
+        }
+
+        for name in [
+            revision.to_string(),
+            format!("refs/heads/{revision}"),
+            format!("refs/tags/{revision}"),
+            format!("refs/remotes/{revision}"),
+        ] {
+            if name.starts_with("refs/")
+                && let Some(hash) = self.refs.read(&name)?
+            {
+                return Ok((Some(name), hash));
+            }
+        }
+
+        Ok((None, self.resolve_revision(revision)?))
+    }
+}
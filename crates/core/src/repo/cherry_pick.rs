@@ -0,0 +1,247 @@
+use crate::error::{Context, Error, Result, bail};
+use crate::merge::{self, TreeMerge};
+use crate::objects::{blob, commit};
+use crate::repo::repository::Repository;
+use crate::shared::types::apply_outcome::ApplyOutcome;
+use crate::shared::types::commit_info::CommitInfo;
+use crate::shared::types::reset_mode::ResetMode;
+use std::fs;
+
+const CHERRY_PICK_HEAD: &str = "CHERRY_PICK_HEAD";
+const REVERT_HEAD: &str = "REVERT_HEAD";
+const ORIG_HEAD: &str = "ORIG_HEAD";
+const MERGE_MSG: &str = "MERGE_MSG";
+const MERGE_CONFLICTS: &str = "MERGE_CONFLICTS";
+
+impl Repository {
+    /// Applies the changes introduced by `revision` on top of HEAD and commits them,
+    /// keeping the original author and message.
+    pub fn cherry_pick(&mut self, revision: &str) -> Result<ApplyOutcome> {
+        let picked = self.prepare_pick(revision)?;
+        let label = format!("{} ({})", &picked.hash[..7], picked.summary());
+
+        let merge = self.apply_changes(
+            picked.parents.first().map(String::as_str),
+            Some(&picked.hash),
+            &label,
+        )?;
+
+        self.finish_pick(
+            merge,
+            CHERRY_PICK_HEAD,
+            &picked.hash,
+            picked.message.clone(),
+            Some(&picked),
+        )
+    }
+
+    /// Creates a commit undoing the changes introduced by `revision`.
+    pub fn revert(&mut self, revision: &str) -> Result<ApplyOutcome> {
+        let reverted = self.prepare_pick(revision)?;
+        let label = format!("parent of {} ({})", &reverted.hash[..7], reverted.summary());
+
+        let merge = self.apply_changes(
+            Some(&reverted.hash),
+            reverted.parents.first().map(String::as_str),
+            &label,
+        )?;
+
+        let message = format!(
+            "Revert \"{}\"\n\nThis reverts commit {}.\n",
+            reverted.summary(),
+            reverted.hash
+        );
+
+        self.finish_pick(merge, REVERT_HEAD, &reverted.hash, message, None)
+    }
+
+    /// Commits the resolved state of a conflicted cherry-pick or revert.
+    pub fn pick_continue(&mut self) -> Result<String> {
+        let (state_file, original) = self
+            .pick_in_progress()?
+            .context("No cherry-pick or revert in progress")?;
+
+        self.check_conflicts_resolved()?;
+
+        let message = fs::read_to_string(self.store_dir.join(MERGE_MSG))?;
+        let author = if state_file == CHERRY_PICK_HEAD {
+            Some(commit::parse_commit(&self.store_dir, &original)?.author)
+        } else {
+            None
+        };
+
+        let hash = self.commit_as(author, message)?;
+        self.clear_pick_state()?;
+        self.load_branches()?;
+        Ok(hash)
+    }
+
+    /// Drops a conflicted cherry-pick or revert and goes back to the previous HEAD.
+    pub fn pick_abort(&mut self) -> Result<()> {
+        if self.pick_in_progress()?.is_none() {
+            bail!(
+                Error::OperationInProgress,
+                "No cherry-pick or revert in progress"
+            );
+        }
+
+        let orig_head = fs::read_to_string(self.store_dir.join(ORIG_HEAD))?;
+        self.reset(ResetMode::Hard, Some(orig_head.trim()))?;
+        self.clear_pick_state()
+    }
+
+    fn prepare_pick(&self, revision: &str) -> Result<CommitInfo> {
+        if self.pick_in_progress()?.is_some() {
+            bail!(
+                Error::OperationInProgress,
+                "A cherry-pick or revert is already in progress, use --continue or --abort"
+            );
+        }
+        if self.rebase_in_progress() {
+            bail!(
+                Error::OperationInProgress,
+                "A rebase is in progress, finish it with --continue, --skip or --abort"
+            );
+        }
+        if self.has_uncommitted_changes()? {
+            bail!(
+                Error::DirtyWorkTree,
+                "Your local changes would be overwritten, commit them first"
+            );
+        }
+
+        let hash = self.resolve_revision(revision)?;
+        let picked = commit::parse_commit(&self.store_dir, &hash)?;
+
+        if picked.parents.len() > 1 {
+            bail!("Commit {} is a merge commit, which is not supported", hash);
+        }
+
+        Ok(picked)
+    }
+
+    fn finish_pick(
+        &mut self,
+        merge: TreeMerge,
+        state_file: &str,
+        original: &str,
+        message: String,
+        picked: Option<&CommitInfo>,
+    ) -> Result<ApplyOutcome> {
+        if merge.conflicts.is_empty() {
+            let author = picked.map(|picked| picked.author.clone());
+            let hash = self.commit_as(author, message)?;
+            self.load_branches()?;
+            return Ok(ApplyOutcome::Committed(hash));
+        }
+
+        let orig_head = self.head_commit()?.unwrap_or_default();
+        fs::write(self.store_dir.join(ORIG_HEAD), orig_head)?;
+        fs::write(self.store_dir.join(state_file), original)?;
+        fs::write(self.store_dir.join(MERGE_MSG), message)?;
+        self.record_conflicts(&merge.conflicts)?;
+
+        Ok(ApplyOutcome::Conflicts(merge.conflicts))
+    }
+
+    /// Three-way merges the change `base -> theirs` into HEAD, updating the index
+    /// and the working tree. Conflicted files get conflict markers in the working tree.
+    /// Nothing is touched if an incoming file would overwrite an untracked one.
+    pub(crate) fn apply_changes(
+        &mut self,
+        base: Option<&str>,
+        theirs: Option<&str>,
+        theirs_label: &str,
+    ) -> Result<TreeMerge> {
+        let base_files = self.commit_files(base)?;
+        let their_files = self.commit_files(theirs)?;
+        let our_files = self.commit_files(self.head_commit()?.as_deref())?;
+        let previously_tracked = self.tracked_files()?;
+
+        let merge = merge::merge_trees(
+            &self.store_dir,
+            &base_files,
+            &our_files,
+            &their_files,
+            "HEAD",
+            theirs_label,
+        )?;
+
+        self.check_untracked_overwrite(&previously_tracked, &merge.files)?;
+        self.checkout_files(&previously_tracked, &merge.files)?;
+        for (path, content) in &merge.conflict_content {
+            fs::write(self.work_tree.join(path), content)?;
+        }
+        self.index.replace(merge.files.clone())?;
+
+        Ok(merge)
+    }
+
+    pub(crate) fn record_conflicts(&self, conflicts: &[String]) -> Result<()> {
+        fs::write(self.store_dir.join(MERGE_CONFLICTS), conflicts.join("\n"))?;
+        Ok(())
+    }
+
+    pub(crate) fn clear_conflicts(&self) -> Result<()> {
+        let path = self.store_dir.join(MERGE_CONFLICTS);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Every conflicted path must be staged and free of conflict markers.
+    pub(crate) fn check_conflicts_resolved(&self) -> Result<()> {
+        let conflicts_path = self.store_dir.join(MERGE_CONFLICTS);
+        let conflicts = fs::read_to_string(&conflicts_path).unwrap_or_default();
+
+        for path in conflicts.lines().filter(|line| !line.is_empty()) {
+            let full_path = self.work_tree.join(path);
+
+            if !full_path.exists() {
+                if self.index.get(path).is_some() {
+                    bail!("'{path}' was deleted but is still staged");
+                }
+                continue;
+            }
+
+            let content = fs::read(&full_path)?;
+            if content
+                .split(|&b| b == b'\n')
+                .any(|line| line.starts_with(b"<<<<<<< ") || line.starts_with(b">>>>>>> "))
+            {
+                bail!("'{path}' still contains conflict markers");
+            }
+
+            let staged = self.index.get(path).map(|entry| entry.hash.as_str());
+            if staged != Some(blob::hash_blob(content)?.object_hash.as_str()) {
+                bail!("'{path}' has unstaged changes, add it after resolving the conflict");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn pick_in_progress(&self) -> Result<Option<(&'static str, String)>> {
+        for state_file in [CHERRY_PICK_HEAD, REVERT_HEAD] {
+            let path = self.store_dir.join(state_file);
+            if path.exists() {
+                return Ok(Some((
+                    state_file,
+                    fs::read_to_string(path)?.trim().to_string(),
+                )));
+            }
+        }
+        Ok(None)
+    }
+
+    fn clear_pick_state(&self) -> Result<()> {
+        for state_file in [CHERRY_PICK_HEAD, REVERT_HEAD, MERGE_MSG, MERGE_CONFLICTS] {
+            let path = self.store_dir.join(state_file);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
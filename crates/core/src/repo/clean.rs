@@ -0,0 +1,155 @@
+use crate::error::{Context, Result};
+use crate::ignore::IgnoreRules;
+use crate::pathspec::Pathspec;
+use crate::repo::discovery;
+use crate::repo::repository::{Repository, is_store_dir};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+impl Repository {
+    /// The untracked files selected by `pathspec`, which `clean` removes. Directories
+    /// are listed with a trailing `/`, sorted with the files.
+    ///
+    /// Files in directories that hold no tracked file are only listed with
+    /// `directories`, and such a directory is listed as a whole when all of its files
+    /// are. Directories without any file are listed too with `directories`. Files matched by `.fluxignore` are left out unless `ignored` is set.
+    /// Nested repositories are always left alone, like the directories holding them.
+    pub fn untracked_paths(
+        &self,
+        pathspec: &Pathspec,
+        directories: bool,
+        ignored: bool,
+    ) -> Result<Vec<String>> {
+        let all_files = self.all_worktree_files()?;
+        let files = if ignored {
+            all_files.clone()
+        } else {
+            self.worktree_files()?
+        };
+        let tracked_dirs: HashSet<&str> = self
+            .index
+            .map
+            .keys()
+            .flat_map(|path| path.match_indices('/').map(|(i, _)| &path[..i]))
+            .collect();
+
+        let mut paths = Vec::new();
+        let mut in_untracked_dirs: BTreeMap<&str, (Vec<&String>, Vec<&String>)> = BTreeMap::new();
+        for path in &files {
+            if self.index.map.contains_key(path) || !pathspec.matches(path) {
+                continue;
+            }
+
+            let untracked_dir = path
+                .match_indices('/')
+                .map(|(i, _)| &path[..i])
+                .find(|dir| !tracked_dirs.contains(dir));
+            match untracked_dir {
+                None => paths.push(path.clone()),
+                Some(dir) if directories => in_untracked_dirs.entry(dir).or_default().0.push(path),
+                Some(_) => {}
+            }
+        }
+
+        let mut empty_dirs = Vec::new();
+        if directories {
+            self.collect_empty_dirs(&self.work_tree, &mut empty_dirs)?;
+        }
+        let rules = IgnoreRules::load(&self.work_tree, &all_files)?;
+        for dir in &empty_dirs {
+            if !pathspec.matches(dir) || (!ignored && rules.is_ignored(dir, true)) {
+                continue;
+            }
+            let untracked_dir = dir
+                .match_indices('/')
+                .map(|(i, _)| &dir[..i])
+                .find(|dir| !tracked_dirs.contains(dir))
+                .unwrap_or(dir);
+            in_untracked_dirs
+                .entry(untracked_dir)
+                .or_default()
+                .1
+                .push(dir);
+        }
+
+        for (dir, (selected, empty)) in in_untracked_dirs {
+            let prefix = format!("{dir}/");
+            let all = all_files
+                .iter()
+                .filter(|path| path.starts_with(&prefix))
+                .count();
+            if selected.len() == all
+                && pathspec.matches(dir)
+                && !holds_repository(&self.work_tree.join(dir))?
+            {
+                paths.push(prefix);
+            } else {
+                paths.extend(selected.into_iter().cloned());
+                paths.extend(empty.into_iter().map(|dir| format!("{dir}/")));
+            }
+        }
+
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Collects the directories under `dir` that hold no file at all, the topmost ones
+    /// only, and returns whether `dir` itself is one of them.
+    fn collect_empty_dirs(&self, dir: &Path, empty_dirs: &mut Vec<String>) -> Result<bool> {
+        let mut empty_children = Vec::new();
+        let mut is_empty = true;
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir()
+                && !is_store_dir(&path)
+                && discovery::store_in(&path).is_none()
+                && self.collect_empty_dirs(&path, empty_dirs)?
+            {
+                empty_children.push(path);
+            } else {
+                is_empty = false;
+            }
+        }
+
+        if !is_empty {
+            for path in empty_children {
+                let rel_path = path
+                    .strip_prefix(&self.work_tree)
+                    .context("Path is outside work tree")?;
+                empty_dirs.push(rel_path.to_str().context("Non UTF-8 path")?.to_string());
+            }
+        }
+        Ok(is_empty)
+    }
+
+    /// Removes the paths listed by [`Repository::untracked_paths`] from the working
+    /// tree, and returns them.
+    pub fn clean(
+        &self,
+        pathspec: &Pathspec,
+        directories: bool,
+        ignored: bool,
+    ) -> Result<Vec<String>> {
+        let paths = self.untracked_paths(pathspec, directories, ignored)?;
+        for path in &paths {
+            match path.strip_suffix('/') {
+                Some(dir) => fs::remove_dir_all(self.work_tree.join(dir))?,
+                None => self.remove_worktree_file(path)?,
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// Whether a repository is nested somewhere under `dir`.
+fn holds_repository(dir: &Path) -> Result<bool> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() && (discovery::store_in(&path).is_some() || holds_repository(&path)?) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,107 @@
+use crate::error::{Context, Result, bail};
+use crate::protocol;
+use crate::repo::bundle::Bundle;
+use crate::repo::repository::Repository;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+impl Repository {
+    /// Creates a copy of the repository at `source` in `target` (by default a directory
+    /// named after the source in the current directory).
+    ///
+    /// `source` is either a path on disk, optionally prefixed with `file://`, whose objects
+    /// are hardlinked when possible, a bundle file, or an `http://` URL fetched with the
+    /// smart HTTP protocol.
+    /// The source branches become `refs/remotes/origin/*`, tags are copied as they are,
+    /// `origin` is recorded in the config and the branch HEAD of the source points to is
+    /// checked out.
+    pub fn clone_from(source: &str, target: Option<&str>) -> Result<Self> {
+        let (url, source_store) = if protocol::is_http_url(source) {
+            (source.to_string(), None)
+        } else {
+            let path = PathBuf::from(source.strip_prefix("file://").unwrap_or(source));
+            let path = fs::canonicalize(&path)
+                .with_context(|| format!("Repository '{source}' does not exist"))?;
+            if Bundle::is_bundle(&path) {
+                (path.to_string_lossy().into_owned(), None)
+            } else {
+                let source_repo = Repository::open(Some(path.to_string_lossy().into_owned()))?;
+                (
+                    path.to_string_lossy().into_owned(),
+                    Some(source_repo.store_dir),
+                )
+            }
+        };
+
+        let target = match target {
+            Some(target) => PathBuf::from(target),
+            None => default_directory(&url)?,
+        };
+        if target.exists() && fs::read_dir(&target)?.next().is_some() {
+            bail!(
+                "Destination path {:?} already exists and is not empty",
+                target
+            );
+        }
+
+        let mut repo = Repository::init(Some(target.to_string_lossy().into_owned()), false)?;
+        if let Some(source_store) = &source_store {
+            link_objects(
+                &source_store.join("objects"),
+                &repo.store_dir.join("objects"),
+            )?;
+        }
+
+        repo.config.add_remote("origin", &url)?;
+        repo.fetch("origin")?;
+
+        let default_branch = repo.remote_default_branch("origin")?;
+        if let Some(branch) = default_branch
+            && let Some(hash) = repo.refs.read(&format!("refs/remotes/origin/{branch}"))?
+        {
+            let branch_ref = format!("refs/heads/{branch}");
+            if branch_ref != repo.head {
+                repo.refs.delete(&repo.head)?;
+            }
+            repo.refs.write(&branch_ref, &hash)?;
+            repo.switch_branch(&branch, true)?;
+        }
+
+        repo.load_branches()?;
+        Ok(repo)
+    }
+}
+
+/// The last component of a path or URL, without a `.git` or `.bundle` suffix.
+fn default_directory(url: &str) -> Result<PathBuf> {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .map(|name| {
+            name.strip_suffix(".git")
+                .or_else(|| name.strip_suffix(".bundle"))
+                .unwrap_or(name)
+        })
+        .filter(|name| !name.is_empty() && !name.contains(':'))
+        .context("Cannot derive a directory name from the source, pass one explicitly")?;
+    Ok(PathBuf::from(name))
+}
+
+/// Hardlinks every object file from one object directory into another,
+/// copying them when linking is not possible (e.g. across filesystems).
+fn link_objects(source: &Path, target: &Path) -> Result<()> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let path = entry.path();
+        let target_path = target.join(entry.file_name());
+
+        if path.is_dir() {
+            fs::create_dir_all(&target_path)?;
+            link_objects(&path, &target_path)?;
+        } else if !target_path.exists() && fs::hard_link(&path, &target_path).is_err() {
+            fs::copy(&path, &target_path)?;
+        }
+    }
+    Ok(())
+}
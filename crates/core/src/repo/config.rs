@@ -1,82 +1,654 @@
-use anyhow::Context;
-use serde::Deserialize;
+use crate::error::{Context, Error, Result, bail};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
 use std::fs;
-use std::fs::{File, OpenOptions};
-use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-#[derive(Deserialize)]
-pub struct ConfigFields {
-    user_name: Option<String>,
-    user_email: Option<String>,
+const SYSTEM_CONFIG: &str = "/etc/fluxconfig";
+const GLOBAL_CONFIG: &str = ".fluxconfig";
+
+/// Sections whose keys flux knows about. `remote` and `branch` keys live in a
+/// subsection named after the remote or branch.
+const SECTIONS: [&str; 5] = ["user", "core", "remote", "branch", "alias"];
+
+/// Keys that flux used to write at the top level of the file, before it had sections.
+const LEGACY_KEYS: [(&str, &str); 2] = [("user_name", "user.name"), ("user_email", "user.email")];
+
+static COMMAND_LINE: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Where a configuration value comes from. Later scopes take precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigScope {
+    /// `/etc/fluxconfig`, or `$FLUX_CONFIG_SYSTEM`. Skipped when `$FLUX_CONFIG_NOSYSTEM` is set.
+    System,
+    /// `~/.fluxconfig`, or `$FLUX_CONFIG_GLOBAL`.
+    Global,
+    /// The `.git/config` of a repository flux works on directly. Only ever read.
+    Git,
+    /// The repository's own file: `.flux/config`, or `.git/fluxconfig`.
+    Repository,
+    /// `FLUX_CONFIG_COUNT`/`FLUX_CONFIG_KEY_<n>`/`FLUX_CONFIG_VALUE_<n>` environment
+    /// variables, then `-c key=value` options.
+    Override,
 }
 
+/// The configuration of a repository, layered from every [`ConfigScope`].
+///
+/// Files are TOML with one table per section (`[user]`, `[remote.origin]`, ...) and
+/// keys are named `section.key` or `section.subsection.key`. Section and key names are
+/// case-insensitive, subsection names are not.
 pub struct Config {
-    path: PathBuf,
-    pub user_name: Option<String>,
-    pub user_email: Option<String>,
+    /// The repository's config file, `None` outside of a repository.
+    path: Option<PathBuf>,
+    layers: Vec<(ConfigScope, BTreeMap<String, String>)>,
 }
 
 impl Config {
-    pub fn default(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+    /// Creates the repository config file at `path` and loads the configuration.
+    pub fn default(path: impl Into<PathBuf>) -> Result<Self> {
         let path = path.into();
 
-        let mut file = File::create(&path)
-            .with_context(|| format!("Cannot initialize config file at {:?}", path))?;
-
-        writeln!(
-            file,
+        fs::write(
+            &path,
             "\
-# Configuration file for git
+# Configuration file for flux
 # Values can be set either by modifying the file or by using the set command.
 #
-# user_name  =
-# user_email ="
-        )?;
+# [user]
+# name  =
+# email =
+",
+        )
+        .with_context(|| format!("Cannot initialize config file at {:?}", path))?;
 
-        Ok(Self {
-            path,
-            user_name: None,
-            user_email: None,
-        })
+        Self::from(path)
     }
 
-    pub fn from(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+    /// Loads the configuration of the repository whose config file is `path`.
+    pub fn from(path: impl Into<PathBuf>) -> Result<Self> {
         let path = path.into();
+        let mut config = Self::global()?;
+        let values = read_file(&path)?;
+        config.path = Some(path);
+        *config.layer(ConfigScope::Repository) = values;
+        Ok(config)
+    }
+
+    /// The configuration that applies outside of any repository: the system and global
+    /// files and the overrides.
+    pub fn global() -> Result<Self> {
+        let mut layers = Vec::new();
+        for scope in [ConfigScope::System, ConfigScope::Global] {
+            if let Some(file) = scope_path(scope)
+                && file.is_file()
+            {
+                layers.push((scope, read_file(&file)?));
+            }
+        }
+        layers.push((ConfigScope::Override, overrides()?));
+
+        Ok(Self { path: None, layers })
+    }
+
+    /// Uses a git config file as a layer below the repository's own file, so that
+    /// `user.*`, `remote.*` and the rest of git's settings apply unless flux overrides
+    /// them. The git file is only read.
+    pub fn inherit_git_config(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Could not read git config file {:?}", path))?;
+
+        let mut values = BTreeMap::new();
+        let mut section = String::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some((header, _)) = line.strip_prefix('[').and_then(|l| l.split_once(']')) {
+                section = match header.split_once(' ') {
+                    Some((name, subsection)) => format!(
+                        "{}.{}",
+                        name.to_lowercase(),
+                        subsection.trim().trim_matches('"')
+                    ),
+                    None => header.to_lowercase(),
+                };
+                continue;
+            }
+
+            // A key without a value is a boolean set to true.
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key, git_value(value)),
+                None => (
+                    line.split(['#', ';']).next().unwrap_or(line),
+                    "true".to_string(),
+                ),
+            };
+            let key = key.trim().to_lowercase();
+            values.insert(format!("{section}.{key}"), value);
+        }
 
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Could not read config file {:?}", path))?;
+        let position = self
+            .layers
+            .iter()
+            .position(|(scope, _)| *scope > ConfigScope::Git)
+            .unwrap_or(self.layers.len());
+        self.layers.insert(position, (ConfigScope::Git, values));
+        Ok(())
+    }
 
-        let fields: ConfigFields =
-            toml::from_str(&content).with_context(|| "Failed parsing config file")?;
+    /// The value of `key` from the scope with the highest precedence that sets it.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        let key = normalize_key(key).ok()?;
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(_, values)| values.get(&key))
+            .map(String::as_str)
+    }
 
-        Ok(Self {
-            path,
-            user_name: fields.user_name,
-            user_email: fields.user_email,
-        })
+    /// A boolean value: `true`/`yes`/`on`/`1` or `false`/`no`/`off`/`0`.
+    pub fn get_bool(&self, key: &str) -> Result<Option<bool>> {
+        let Some(value) = self.get_str(key) else {
+            return Ok(None);
+        };
+        match value.to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Ok(Some(true)),
+            "false" | "no" | "off" | "0" | "" => Ok(Some(false)),
+            _ => bail!(
+                Error::InvalidConfig,
+                "Invalid boolean '{value}' for config key '{key}'"
+            ),
+        }
     }
 
-    pub fn set(&self, key: String, value: String) -> anyhow::Result<()> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open(&self.path)?;
+    /// An integer value, optionally scaled by a `k`, `m` or `g` suffix.
+    pub fn get_int(&self, key: &str) -> Result<Option<i64>> {
+        let Some(value) = self.get_str(key) else {
+            return Ok(None);
+        };
+        let (digits, scale) = match value.to_lowercase().chars().last() {
+            Some('k') => (&value[..value.len() - 1], 1 << 10),
+            Some('m') => (&value[..value.len() - 1], 1 << 20),
+            Some('g') => (&value[..value.len() - 1], 1 << 30),
+            _ => (value, 1),
+        };
+        let invalid =
+            || Error::InvalidConfig(format!("Invalid integer '{value}' for config key '{key}'"));
+        let number: i64 = digits.trim().parse().map_err(|_| invalid())?;
+        number.checked_mul(scale).map(Some).ok_or_else(invalid)
+    }
 
-        writeln!(file, r#"{key} = "{value}""#)?;
+    /// The keys and values of `section` across all scopes, keyed by what follows
+    /// `section.`, e.g. `st` for `alias.st` or `origin.url` for `remote.origin.url`.
+    pub fn section(&self, section: &str) -> BTreeMap<String, String> {
+        let prefix = format!("{}.", section.to_lowercase());
+        let mut entries = BTreeMap::new();
+        for (_, values) in &self.layers {
+            for (key, value) in values {
+                if let Some(name) = key.strip_prefix(&prefix) {
+                    entries.insert(name.to_string(), value.clone());
+                }
+            }
+        }
+        entries
+    }
+
+    /// The names of the subsections of `section`, such as the remotes for `remote`.
+    pub fn subsections(&self, section: &str) -> Vec<String> {
+        let names: BTreeSet<String> = self
+            .section(section)
+            .keys()
+            .filter_map(|key| key.rsplit_once('.').map(|(name, _)| name.to_string()))
+            .collect();
+        names.into_iter().collect()
+    }
+
+    pub fn user_name(&self) -> Option<&str> {
+        self.get_str("user.name")
+    }
+
+    pub fn user_email(&self) -> Option<&str> {
+        self.get_str("user.email")
+    }
+
+    /// Remote names mapped to their URLs, from the `remote.<name>.url` keys.
+    pub fn remotes(&self) -> BTreeMap<String, String> {
+        self.subsections("remote")
+            .into_iter()
+            .filter_map(|name| {
+                let url = self.get_str(&format!("remote.{name}.url"))?.to_string();
+                Some((name, url))
+            })
+            .collect()
+    }
+
+    /// Sets `key` in the repository's config file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.set_in(ConfigScope::Repository, key, value)
+    }
+
+    /// Sets `key` in the file of `scope`, replacing the line that sets it if there is
+    /// one (and dropping any duplicates) so that comments and ordering are kept.
+    pub fn set_in(&mut self, scope: ConfigScope, key: &str, value: &str) -> Result<()> {
+        let key = validate_key(key)?;
+        let path = self.writable_path(scope)?;
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let content = edit(&content, &key, Some(value));
+        fs::write(&path, content).with_context(|| format!("Could not write {:?}", path))?;
+
+        self.layer(scope).insert(key, value.to_string());
         Ok(())
     }
 
-    pub fn get(&self) -> (String, String) {
-        (
-            self.user_name
-                .clone()
-                .with_context(|| "The user_name field needs to be set")
-                .unwrap(),
-            self.user_email
-                .clone()
-                .with_context(|| "The user_email field needs to be set")
-                .unwrap(),
-        )
+    /// Removes `key` from the repository's config file.
+    pub fn unset(&mut self, key: &str) -> Result<()> {
+        self.unset_in(ConfigScope::Repository, key)
+    }
+
+    /// Removes `key` from the file of `scope`, which has to set it.
+    pub fn unset_in(&mut self, scope: ConfigScope, key: &str) -> Result<()> {
+        let key = normalize_key(key)?;
+        if self.layer(scope).remove(&key).is_none() {
+            return Err(Error::ConfigMissing(key));
+        }
+        let path = self.writable_path(scope)?;
+        let content = fs::read_to_string(&path)?;
+        fs::write(&path, edit(&content, &key, None))?;
+        Ok(())
+    }
+
+    pub fn add_remote(&mut self, name: &str, url: &str) -> Result<()> {
+        if self.remotes().contains_key(name) {
+            bail!("Remote '{name}' already exists");
+        }
+        self.set(&format!("remote.{name}.url"), url)
+    }
+
+    /// Removes the `[remote.<name>]` table, up to the next table header.
+    pub fn remove_remote(&mut self, name: &str) -> Result<()> {
+        if !self.remotes().contains_key(name) {
+            return Err(Error::ConfigMissing(format!("remote.{name}.url")));
+        }
+
+        let prefix = format!("remote.{name}.");
+        for (_, values) in &mut self.layers {
+            values.retain(|key, _| !key.starts_with(&prefix));
+        }
+
+        let path = self.writable_path(ConfigScope::Repository)?;
+        let content = fs::read_to_string(&path)?;
+        let mut kept = Vec::new();
+        let mut in_section = false;
+        for line in content.lines() {
+            if let Some(table) = parse_header(line) {
+                in_section = table == ("remote".to_string(), Some(name.to_string()));
+            }
+            if !in_section {
+                kept.push(line);
+            }
+        }
+        while kept.last().is_some_and(|line| line.trim().is_empty()) {
+            kept.pop();
+        }
+
+        fs::write(&path, kept.join("\n") + "\n")?;
+        Ok(())
+    }
+
+    fn writable_path(&self, scope: ConfigScope) -> Result<PathBuf> {
+        match scope {
+            ConfigScope::Repository => self.path.clone().context("Not in a repository"),
+            ConfigScope::System | ConfigScope::Global => {
+                scope_path(scope).context("Could not locate the config file")
+            }
+            ConfigScope::Git | ConfigScope::Override => {
+                bail!("{scope:?} configuration cannot be written")
+            }
+        }
+    }
+
+    fn layer(&mut self, scope: ConfigScope) -> &mut BTreeMap<String, String> {
+        let position = match self.layers.iter().position(|(s, _)| *s >= scope) {
+            Some(position) if self.layers[position].0 == scope => position,
+            Some(position) => {
+                self.layers.insert(position, (scope, BTreeMap::new()));
+                position
+            }
+            None => {
+                self.layers.push((scope, BTreeMap::new()));
+                self.layers.len() - 1
+            }
+        };
+        &mut self.layers[position].1
+    }
+}
+
+/// Sets the `-c key=value` options given on the command line. They take precedence over
+/// every file and over the environment.
+pub fn set_command_line_overrides(overrides: Vec<(String, String)>) -> Result<()> {
+    for (key, _) in &overrides {
+        normalize_key(key)?;
+    }
+    COMMAND_LINE
+        .set(overrides)
+        .ok()
+        .context("Command line configuration was already set")
+}
+
+fn scope_path(scope: ConfigScope) -> Option<PathBuf> {
+    match scope {
+        ConfigScope::System => {
+            if env::var_os("FLUX_CONFIG_NOSYSTEM").is_some() {
+                return None;
+            }
+            Some(
+                env::var_os("FLUX_CONFIG_SYSTEM")
+                    .map_or(PathBuf::from(SYSTEM_CONFIG), PathBuf::from),
+            )
+        }
+        ConfigScope::Global => match env::var_os("FLUX_CONFIG_GLOBAL") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => env::var_os("HOME").map(|home| PathBuf::from(home).join(GLOBAL_CONFIG)),
+        },
+        _ => None,
+    }
+}
+
+fn overrides() -> Result<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+
+    if let Ok(count) = env::var("FLUX_CONFIG_COUNT") {
+        let count: usize = count.parse().context("Invalid FLUX_CONFIG_COUNT")?;
+        for i in 0..count {
+            let key = env::var(format!("FLUX_CONFIG_KEY_{i}"))
+                .with_context(|| format!("Missing FLUX_CONFIG_KEY_{i}"))?;
+            let value = env::var(format!("FLUX_CONFIG_VALUE_{i}")).unwrap_or_default();
+            values.insert(normalize_key(&key)?, value);
+        }
+    }
+    for (key, value) in COMMAND_LINE.get().into_iter().flatten() {
+        values.insert(normalize_key(key)?, value.clone());
+    }
+
+    Ok(values)
+}
+
+/// Reads a TOML config file into `section.[subsection.]key` values.
+fn read_file(path: &Path) -> Result<BTreeMap<String, String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Could not read config file {:?}", path))?;
+    let table: toml::Table = toml::from_str(&content)
+        .with_context(|| format!("Failed parsing config file {:?}", path))?;
+
+    let mut values = BTreeMap::new();
+    for (name, value) in table {
+        let section = name.to_lowercase();
+        let toml::Value::Table(entries) = value else {
+            match LEGACY_KEYS.iter().find(|(legacy, _)| *legacy == name) {
+                Some((_, key)) => {
+                    values.insert(key.to_string(), scalar(&name, value)?);
+                    continue;
+                }
+                None => bail!(
+                    Error::InvalidConfig,
+                    "Config key '{name}' in {path:?} is not in a [section]"
+                ),
+            }
+        };
+
+        for (name, value) in entries {
+            match value {
+                toml::Value::Table(subsection) => {
+                    for (key, value) in subsection {
+                        let full_key = format!("{section}.{name}.{}", key.to_lowercase());
+                        values.insert(full_key.clone(), scalar(&full_key, value)?);
+                    }
+                }
+                value => {
+                    let full_key = format!("{section}.{}", name.to_lowercase());
+                    values.insert(full_key.clone(), scalar(&full_key, value)?);
+                }
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn scalar(key: &str, value: toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(value) => value,
+        toml::Value::Integer(value) => value.to_string(),
+        toml::Value::Float(value) => value.to_string(),
+        toml::Value::Boolean(value) => value.to_string(),
+        toml::Value::Datetime(value) => value.to_string(),
+        _ => bail!(
+            Error::InvalidConfig,
+            "Config key '{key}' must be a string, number or boolean"
+        ),
+    })
+}
+
+/// Splits a key into its section, subsection and name.
+fn split_key(key: &str) -> Result<(String, Option<String>, String)> {
+    if let Some((_, key)) = LEGACY_KEYS.iter().find(|(legacy, _)| *legacy == key) {
+        return split_key(key);
+    }
+    let (section, rest) = key.split_once('.').ok_or_else(|| {
+        Error::InvalidConfig(format!(
+            "Config key '{key}' has no section, use section.key"
+        ))
+    })?;
+    let (subsection, name) = match rest.rsplit_once('.') {
+        Some((subsection, name)) => (Some(subsection.to_string()), name),
+        None => (None, rest),
+    };
+    if section.is_empty() || name.is_empty() || subsection.as_deref() == Some("") {
+        bail!(Error::InvalidConfig, "Invalid config key '{key}'");
+    }
+    Ok((section.to_lowercase(), subsection, name.to_lowercase()))
+}
+
+fn normalize_key(key: &str) -> Result<String> {
+    let (section, subsection, name) = split_key(key)?;
+    Ok(match subsection {
+        Some(subsection) => format!("{section}.{subsection}.{name}"),
+        None => format!("{section}.{name}"),
+    })
+}
+
+/// Normalizes a key that is about to be written, rejecting the ones flux does not know.
+fn validate_key(key: &str) -> Result<String> {
+    let (section, subsection, name) = split_key(key)?;
+    let known = match (section.as_str(), &subsection) {
+        ("user", None) => ["name", "email"].contains(&name.as_str()),
+        ("core" | "alias", None) => true,
+        ("remote", Some(_)) => ["url", "fetch", "pushurl"].contains(&name.as_str()),
+        ("branch", Some(_)) => ["remote", "merge"].contains(&name.as_str()),
+        _ => false,
+    };
+    if !known {
+        if !SECTIONS.contains(&section.as_str()) {
+            bail!(
+                Error::InvalidConfig,
+                "Unknown config section '{section}', expected one of {}",
+                SECTIONS.join(", ")
+            );
+        }
+        bail!(Error::InvalidConfig, "Unknown config key '{key}'");
+    }
+    normalize_key(key)
+}
+
+/// The section and subsection of a `[section]` or `[section.subsection]` header line.
+fn parse_header(line: &str) -> Option<(String, Option<String>)> {
+    let header = line.trim().strip_prefix('[')?.strip_suffix(']')?.trim();
+    if header.starts_with('[') {
+        return None;
     }
+    let (section, subsection) = match header.split_once('.') {
+        Some((section, subsection)) => (section, Some(unquote(subsection.trim()))),
+        None => (header, None),
+    };
+    Some((unquote(section.trim()).to_lowercase(), subsection))
+}
+
+/// The key a `key = value` line sets.
+fn parse_key_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.starts_with('#') || line.starts_with('[') {
+        return None;
+    }
+    let (key, _) = line.split_once('=')?;
+    Some(unquote(key.trim()))
+}
+
+/// A value of a git config file: quotes are removed, `\\` escapes are applied and a
+/// `#` or `;` outside quotes starts a comment, as in git.
+fn git_value(raw: &str) -> String {
+    let mut value = String::new();
+    // the length of the value without its unquoted trailing whitespace
+    let mut kept = 0;
+    let mut quoted = false;
+    let mut chars = raw.trim_start().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('b') => value.push('\u{8}'),
+                Some(c) => value.push(c),
+                None => break,
+            },
+            '#' | ';' if !quoted => break,
+            c => value.push(c),
+        }
+        if quoted || !c.is_whitespace() {
+            kept = value.len();
+        }
+    }
+    value.truncate(kept);
+    value
+}
+
+fn unquote(text: &str) -> String {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+        .to_string()
+}
+
+/// Quotes names that are not TOML bare keys.
+fn quote_name(name: &str) -> String {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        name.to_string()
+    } else {
+        toml_string(name)
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Rewrites the lines of `content` that set `key`: the first one is replaced by the new
+/// value (or removed when `value` is `None`) and any other one is dropped. A key that is
+/// not set yet goes at the end of its table, which is created if needed.
+fn edit(content: &str, key: &str, value: Option<&str>) -> String {
+    let (section, subsection, name) = split_key(key).expect("key was validated");
+    let table = (section.clone(), subsection.clone());
+    let legacy = LEGACY_KEYS
+        .iter()
+        .find(|(_, normalized)| *normalized == key)
+        .map(|(legacy, _)| *legacy);
+    let new_line = value.map(|value| format!("{} = {}", quote_name(&name), toml_string(value)));
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut current: Option<(String, Option<String>)> = None;
+    let mut replaced = false;
+    let mut table_end = None;
+
+    for line in content.lines() {
+        if let Some(header) = parse_header(line) {
+            current = Some(header);
+            lines.push(line.to_string());
+            if current.as_ref() == Some(&table) {
+                table_end = Some(lines.len());
+            }
+            continue;
+        }
+
+        let sets_key = match (&current, parse_key_line(line)) {
+            (Some(header), Some(line_key)) => {
+                *header == table && line_key.eq_ignore_ascii_case(&name)
+            }
+            (None, Some(line_key)) => Some(line_key.as_str()) == legacy,
+            _ => false,
+        };
+        if sets_key {
+            if !replaced && current.as_ref() == Some(&table) {
+                if let Some(new_line) = &new_line {
+                    lines.push(new_line.clone());
+                    table_end = Some(lines.len());
+                }
+                replaced = true;
+            }
+            continue;
+        }
+
+        lines.push(line.to_string());
+        if current.as_ref() == Some(&table) && !line.trim().is_empty() {
+            table_end = Some(lines.len());
+        }
+    }
+
+    // Dropping legacy top-level keys can leave the file starting with blank lines.
+    let leading_blanks = lines
+        .iter()
+        .take_while(|line| line.trim().is_empty())
+        .count();
+    lines.drain(..leading_blanks);
+    table_end = table_end.map(|end| end - leading_blanks);
+
+    if let (false, Some(new_line)) = (replaced, new_line) {
+        match table_end {
+            Some(end) => lines.insert(end, new_line),
+            None => {
+                while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                    lines.pop();
+                }
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                let header = match &subsection {
+                    Some(subsection) => format!("[{section}.{}]", quote_name(subsection)),
+                    None => format!("[{section}]"),
+                };
+                lines.push(header);
+                lines.push(new_line);
+            }
+        }
+    }
+
+    lines.join("\n") + "\n"
 }
@@ -0,0 +1,155 @@
+use crate::error::{Error, Result, bail};
+use crate::repo::repository::{FLUX_DIR, GIT_DIR, Repository};
+use std::env;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+
+/// Overrides the store directory (`.flux` or `.git`) found by discovery.
+const DIR_VARIABLE: &str = "FLUX_DIR";
+/// Overrides the work tree, which is the parent of the store directory otherwise.
+const WORK_TREE_VARIABLE: &str = "FLUX_WORK_TREE";
+
+/// Where a repository was found, and where flux was run from inside of it.
+pub(crate) struct Location {
+    pub work_tree: PathBuf,
+    pub store_dir: PathBuf,
+    /// The starting directory relative to `work_tree`, `/`-separated (empty at the top).
+    pub prefix: String,
+}
+
+/// Looks for the repository containing `path` (the current directory by default):
+/// the directory itself, then each of its parents until one holds a `.flux` or
+/// `.git` store. The search does not cross into another filesystem.
+///
+/// When no path is given, `FLUX_DIR` and `FLUX_WORK_TREE` take the place of the
+/// search. An explicit path is always searched, so opening another repository by
+/// its path (a local remote, a clone source) is not redirected.
+pub(crate) fn discover(path: Option<&str>) -> Result<Location> {
+    let start = match path {
+        Some(path) => PathBuf::from(path),
+        None => env::current_dir()?,
+    };
+    let start = fs::canonicalize(&start).map_err(|_| Error::NotARepository(start))?;
+
+    let (work_tree, store_dir) = match (path, env_path(DIR_VARIABLE)) {
+        (None, Some(store_dir)) => {
+            let store_dir = fs::canonicalize(&store_dir)
+                .ok()
+                .filter(|dir| dir.join("HEAD").is_file())
+                .ok_or(Error::NotARepository(store_dir))?;
+            let work_tree = store_dir
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| store_dir.clone());
+            (work_tree, store_dir)
+        }
+        _ => search(&start)?,
+    };
+
+    let work_tree = match env_path(WORK_TREE_VARIABLE).filter(|_| path.is_none()) {
+        Some(work_tree) => {
+            fs::canonicalize(&work_tree).map_err(|_| Error::NotARepository(work_tree))?
+        }
+        None => work_tree,
+    };
+
+    let prefix = start
+        .strip_prefix(&work_tree)
+        .map(|prefix| components(prefix).join("/"))
+        .unwrap_or_default();
+
+    Ok(Location {
+        work_tree,
+        store_dir,
+        prefix,
+    })
+}
+
+/// Whether a store directory uses git's layout (binary index, git's `config`). Only
+/// `.flux` directories use flux's own.
+pub(crate) fn is_git_store(store_dir: &Path) -> bool {
+    !store_dir.ends_with(FLUX_DIR)
+}
+
+/// The store directory of `dir`, if it is the top of a repository. A `.flux` store
+/// is preferred over a `.git` one.
+pub(crate) fn store_in(dir: &Path) -> Option<PathBuf> {
+    if dir.join(FLUX_DIR).is_dir() {
+        Some(dir.join(FLUX_DIR))
+    } else if dir.join(GIT_DIR).join("HEAD").is_file() {
+        Some(dir.join(GIT_DIR))
+    } else {
+        None
+    }
+}
+
+fn search(start: &Path) -> Result<(PathBuf, PathBuf)> {
+    let device = fs::metadata(start)?.dev();
+
+    for dir in start.ancestors() {
+        if fs::metadata(dir).map(|metadata| metadata.dev()).ok() != Some(device) {
+            break;
+        }
+        if let Some(store_dir) = store_in(dir) {
+            return Ok((dir.to_path_buf(), store_dir));
+        }
+    }
+
+    Err(Error::NotARepository(start.to_path_buf()))
+}
+
+fn env_path(variable: &str) -> Option<PathBuf> {
+    env::var_os(variable)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+impl Repository {
+    /// The index path of a path given by the user. Relative paths start from where
+    /// flux was run, absolute ones have to be inside the work tree. The top of the
+    /// work tree is the empty path.
+    pub fn resolve_path(&self, path: &str) -> Result<String> {
+        let parts = if Path::new(path).is_absolute() {
+            let work_tree = components(&fs::canonicalize(&self.work_tree)?);
+            components_from(Path::new(path), Vec::new())
+                .and_then(|parts| parts.strip_prefix(work_tree.as_slice()).map(<[_]>::to_vec))
+        } else {
+            components_from(Path::new(path), components(Path::new(&self.prefix)))
+        };
+
+        match parts {
+            Some(parts) => Ok(parts.join("/")),
+            None => bail!("Path '{path}' is outside the repository"),
+        }
+    }
+
+    /// `path`, relative to the top of the work tree, as seen from where flux was run.
+    pub fn relative_path(&self, path: &str) -> String {
+        let prefix = components(Path::new(&self.prefix));
+        let path = components(Path::new(path));
+        let common = prefix.iter().zip(&path).take_while(|(a, b)| a == b).count();
+
+        let mut parts = vec![".."; prefix.len() - common];
+        parts.extend(path[common..].iter().map(String::as_str));
+        parts.join("/")
+    }
+}
+
+fn components(path: &Path) -> Vec<String> {
+    components_from(path, Vec::new()).unwrap_or_default()
+}
+
+/// Applies the components of `path` to `base`, or `None` when `..` goes above it.
+fn components_from(path: &Path, mut parts: Vec<String>) -> Option<Vec<String>> {
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    Some(parts)
+}
@@ -0,0 +1,70 @@
+use crate::error::{Result, bail};
+use crate::repo::config::Config;
+use crate::repo::repository::Repository;
+use std::env;
+use std::fs;
+use std::process::Command;
+
+const MESSAGE_HELP: &str = "\
+# Please enter the commit message for your changes. Lines starting
+# with '#' will be ignored, and an empty message aborts the commit.
+";
+
+impl Config {
+    /// The command used to edit text: `FLUX_EDITOR`, `core.editor`, `VISUAL`, `EDITOR`,
+    /// then `vi`.
+    pub fn editor(&self) -> String {
+        non_empty(env::var("FLUX_EDITOR").ok())
+            .or_else(|| non_empty(self.get_str("core.editor").map(String::from)))
+            .or_else(|| non_empty(env::var("VISUAL").ok()))
+            .or_else(|| non_empty(env::var("EDITOR").ok()))
+            .unwrap_or_else(|| "vi".to_string())
+    }
+}
+
+/// An empty or blank setting counts as unset.
+fn non_empty(editor: Option<String>) -> Option<String> {
+    editor.filter(|editor| !editor.trim().is_empty())
+}
+
+impl Repository {
+    /// Opens `text` in the editor and returns it once the editor exits. The text is kept
+    /// in `file_name` inside the store directory while it is edited.
+    pub fn edit_text(&self, file_name: &str, text: &str) -> Result<String> {
+        let path = self.store_dir.join(file_name);
+        fs::write(&path, text)?;
+
+        let editor = self.config.editor();
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{editor} \"$@\""))
+            .arg(&editor)
+            .arg(&path)
+            .current_dir(&self.work_tree)
+            .status()?;
+        if !status.success() {
+            bail!("There was a problem with the editor '{editor}'");
+        }
+
+        let edited = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+        Ok(edited)
+    }
+
+    /// Opens a commit message in the editor, with a note on how it is used, and returns
+    /// it without the `#` lines and the trailing blank space. Fails when it is empty.
+    pub fn edit_message(&self, message: &str) -> Result<String> {
+        let text = format!("{}\n\n{MESSAGE_HELP}", message.trim_end());
+        let edited = self.edit_text("COMMIT_EDITMSG", &text)?;
+
+        let message: Vec<&str> = edited
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+        let message = message.join("\n").trim().to_string();
+        if message.is_empty() {
+            bail!("Aborting commit due to empty commit message");
+        }
+        Ok(message)
+    }
+}
@@ -0,0 +1,186 @@
+use crate::error::Result;
+use crate::objects::{commit, walk};
+use crate::repo::repository::Repository;
+use crate::shared::types::object_type::ObjectType;
+use crate::utils;
+use std::collections::{HashMap, HashSet};
+
+const GITLINK_MODE: &str = "160000";
+
+impl Repository {
+    /// Writes every branch and tag as a `git fast-import` stream: blobs and commits
+    /// carry marks, parents come before their children, and each commit lists its
+    /// changes against its first parent.
+    pub fn fast_export(&self) -> Result<Vec<u8>> {
+        let mut refs = self.refs.iter("refs/heads/")?;
+        refs.extend(self.refs.iter("refs/tags/")?);
+
+        let mut tips = Vec::new();
+        for reference in &refs {
+            if let Some(target) = &reference.target
+                && let Some(commit) = self.peeled_commit(target)?
+            {
+                tips.push((reference.name.clone(), commit));
+            }
+        }
+
+        let mut out = Vec::new();
+        let mut marks: HashMap<String, usize> = HashMap::new();
+
+        for (ref_name, hash) in self.commits_parents_first(&tips)? {
+            let info = commit::parse_commit(&self.store_dir, &hash)?;
+            let files = self.commit_files(Some(&hash))?;
+            let parent_files = self.commit_files(info.parents.first().map(String::as_str))?;
+
+            let mut changes = Vec::new();
+            for (path, entry) in &files {
+                if parent_files.get(path) == Some(entry) {
+                    continue;
+                }
+                let data_ref = if entry.mode == GITLINK_MODE {
+                    entry.hash.clone()
+                } else {
+                    if !marks.contains_key(&entry.hash) {
+                        let mark = marks.len() + 1;
+                        marks.insert(entry.hash.clone(), mark);
+                        let blob = utils::read_object(&self.store_dir, &entry.hash)?;
+                        out.extend(format!("blob\nmark :{mark}\n").into_bytes());
+                        write_data(&mut out, &blob.decompressed_content);
+                    }
+                    format!(":{}", marks[&entry.hash])
+                };
+                changes.push(format!(
+                    "M {} {data_ref} {}\n",
+                    entry.mode,
+                    quote_path(path)
+                ));
+            }
+            for path in parent_files
+                .keys()
+                .filter(|path| !files.contains_key(*path))
+            {
+                changes.push(format!("D {}\n", quote_path(path)));
+            }
+
+            // A root commit must not continue from whatever the ref points to in the stream.
+            if info.parents.is_empty() {
+                out.extend(format!("reset {ref_name}\n").into_bytes());
+            }
+            let mark = marks.len() + 1;
+            marks.insert(hash.clone(), mark);
+            out.extend(
+                format!(
+                    "commit {ref_name}\nmark :{mark}\nauthor {}\ncommitter {}\n",
+                    info.author, info.committer
+                )
+                .into_bytes(),
+            );
+            write_data(&mut out, info.message.as_bytes());
+
+            for (position, parent) in info.parents.iter().enumerate() {
+                let command = if position == 0 { "from" } else { "merge" };
+                out.extend(format!("{command} :{}\n", marks[parent]).into_bytes());
+            }
+            for change in changes {
+                out.extend(change.into_bytes());
+            }
+            out.push(b'\n');
+        }
+
+        for reference in &refs {
+            let Some(target) = &reference.target else {
+                continue;
+            };
+            let Some(commit) = self.peeled_commit(target)? else {
+                continue;
+            };
+            let mark = marks[&commit];
+
+            let object = utils::read_object(&self.store_dir, target)?;
+            if object.object_type == ObjectType::Tag {
+                let content = String::from_utf8(object.decompressed_content)?;
+                let (headers, message) = content.split_once("\n\n").unwrap_or((&content, ""));
+
+                out.extend(format!("tag {}\nfrom :{mark}\n", reference.short_name()).into_bytes());
+                if let Some(tagger) = headers.lines().find_map(|l| l.strip_prefix("tagger ")) {
+                    out.extend(format!("tagger {tagger}\n").into_bytes());
+                }
+                write_data(&mut out, message.as_bytes());
+            } else {
+                out.extend(format!("reset {}\nfrom :{mark}\n", reference.name).into_bytes());
+            }
+            out.push(b'\n');
+        }
+
+        Ok(out)
+    }
+
+    /// The commit a ref target leads to once annotated tags are followed,
+    /// `None` for tags of trees or blobs.
+    pub(crate) fn peeled_commit(&self, target: &str) -> Result<Option<String>> {
+        let mut hash = target.to_string();
+        loop {
+            let object = utils::read_object(&self.store_dir, &hash)?;
+            match object.object_type {
+                ObjectType::Commit => return Ok(Some(hash)),
+                ObjectType::Tag => hash = walk::tag_target(&object.decompressed_content)?,
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// All commits reachable from `tips`, each paired with the first ref it was reached
+    /// from, ordered so that parents always come before their children.
+    fn commits_parents_first(&self, tips: &[(String, String)]) -> Result<Vec<(String, String)>> {
+        let mut ordered = Vec::new();
+        let mut visited = HashSet::new();
+
+        for (ref_name, tip) in tips {
+            let mut stack = vec![(tip.clone(), false)];
+            while let Some((hash, parents_done)) = stack.pop() {
+                if parents_done {
+                    ordered.push((ref_name.clone(), hash));
+                    continue;
+                }
+                if !visited.insert(hash.clone()) {
+                    continue;
+                }
+
+                stack.push((hash.clone(), true));
+                let parents = commit::get_parent_hashes(&self.store_dir, &hash)?;
+                for parent in parents.into_iter().rev() {
+                    if !visited.contains(&parent) {
+                        stack.push((parent, false));
+                    }
+                }
+            }
+        }
+
+        Ok(ordered)
+    }
+}
+
+fn write_data(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend(format!("data {}\n", data.len()).into_bytes());
+    out.extend_from_slice(data);
+    out.push(b'\n');
+}
+
+/// Paths are quoted C-style when they could not be read back verbatim.
+fn quote_path(path: &str) -> String {
+    if !path.starts_with('"') && !path.contains('\n') {
+        return path.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,391 @@
+use crate::error::{Context, Result, bail};
+use crate::objects::{commit, tree};
+use crate::repo::repository::Repository;
+use crate::shared::types::index_entry::IndexEntry;
+use crate::shared::types::object_type::ObjectType;
+use crate::shared::types::signature::Signature;
+use crate::utils;
+use std::collections::{BTreeMap, HashMap};
+
+/// A `git fast-import` stream being read line by line, `data` blocks included.
+struct Stream<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn peek_line(&self) -> Result<Option<&'a str>> {
+        if self.pos >= self.data.len() {
+            return Ok(None);
+        }
+        let rest = &self.data[self.pos..];
+        let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        Ok(Some(
+            std::str::from_utf8(&rest[..end]).context("Non UTF-8 command in stream")?,
+        ))
+    }
+
+    fn next_line(&mut self) -> Result<Option<&'a str>> {
+        let line = self.peek_line()?;
+        if let Some(line) = line {
+            self.pos += line.len() + 1;
+        }
+        Ok(line)
+    }
+
+    /// Consumes the next line if it starts with `prefix`, returning the rest of it.
+    fn next_if(&mut self, prefix: &str) -> Result<Option<&'a str>> {
+        match self.peek_line()? {
+            Some(line) if line.starts_with(prefix) => {
+                self.pos += line.len() + 1;
+                Ok(Some(&line[prefix.len()..]))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads a `data <count>` or `data <<<delimiter>` block.
+    fn read_data(&mut self) -> Result<Vec<u8>> {
+        let header = self.next_if("data ")?.context("Expected a data command")?;
+
+        if let Some(delimiter) = header.strip_prefix("<<") {
+            let mut content = Vec::new();
+            loop {
+                let line = self
+                    .next_line()?
+                    .with_context(|| format!("Missing data delimiter '{delimiter}'"))?;
+                if line == delimiter {
+                    break;
+                }
+                content.extend_from_slice(line.as_bytes());
+                content.push(b'\n');
+            }
+            return Ok(content);
+        }
+
+        let count: usize = header.parse().context("Invalid data length")?;
+        let content = self
+            .pos
+            .checked_add(count)
+            .and_then(|end| self.data.get(self.pos..end))
+            .context("Truncated data block")?
+            .to_vec();
+        self.pos += count;
+        // The LF after the data is optional.
+        if self.data.get(self.pos) == Some(&b'\n') {
+            self.pos += 1;
+        }
+        Ok(content)
+    }
+}
+
+/// What an import has produced so far: marked objects and the refs it moved.
+struct Import {
+    marks: HashMap<String, String>,
+    /// `None` for a ref that was reset without a new target.
+    refs: BTreeMap<String, Option<String>>,
+}
+
+impl Repository {
+    /// Reads a `git fast-import` stream, writing its blobs, trees, commits and tags to
+    /// the object store and updating the refs it names. The index and the working tree
+    /// are left alone. Returns the updated refs with their new targets.
+    pub fn fast_import(&mut self, data: &[u8]) -> Result<Vec<(String, String)>> {
+        let mut stream = Stream { data, pos: 0 };
+        let mut import = Import {
+            marks: HashMap::new(),
+            refs: BTreeMap::new(),
+        };
+
+        while let Some(line) = stream.next_line()? {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+
+            match command {
+                "blob" => {
+                    let mark = read_mark(&mut stream)?;
+                    let content = stream.read_data()?;
+                    let hash =
+                        utils::write_raw_object(&self.store_dir, ObjectType::Blob, &content)?;
+                    if let Some(mark) = mark {
+                        import.marks.insert(mark, hash);
+                    }
+                }
+                "commit" => self.import_commit(&mut stream, &mut import, argument)?,
+                "tag" => self.import_tag(&mut stream, &mut import, argument)?,
+                "reset" => {
+                    let target = match stream.next_if("from ")? {
+                        Some(from) => Some(self.resolve_import_ref(&import, from)?),
+                        None => None,
+                    };
+                    import.refs.insert(argument.to_string(), target);
+                }
+                "feature" | "option" | "progress" | "checkpoint" => {}
+                "done" => break,
+                _ => bail!("Unsupported fast-import command '{command}'"),
+            }
+        }
+
+        let mut updated = Vec::new();
+        for (name, target) in import.refs {
+            if let Some(target) = target {
+                self.refs.write(&name, &target)?;
+                updated.push((name, target));
+            }
+        }
+        self.load_branches()?;
+        Ok(updated)
+    }
+
+    fn import_commit(
+        &self,
+        stream: &mut Stream,
+        import: &mut Import,
+        ref_name: &str,
+    ) -> Result<()> {
+        let mark = read_mark(stream)?;
+        stream.next_if("original-oid ")?;
+        let author = stream
+            .next_if("author ")?
+            .map(Signature::parse)
+            .transpose()?;
+        let committer = Signature::parse(
+            stream
+                .next_if("committer ")?
+                .context("Commit without a committer")?,
+        )?;
+        stream.next_if("encoding ")?;
+        let message = String::from_utf8(stream.read_data()?).context("Non UTF-8 commit message")?;
+
+        // Without `from`, a commit continues the ref it is made on.
+        let mut parents = Vec::new();
+        if let Some(from) = stream.next_if("from ")? {
+            parents.push(self.resolve_import_ref(import, from)?);
+        } else if let Some(tip) = match import.refs.get(ref_name) {
+            Some(tip) => tip.clone(),
+            None => self.refs.read(ref_name)?,
+        } {
+            parents.push(tip);
+        }
+        while let Some(merge) = stream.next_if("merge ")? {
+            parents.push(self.resolve_import_ref(import, merge)?);
+        }
+
+        let mut files = self.commit_files(parents.first().map(String::as_str))?;
+        while let Some(line) = stream.peek_line()? {
+            if line.is_empty() {
+                stream.next_line()?;
+                break;
+            }
+            if !(line.starts_with("M ")
+                || line.starts_with("D ")
+                || line.starts_with("C ")
+                || line.starts_with("R ")
+                || line == "deleteall")
+            {
+                break;
+            }
+            stream.next_line()?;
+            self.apply_file_change(stream, import, &mut files, line)?;
+        }
+
+        let tree_hash = tree::write_tree_from_entries(&self.store_dir, &files)?;
+        let hash = commit::write_commit(
+            &self.store_dir,
+            &tree_hash,
+            &parents,
+            author.as_ref().unwrap_or(&committer),
+            &committer,
+            &message,
+        )?;
+
+        if let Some(mark) = mark {
+            import.marks.insert(mark, hash.clone());
+        }
+        import.refs.insert(ref_name.to_string(), Some(hash));
+        Ok(())
+    }
+
+    fn apply_file_change(
+        &self,
+        stream: &mut Stream,
+        import: &Import,
+        files: &mut BTreeMap<String, IndexEntry>,
+        line: &str,
+    ) -> Result<()> {
+        if line == "deleteall" {
+            files.clear();
+            return Ok(());
+        }
+
+        let (command, rest) = line.split_at(2);
+        match command.trim() {
+            "M" => {
+                let (mode, rest) = rest.split_once(' ').context("Invalid filemodify")?;
+                let (data_ref, path) = rest.split_once(' ').context("Invalid filemodify")?;
+                let mode = match mode {
+                    "644" | "100644" => "100644",
+                    "755" | "100755" => "100755",
+                    "120000" | "160000" => mode,
+                    _ => bail!("Unsupported file mode {mode}"),
+                };
+
+                let hash = if data_ref == "inline" {
+                    let content = stream.read_data()?;
+                    utils::write_raw_object(&self.store_dir, ObjectType::Blob, &content)?
+                } else if data_ref.starts_with(':') {
+                    import
+                        .marks
+                        .get(data_ref)
+                        .with_context(|| format!("Unknown mark {data_ref}"))?
+                        .clone()
+                } else {
+                    data_ref.to_string()
+                };
+
+                files.insert(
+                    parse_path(path)?,
+                    IndexEntry {
+                        hash,
+                        mode: mode.to_string(),
+                    },
+                );
+            }
+            "D" => {
+                let path = parse_path(rest)?;
+                files.retain(|file, _| !is_within(file, &path));
+            }
+            "C" | "R" => {
+                let (source, rest) = split_path(rest)?;
+                let target = parse_path(rest.trim_start())?;
+
+                let moved: Vec<(String, IndexEntry)> = files
+                    .iter()
+                    .filter(|(file, _)| is_within(file, &source))
+                    .map(|(file, entry)| {
+                        (format!("{target}{}", &file[source.len()..]), entry.clone())
+                    })
+                    .collect();
+                if moved.is_empty() {
+                    bail!("Path '{source}' not found in commit");
+                }
+                if command.trim() == "R" {
+                    files.retain(|file, _| !is_within(file, &source));
+                }
+                files.extend(moved);
+            }
+            _ => unreachable!("only file commands are passed"),
+        }
+        Ok(())
+    }
+
+    fn import_tag(&self, stream: &mut Stream, import: &mut Import, name: &str) -> Result<()> {
+        let mark = read_mark(stream)?;
+        let from = stream
+            .next_if("from ")?
+            .context("Tag without a from command")?;
+        let target = self.resolve_import_ref(import, from)?;
+        stream.next_if("original-oid ")?;
+        let tagger = stream.next_if("tagger ")?;
+        let message = stream.read_data()?;
+
+        let target_type = utils::read_object(&self.store_dir, &target)?.object_type;
+        let mut content = format!(
+            "object {target}\ntype {}\ntag {name}\n",
+            target_type.as_str()
+        );
+        if let Some(tagger) = tagger {
+            content.push_str(&format!("tagger {tagger}\n"));
+        }
+        content.push('\n');
+        let mut content = content.into_bytes();
+        content.extend(message);
+
+        let hash = utils::write_raw_object(&self.store_dir, ObjectType::Tag, &content)?;
+        if let Some(mark) = mark {
+            import.marks.insert(mark, hash.clone());
+        }
+        import.refs.insert(format!("refs/tags/{name}"), Some(hash));
+        Ok(())
+    }
+
+    /// Resolves the argument of `from`/`merge`: a mark, a ref updated earlier in the
+    /// stream, or any revision of the repository.
+    fn resolve_import_ref(&self, import: &Import, commit_ish: &str) -> Result<String> {
+        if commit_ish.starts_with(':') {
+            return import
+                .marks
+                .get(commit_ish)
+                .cloned()
+                .with_context(|| format!("Unknown mark {commit_ish}"));
+        }
+        if let Some(Some(hash)) = import.refs.get(commit_ish) {
+            return Ok(hash.clone());
+        }
+        self.resolve_revision(commit_ish)
+    }
+}
+
+fn read_mark(stream: &mut Stream) -> Result<Option<String>> {
+    Ok(stream.next_if("mark ")?.map(String::from))
+}
+
+fn is_within(file: &str, path: &str) -> bool {
+    file == path || file.starts_with(&format!("{path}/"))
+}
+
+/// Reads a path spanning the rest of the line, possibly quoted C-style.
+fn parse_path(text: &str) -> Result<String> {
+    if text.starts_with('"') {
+        Ok(split_path(text)?.0)
+    } else {
+        Ok(text.to_string())
+    }
+}
+
+/// Reads the source path of a copy or rename, which ends at the first space unless
+/// it is quoted C-style, returning it with what follows it.
+fn split_path(text: &str) -> Result<(String, &str)> {
+    let Some(quoted) = text.strip_prefix('"') else {
+        return text
+            .split_once(' ')
+            .map(|(path, rest)| (path.to_string(), rest))
+            .context("Expected a source and a target path");
+    };
+
+    let mut bytes = Vec::new();
+    let mut chars = quoted.char_indices();
+    while let Some((position, c)) = chars.next() {
+        match c {
+            '"' => {
+                let path = String::from_utf8(bytes).context("Non UTF-8 path in stream")?;
+                return Ok((path, &quoted[position + 1..]));
+            }
+            '\\' => {
+                let (_, escaped) = chars.next().context("Unterminated quoted path")?;
+                match escaped {
+                    'n' => bytes.push(b'\n'),
+                    't' => bytes.push(b'\t'),
+                    '"' | '\\' => bytes.push(escaped as u8),
+                    '0'..='7' => {
+                        let mut value = escaped.to_digit(8).unwrap_or(0);
+                        for _ in 0..2 {
+                            let (_, digit) = chars.next().context("Invalid octal escape")?;
+                            value =
+                                value * 8 + digit.to_digit(8).context("Invalid octal escape")?;
+                        }
+                        bytes.push(value as u8);
+                    }
+                    other => bail!("Unsupported escape '\\{other}' in path"),
+                }
+            }
+            c => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    bail!("Unterminated quoted path")
+}
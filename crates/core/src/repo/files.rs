@@ -0,0 +1,159 @@
+use crate::error::{Error, Result, bail};
+use crate::repo::repository::Repository;
+use crate::utils;
+use std::fs;
+
+impl Repository {
+    /// Removes the tracked files selected by the pathspec `paths` from the index and,
+    /// unless `cached` is set, from the working tree. Returns the removed paths.
+    ///
+    /// Without `force`, files whose changes would be lost are refused: with `cached`,
+    /// those whose index content matches neither HEAD nor the working tree; otherwise
+    /// those with staged changes or local modifications.
+    pub fn remove_files(
+        &mut self,
+        paths: &[String],
+        cached: bool,
+        force: bool,
+    ) -> Result<Vec<String>> {
+        let pathspec = self.pathspec(paths)?;
+        let removed: Vec<String> = self
+            .index
+            .entries()
+            .into_keys()
+            .filter(|path| pathspec.matches(path))
+            .collect();
+        pathspec.check_matched(&removed)?;
+
+        if !force {
+            let head = self.commit_files(self.head_commit()?.as_deref())?;
+            for path in &removed {
+                let entry = &self.index.map[path];
+                let full_path = self.work_tree.join(path);
+                let worktree_hash = if utils::is_worktree_file(&full_path) {
+                    Some(utils::get_hash(
+                        &self.store_dir,
+                        &self.work_tree,
+                        &full_path,
+                    )?)
+                } else {
+                    None
+                };
+                let staged = head.get(path) != Some(entry);
+                let modified = worktree_hash
+                    .as_ref()
+                    .is_some_and(|hash| *hash != entry.hash);
+
+                if cached && staged && worktree_hash.as_ref() != Some(&entry.hash) {
+                    bail!(
+                        Error::DirtyWorkTree,
+                        "'{path}' has staged content different from both the file and HEAD (use -f to force removal)"
+                    );
+                }
+                if !cached && staged {
+                    bail!(
+                        Error::DirtyWorkTree,
+                        "'{path}' has changes staged in the index (use --cached to keep the file, or -f to force removal)"
+                    );
+                }
+                if !cached && modified {
+                    bail!(
+                        Error::DirtyWorkTree,
+                        "'{path}' has local modifications (use --cached to keep the file, or -f to force removal)"
+                    );
+                }
+            }
+        }
+
+        for path in &removed {
+            self.index.remove(path.clone())?;
+            if !cached {
+                self.remove_worktree_file(path)?;
+            }
+        }
+        self.index.flush()?;
+
+        Ok(removed)
+    }
+
+    /// Renames tracked files or directories in the working tree and the index. With
+    /// several sources, or when `destination` is an existing directory, the sources are
+    /// moved into it. Returns the `(from, to)` pairs of the renamed paths.
+    pub fn move_paths(
+        &mut self,
+        sources: &[String],
+        destination: &str,
+        force: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let destination = self.resolve_path(destination)?;
+        let into_directory = self.work_tree.join(&destination).is_dir();
+        if sources.len() > 1 && !into_directory {
+            bail!("Destination '{destination}' is not a directory");
+        }
+
+        // every move is checked before anything is renamed
+        let mut moves = Vec::new();
+        for source in sources {
+            let source = self.resolve_path(source)?;
+            let target = if into_directory {
+                let name = source.rsplit('/').next().unwrap_or_default();
+                if destination.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{destination}/{name}")
+                }
+            } else {
+                destination.clone()
+            };
+
+            if source.is_empty() || target == source {
+                bail!("Cannot move '{source}' onto itself");
+            }
+            if target.starts_with(&format!("{source}/")) {
+                bail!("Cannot move directory '{source}' into itself");
+            }
+            let entries: Vec<String> = self
+                .index
+                .entries()
+                .into_keys()
+                .filter(|path| *path == source || path.starts_with(&format!("{source}/")))
+                .collect();
+            if entries.is_empty() {
+                bail!("'{source}' is not under version control");
+            }
+            if !self.work_tree.join(&source).exists() {
+                bail!("'{source}' does not exist in the working tree");
+            }
+            let target_path = self.work_tree.join(&target);
+            if target_path.exists() && (!force || target_path.is_dir()) {
+                bail!("Destination '{target}' already exists");
+            }
+            if moves.iter().any(|(_, other, _)| *other == target) {
+                bail!("Cannot move several sources to '{target}'");
+            }
+            moves.push((source, target, entries));
+        }
+
+        for (source, target, entries) in &moves {
+            let target_path = self.work_tree.join(target);
+            if let Some(parent) = target_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(self.work_tree.join(source), &target_path)?;
+
+            for path in entries {
+                let entry = self.index.map[path].clone();
+                let renamed = format!("{target}{}", &path[source.len()..]);
+                self.index.remove(path.clone())?;
+                self.index.remove(renamed.clone())?;
+                self.index.add(renamed, entry.hash, entry.mode)?;
+            }
+        }
+
+        self.index.flush()?;
+        Ok(moves
+            .into_iter()
+            .map(|(source, target, _)| (source, target))
+            .collect())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::error::Result;
+use crate::repo::repository::Repository;
+use crate::repo::revwalk::Sort;
+use crate::shared::types::commit_info::CommitInfo;
+use std::collections::HashMap;
+
+impl Repository {
+    /// One line per commit, newest first, each prefixed by an ASCII drawing of the
+    /// lines of history and followed by the branches and tags pointing at it.
+    /// `revisions` are given to [`Repository::revwalk`].
+    pub fn log_graph(&self, revisions: &[String]) -> Result<String> {
+        let mut walk = self.revwalk(revisions)?;
+        walk.sort = Sort::Topological;
+        let decorations = self.decorations()?;
+
+        let mut graph = Graph::default();
+        for info in walk.commits()? {
+            let mut text = info.hash[..7].to_string();
+            if let Some(names) = decorations.get(&info.hash) {
+                text.push_str(&format!(" ({})", names.join(", ")));
+            }
+            text.push(' ');
+            text.push_str(info.summary());
+            graph.add_commit(&info, &text);
+        }
+
+        Ok(graph.rows.join("\n"))
+    }
+
+    /// The names pointing at each commit: `HEAD -> <branch>` first, then the other
+    /// branches, remote-tracking branches and `tag: <name>`.
+    fn decorations(&self) -> Result<HashMap<String, Vec<String>>> {
+        let mut decorations: HashMap<String, Vec<String>> = HashMap::new();
+        if let Some(head) = self.head_commit()? {
+            let branch = self.head.strip_prefix("refs/heads/").unwrap_or(&self.head);
+            decorations
+                .entry(head)
+                .or_default()
+                .push(format!("HEAD -> {branch}"));
+        }
+
+        for (prefix, label) in [
+            ("refs/heads/", ""),
+            ("refs/remotes/", ""),
+            ("refs/tags/", "tag: "),
+        ] {
+            for reference in self.refs.iter(prefix)? {
+                if reference.name == self.head {
+                    continue;
+                }
+                let Some(target) = &reference.target else {
+                    continue;
+                };
+                if let Some(commit) = self.peeled_commit(target)? {
+                    decorations
+                        .entry(commit)
+                        .or_default()
+                        .push(format!("{label}{}", reference.short_name()));
+                }
+            }
+        }
+
+        Ok(decorations)
+    }
+}
+
+/// Draws commits listed children first into lanes, one per line of history being
+/// followed. Each lane holds the commit it expects next.
+#[derive(Default)]
+struct Graph {
+    lanes: Vec<String>,
+    rows: Vec<String>,
+}
+
+impl Graph {
+    fn add_commit(&mut self, info: &CommitInfo, text: &str) {
+        let column = match self.lanes.iter().position(|lane| *lane == info.hash) {
+            Some(column) => column,
+            None => {
+                self.lanes.push(info.hash.clone());
+                self.lanes.len() - 1
+            }
+        };
+
+        let mut row = self.row('|');
+        row[2 * column] = '*';
+        self.push_row(row, text);
+
+        match info.parents.split_first() {
+            None => {
+                // The lanes to the right move over to fill the gap.
+                self.lanes.remove(column);
+                if column < self.lanes.len() {
+                    let mut row = self.row(' ');
+                    for lane in 0..column {
+                        row[2 * lane] = '|';
+                    }
+                    for lane in column..self.lanes.len() {
+                        row[2 * lane + 1] = '/';
+                    }
+                    self.push_row(row, "");
+                }
+            }
+            Some((first, others)) => {
+                self.lanes[column] = first.clone();
+                if !others.is_empty() {
+                    // Merged parents get new lanes next to the merge, pushing the
+                    // lanes to the right further out.
+                    for (offset, parent) in others.iter().enumerate() {
+                        self.lanes.insert(column + 1 + offset, parent.clone());
+                    }
+                    let mut row = self.row(' ');
+                    for lane in 0..=column {
+                        row[2 * lane] = '|';
+                    }
+                    for lane in column + 1..self.lanes.len() {
+                        row[2 * lane - 1] = '\\';
+                    }
+                    self.push_row(row, "");
+                }
+            }
+        }
+
+        self.collapse();
+    }
+
+    /// Lanes waiting for the same commit join the leftmost of them.
+    fn collapse(&mut self) {
+        while let Some((target, lane)) = (0..self.lanes.len()).rev().find_map(|lane| {
+            let target = self.lanes[..lane]
+                .iter()
+                .position(|other| *other == self.lanes[lane])?;
+            Some((target, lane))
+        }) {
+            let mut row = self.row(' ');
+            for other in 0..lane {
+                row[2 * other] = '|';
+            }
+            for gap in (2 * target + 1..2 * lane - 1).step_by(2) {
+                row[gap] = '_';
+            }
+            for other in lane..self.lanes.len() {
+                row[2 * other - 1] = '/';
+            }
+            self.lanes.remove(lane);
+            self.push_row(row, "");
+        }
+    }
+
+    /// An empty row as wide as the lanes, with a `lane` character in each of them.
+    fn row(&self, lane: char) -> Vec<char> {
+        let mut row = vec![' '; 2 * self.lanes.len()];
+        for column in 0..self.lanes.len() {
+            row[2 * column] = lane;
+        }
+        row
+    }
+
+    fn push_row(&mut self, row: Vec<char>, text: &str) {
+        let drawing: String = row.into_iter().collect();
+        self.rows.push(
+            format!("{} {text}", drawing.trim_end())
+                .trim_end()
+                .to_string(),
+        );
+    }
+}
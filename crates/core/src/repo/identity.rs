@@ -0,0 +1,157 @@
+use crate::repo::config::Config;
+use crate::shared::types::signature::Signature;
+use chrono::{DateTime, FixedOffset, Local};
+use std::env;
+use std::fmt;
+
+/// Whose signature is being resolved: the author wrote the change, the committer
+/// recorded it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Author,
+    Committer,
+}
+
+impl Role {
+    fn env_prefix(self) -> &'static str {
+        match self {
+            Role::Author => "FLUX_AUTHOR",
+            Role::Committer => "FLUX_COMMITTER",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Author => write!(f, "author"),
+            Role::Committer => write!(f, "committer"),
+        }
+    }
+}
+
+/// Why no signature could be made for a commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentityError {
+    MissingName(Role),
+    MissingEmail(Role),
+    /// An `--author` that is not of the form `Name <email>`.
+    InvalidIdent(String),
+    /// A `FLUX_*_DATE` variable that is not a date flux understands.
+    InvalidDate {
+        variable: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for IdentityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdentityError::MissingName(role) => write!(
+                f,
+                "No {role} name found: run `flux set user.name <name>` or set {}_NAME",
+                role.env_prefix()
+            ),
+            IdentityError::MissingEmail(role) => write!(
+                f,
+                "No {role} email found: run `flux set user.email <email>` or set {}_EMAIL",
+                role.env_prefix()
+            ),
+            IdentityError::InvalidIdent(ident) => {
+                write!(f, "Invalid author '{ident}', expected 'Name <email>'")
+            }
+            IdentityError::InvalidDate { variable, value } => {
+                write!(f, "Invalid date '{value}' in {variable}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IdentityError {}
+
+impl Config {
+    /// The author of a new commit. `ident` is an `--author` value (`Name <email>`)
+    /// that replaces the name and email found otherwise.
+    pub fn author(&self, ident: Option<&str>) -> Result<Signature, IdentityError> {
+        match ident {
+            Some(ident) => {
+                let (name, email) = parse_ident(ident)?;
+                Ok(Signature::at(name, email, date(Role::Author)?))
+            }
+            None => self.signature(Role::Author),
+        }
+    }
+
+    /// The committer of a new commit, also used for the reflog.
+    pub fn committer(&self) -> Result<Signature, IdentityError> {
+        self.signature(Role::Committer)
+    }
+
+    /// The name and email come from `FLUX_<ROLE>_NAME`/`FLUX_<ROLE>_EMAIL` when set,
+    /// otherwise from `user.name`/`user.email`, and the time from `FLUX_<ROLE>_DATE`
+    /// or the current time.
+    fn signature(&self, role: Role) -> Result<Signature, IdentityError> {
+        let name = env_value(role, "NAME")
+            .or_else(|| non_empty(self.user_name()))
+            .ok_or(IdentityError::MissingName(role))?;
+        let email = env_value(role, "EMAIL")
+            .or_else(|| non_empty(self.user_email()))
+            .ok_or(IdentityError::MissingEmail(role))?;
+
+        Ok(Signature::at(name, email, date(role)?))
+    }
+}
+
+fn env_value(role: Role, field: &str) -> Option<String> {
+    let value = env::var(format!("{}_{field}", role.env_prefix())).ok()?;
+    non_empty(Some(&value))
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+}
+
+fn parse_ident(ident: &str) -> Result<(String, String), IdentityError> {
+    let invalid = || IdentityError::InvalidIdent(ident.to_string());
+    let (name, rest) = ident.split_once('<').ok_or_else(invalid)?;
+    let email = rest.strip_suffix('>').ok_or_else(invalid)?;
+    let name = name.trim();
+    let email = email.trim();
+    if name.is_empty() || email.is_empty() || email.contains(['<', '>']) {
+        return Err(invalid());
+    }
+    Ok((name.to_string(), email.to_string()))
+}
+
+fn date(role: Role) -> Result<DateTime<FixedOffset>, IdentityError> {
+    let variable = format!("{}_DATE", role.env_prefix());
+    match env::var(&variable) {
+        Ok(value) if !value.trim().is_empty() => {
+            parse_date(value.trim()).ok_or(IdentityError::InvalidDate { variable, value })
+        }
+        _ => Ok(Local::now().fixed_offset()),
+    }
+}
+
+/// Accepts git's internal `<timestamp> <+zone>` form (optionally prefixed by `@`),
+/// RFC 3339 (`2024-01-02T12:00:00+01:00`) and RFC 2822 dates.
+fn parse_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    let raw = value.strip_prefix('@').unwrap_or(value);
+    let (timestamp, zone) = raw.split_once(' ').unwrap_or((raw, "+0000"));
+    if let Ok(timestamp) = timestamp.parse::<i64>() {
+        let parsed = Signature {
+            name: String::new(),
+            email: String::new(),
+            timestamp,
+            timezone: zone.trim().to_string(),
+        };
+        return parsed.time();
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .ok()
+}
@@ -1,19 +1,30 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
     path::{Path, PathBuf},
 };
 
-use anyhow::{Context, Ok};
+use crate::error::{Context, Error, Result, bail};
+use crate::repo::discovery;
+use crate::shared::types::index_entry::IndexEntry;
+use crate::utils;
 use json::JsonValue;
 
+const REGULAR_MODE: &str = "100644";
+const BINARY_SIGNATURE: &[u8] = b"DIRC";
+/// ctime, mtime, dev, ino, mode, uid, gid and size, then the hash and the flags.
+const STAT_FIELDS: usize = 10;
+const ENTRY_HEADER_LEN: usize = STAT_FIELDS * 4 + 20 + 2;
+
 pub struct Index {
-    pub map: HashMap<String, String>,
+    pub map: HashMap<String, IndexEntry>,
     path: PathBuf,
+    /// Whether the file uses git's binary format instead of flux's JSON one.
+    binary: bool,
 }
 
 impl Index {
-    pub fn empty(store_dir: &Path) -> anyhow::Result<Self> {
+    pub fn empty(store_dir: &Path) -> Result<Self> {
         let empty_obj = JsonValue::new_object();
         let json_str = empty_obj.dump();
         let path = store_dir.join("index");
@@ -21,14 +32,36 @@ impl Index {
         Ok(Index {
             map: HashMap::new(),
             path,
+            binary: false,
         })
     }
 
-    pub fn load(store_dir: &Path) -> anyhow::Result<Self> {
+    /// Entries are stored as `"path": "hash"` for regular files
+    /// and as `"path": {"hash": ..., "mode": ...}` for anything else.
+    ///
+    /// Inside a `.git` directory the index is git's binary one, which git only
+    /// creates once something is staged.
+    pub fn load(store_dir: &Path) -> Result<Self> {
         let path = store_dir.join("index");
 
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read index file at {:?}", path))?;
+        if discovery::is_git_store(store_dir) && !path.exists() {
+            return Ok(Index {
+                map: HashMap::new(),
+                path,
+                binary: true,
+            });
+        }
+
+        let content =
+            fs::read(&path).with_context(|| format!("Failed to read index file at {:?}", path))?;
+        if content.starts_with(BINARY_SIGNATURE) {
+            return Ok(Index {
+                map: parse_binary(&content)?,
+                path,
+                binary: true,
+            });
+        }
+        let content = String::from_utf8(content).context("Index file is not valid UTF-8")?;
 
         let json_obj =
             json::parse(&content).with_context(|| format!("Failed to parse JSON in {:?}", path))?;
@@ -36,42 +69,182 @@ impl Index {
         let mut map = HashMap::new();
         if let JsonValue::Object(obj) = json_obj {
             for (key, value) in obj.iter() {
-                if let JsonValue::String(s) = value {
-                    map.insert(key.to_string(), s.to_string());
-                }
+                let entry = if let Some(hash) = value.as_str() {
+                    IndexEntry {
+                        hash: hash.to_string(),
+                        mode: REGULAR_MODE.to_string(),
+                    }
+                } else {
+                    let hash = value["hash"]
+                        .as_str()
+                        .with_context(|| format!("Index entry {key} has no hash"))?;
+                    IndexEntry {
+                        hash: hash.to_string(),
+                        mode: value["mode"].as_str().unwrap_or(REGULAR_MODE).to_string(),
+                    }
+                };
+                map.insert(key.to_string(), entry);
             }
         }
-        Ok(Index { map, path })
+        Ok(Index {
+            map,
+            path,
+            binary: false,
+        })
     }
 
-    pub fn flush(&self) -> anyhow::Result<()> {
+    pub fn flush(&self) -> Result<()> {
+        if self.binary {
+            fs::write(&self.path, self.to_binary()?)?;
+            return Ok(());
+        }
+
         let mut json_obj = JsonValue::new_object();
-        for (key, value) in &self.map {
-            json_obj[key] = value.clone().into();
+        for (key, entry) in &self.map {
+            json_obj[key] = if entry.mode == REGULAR_MODE {
+                entry.hash.clone().into()
+            } else {
+                json::object! { hash: entry.hash.clone(), mode: entry.mode.clone() }
+            };
         }
         std::fs::write(&self.path, json_obj.dump())?;
 
         Ok(())
     }
 
-    pub fn add(&mut self, path: String, hash: String) -> anyhow::Result<()> {
-        self.map.insert(path, hash);
+    pub fn add(&mut self, path: String, hash: String, mode: String) -> Result<()> {
+        self.map.insert(path, IndexEntry { hash, mode });
 
         Ok(())
     }
 
-    pub fn remove(&mut self, path: String) -> anyhow::Result<()> {
+    pub fn remove(&mut self, path: String) -> Result<()> {
         self.map.remove(&path);
 
         Ok(())
     }
 
-    pub fn clear(&mut self) -> anyhow::Result<()> {
+    pub fn clear(&mut self) -> Result<()> {
         self.map.clear();
         self.flush()
     }
 
+    /// Replaces the whole content of the index, e.g. with the entries of a commit's tree.
+    pub fn replace(&mut self, entries: BTreeMap<String, IndexEntry>) -> Result<()> {
+        self.map = entries.into_iter().collect();
+        self.flush()
+    }
+
+    pub fn get(&self, path: &str) -> Option<&IndexEntry> {
+        self.map.get(path)
+    }
+
+    /// Index entries sorted by path.
+    pub fn entries(&self) -> BTreeMap<String, IndexEntry> {
+        self.map
+            .iter()
+            .map(|(path, entry)| (path.clone(), entry.clone()))
+            .collect()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
     }
+
+    /// Serializes the entries as a version 2 git index. Flux keeps no stat data, so it
+    /// is left zeroed: git then compares contents and refreshes it on its next run.
+    fn to_binary(&self) -> Result<Vec<u8>> {
+        let entries = self.entries();
+        let mut data = BINARY_SIGNATURE.to_vec();
+        data.extend(2u32.to_be_bytes());
+        data.extend((entries.len() as u32).to_be_bytes());
+
+        for (path, entry) in &entries {
+            let start = data.len();
+            let mode = u32::from_str_radix(&entry.mode, 8)
+                .with_context(|| format!("Invalid mode {} for {path}", entry.mode))?;
+
+            data.extend([0; 6 * 4]);
+            data.extend(mode.to_be_bytes());
+            data.extend([0; 3 * 4]);
+            data.extend(hex::decode(&entry.hash)?);
+            data.extend((path.len().min(0xfff) as u16).to_be_bytes());
+            data.extend_from_slice(path.as_bytes());
+
+            // Entries are NUL-terminated and padded to a multiple of 8 bytes.
+            let len = data.len() - start;
+            data.resize(start + (len + 8) / 8 * 8, 0);
+        }
+
+        let checksum = hex::decode(utils::hash(&data)?)?;
+        data.extend(checksum);
+        Ok(data)
+    }
+}
+
+/// Parses a version 2 or 3 git index. Extensions (cached trees, untracked cache...)
+/// are skipped, and so are the conflict stages of unmerged paths.
+fn parse_binary(data: &[u8]) -> Result<HashMap<String, IndexEntry>> {
+    let corrupt = |reason: &str| Error::CorruptObject {
+        object: "index file".to_string(),
+        reason: reason.to_string(),
+    };
+    if data.len() < 12 + 20 {
+        return Err(corrupt("truncated"));
+    }
+    let (body, checksum) = data.split_at(data.len() - 20);
+    if utils::hash(body)? != hex::encode(checksum) {
+        return Err(corrupt("checksum mismatch"));
+    }
+
+    let read_u32 = |pos: usize| -> Result<u32> {
+        let bytes = body.get(pos..pos + 4).ok_or_else(|| corrupt("truncated"))?;
+        Ok(u32::from_be_bytes(bytes.try_into()?))
+    };
+    let version = read_u32(4)?;
+    if version != 2 && version != 3 {
+        bail!("Unsupported index version {version}");
+    }
+    let count = read_u32(8)? as usize;
+
+    let mut map = HashMap::new();
+    let mut pos = 12;
+    for _ in 0..count {
+        let start = pos;
+        let mode = read_u32(pos + 6 * 4)?;
+        let hash = body
+            .get(pos + STAT_FIELDS * 4..pos + STAT_FIELDS * 4 + 20)
+            .ok_or_else(|| corrupt("truncated"))?;
+        let flags = u16::from_be_bytes(
+            body.get(pos + ENTRY_HEADER_LEN - 2..pos + ENTRY_HEADER_LEN)
+                .ok_or_else(|| corrupt("truncated"))?
+                .try_into()?,
+        );
+        pos += ENTRY_HEADER_LEN;
+        // Version 3 entries may carry a second flags field.
+        if flags & 0x4000 != 0 {
+            pos += 2;
+        }
+
+        let name = body
+            .get(pos..)
+            .and_then(|rest| rest.iter().position(|&b| b == 0).map(|len| &rest[..len]))
+            .ok_or_else(|| corrupt("truncated"))?;
+        let name_len = name.len();
+        let path = String::from_utf8(name.to_vec()).map_err(|_| corrupt("non UTF-8 path"))?;
+        pos = start + (pos + name_len - start + 8) / 8 * 8;
+
+        let stage = (flags >> 12) & 0x3;
+        if stage == 0 {
+            map.insert(
+                path,
+                IndexEntry {
+                    hash: hex::encode(hash),
+                    mode: format!("{mode:o}"),
+                },
+            );
+        }
+    }
+
+    Ok(map)
 }
@@ -1,4 +1,24 @@
-mod config;
-mod index;
+pub mod add_patch;
 pub mod branch;
+pub mod blame;
+mod bundle;
+mod cherry_pick;
+mod clean;
+mod clone;
+pub mod config;
+mod discovery;
+mod editor;
+mod fast_export;
+mod fast_import;
+mod files;
+mod graph;
+pub mod identity;
+mod index;
+mod rebase;
+pub mod reflog;
+pub mod refs;
+mod remote;
 pub mod repository;
+pub mod revwalk;
+pub mod stash;
+mod status;
@@ -0,0 +1,213 @@
+use crate::error::{Context, Error, Result, bail};
+use crate::objects::commit;
+use crate::repo::repository::Repository;
+use crate::repo::revwalk::{RevWalk, Sort};
+use crate::shared::types::apply_outcome::ApplyOutcome;
+use crate::shared::types::reset_mode::ResetMode;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+const REBASE_DIR: &str = "rebase-merge";
+
+impl Repository {
+    /// Replays the commits of the current branch that are not in `upstream` on top of
+    /// `onto` (which defaults to `upstream`).
+    ///
+    /// The branch ref is moved to the new base first and then advanced one replayed commit
+    /// at a time, so `--abort` only has to reset it back to the original commit.
+    pub fn rebase(&mut self, upstream: &str, onto: Option<&str>) -> Result<ApplyOutcome> {
+        if self.rebase_in_progress() {
+            bail!(
+                Error::OperationInProgress,
+                "A rebase is already in progress, use --continue, --skip or --abort"
+            );
+        }
+        if self.pick_in_progress()?.is_some() {
+            bail!(
+                Error::OperationInProgress,
+                "A cherry-pick or revert is in progress"
+            );
+        }
+        if self.has_uncommitted_changes()? {
+            bail!(
+                Error::DirtyWorkTree,
+                "Cannot rebase: you have uncommitted changes"
+            );
+        }
+
+        let orig_head = self
+            .head_commit()?
+            .context("Cannot rebase a branch without commits")?;
+        let upstream_hash = self.resolve_revision(upstream)?;
+        let onto_hash = match onto {
+            Some(onto) => self.resolve_revision(onto)?,
+            None => upstream_hash.clone(),
+        };
+
+        let todo = self.commits_to_replay(&orig_head, &upstream_hash)?;
+        self.check_untracked_overwrite(
+            &self.tracked_files()?,
+            &self.commit_files(Some(&onto_hash))?,
+        )?;
+
+        let state_dir = self.rebase_dir();
+        fs::create_dir_all(&state_dir)?;
+        fs::write(state_dir.join("head-name"), &self.head)?;
+        fs::write(state_dir.join("orig-head"), &orig_head)?;
+        fs::write(state_dir.join("onto"), &onto_hash)?;
+        self.write_todo(&todo)?;
+
+        self.reset(ResetMode::Hard, Some(&onto_hash))?;
+        self.run_rebase()
+    }
+
+    /// Commits the resolved version of the stopped commit and goes on with the rest.
+    pub fn rebase_continue(&mut self) -> Result<ApplyOutcome> {
+        if !self.rebase_in_progress() {
+            bail!(Error::OperationInProgress, "No rebase in progress");
+        }
+
+        if let Some(current) = self.current_rebase_commit()? {
+            self.check_conflicts_resolved()?;
+            self.commit_replayed(&current)?;
+            self.finish_current()?;
+        }
+
+        self.run_rebase()
+    }
+
+    /// Drops the stopped commit and goes on with the rest.
+    pub fn rebase_skip(&mut self) -> Result<ApplyOutcome> {
+        if !self.rebase_in_progress() {
+            bail!(Error::OperationInProgress, "No rebase in progress");
+        }
+
+        self.reset(ResetMode::Hard, None)?;
+        self.finish_current()?;
+        self.run_rebase()
+    }
+
+    /// Puts the branch back where it was before the rebase started.
+    pub fn rebase_abort(&mut self) -> Result<()> {
+        if !self.rebase_in_progress() {
+            bail!(Error::OperationInProgress, "No rebase in progress");
+        }
+
+        let orig_head = fs::read_to_string(self.rebase_dir().join("orig-head"))?;
+        self.reset(ResetMode::Hard, Some(orig_head.trim()))?;
+        self.clear_conflicts()?;
+        fs::remove_dir_all(self.rebase_dir())?;
+        Ok(())
+    }
+
+    pub fn rebase_in_progress(&self) -> bool {
+        self.rebase_dir().exists()
+    }
+
+    fn run_rebase(&mut self) -> Result<ApplyOutcome> {
+        let mut todo = self.read_todo()?;
+
+        while let Some(next) = todo.pop_front() {
+            // the todo list is only advanced once the commit applied, so a refused
+            // one is tried again by --continue
+            let replayed = commit::parse_commit(&self.store_dir, &next)?;
+            let label = format!("{} ({})", &next[..7], replayed.summary());
+            let merge = self.apply_changes(
+                replayed.parents.first().map(String::as_str),
+                Some(&next),
+                &label,
+            )?;
+            fs::write(self.rebase_dir().join("current"), &next)?;
+            self.write_todo(&todo)?;
+
+            if !merge.conflicts.is_empty() {
+                self.record_conflicts(&merge.conflicts)?;
+                return Ok(ApplyOutcome::Conflicts(merge.conflicts));
+            }
+
+            self.commit_replayed(&next)?;
+            self.finish_current()?;
+        }
+
+        let head = self.head_commit()?.unwrap_or_default();
+        fs::remove_dir_all(self.rebase_dir())?;
+        self.load_branches()?;
+        Ok(ApplyOutcome::Committed(head))
+    }
+
+    /// Commits the index with the metadata of `original`. Commits whose changes are
+    /// already part of the new base end up empty and are dropped.
+    fn commit_replayed(&mut self, original: &str) -> Result<()> {
+        let head = self.head_commit()?;
+        let unchanged = match &head {
+            Some(head) => self.commit_tree_hash(head)? == self.tree_from_index()?,
+            None => self.index.is_empty(),
+        };
+        if unchanged {
+            return Ok(());
+        }
+
+        let replayed = commit::parse_commit(&self.store_dir, original)?;
+        self.commit_as(Some(replayed.author), replayed.message)?;
+        Ok(())
+    }
+
+    fn finish_current(&self) -> Result<()> {
+        self.clear_conflicts()?;
+        let current = self.rebase_dir().join("current");
+        if current.exists() {
+            let hash = fs::read_to_string(&current)?;
+            let mut done = fs::read_to_string(self.rebase_dir().join("done")).unwrap_or_default();
+            done.push_str(hash.trim());
+            done.push('\n');
+            fs::write(self.rebase_dir().join("done"), done)?;
+            fs::remove_file(current)?;
+        }
+        Ok(())
+    }
+
+    fn current_rebase_commit(&self) -> Result<Option<String>> {
+        let current = self.rebase_dir().join("current");
+        if !current.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(current)?.trim().to_string()))
+    }
+
+    /// Commits reachable from `head` but not from `upstream`, oldest first.
+    /// Merge commits are left out, like `git rebase` does by default.
+    fn commits_to_replay(&self, head: &str, upstream: &str) -> Result<Vec<String>> {
+        let mut walk = RevWalk::new(&self.store_dir);
+        walk.push(head);
+        walk.hide(upstream);
+        walk.sort = Sort::Topological;
+        walk.reverse = true;
+
+        Ok(walk
+            .commits()?
+            .into_iter()
+            .filter(|info| info.parents.len() <= 1)
+            .map(|info| info.hash)
+            .collect())
+    }
+
+    fn rebase_dir(&self) -> PathBuf {
+        self.store_dir.join(REBASE_DIR)
+    }
+
+    fn read_todo(&self) -> Result<VecDeque<String>> {
+        let content = fs::read_to_string(self.rebase_dir().join("todo"))?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    fn write_todo<'a>(&self, todo: impl IntoIterator<Item = &'a String>) -> Result<()> {
+        let content: String = todo.into_iter().map(|hash| format!("{hash}\n")).collect();
+        fs::write(self.rebase_dir().join("todo"), content)?;
+        Ok(())
+    }
+}
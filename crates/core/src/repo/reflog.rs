@@ -0,0 +1,85 @@
+use crate::error::{Context, Result};
+use crate::shared::types::reflog_entry::ReflogEntry;
+use crate::shared::types::signature::Signature;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const ZERO_HASH: &str = "0000000000000000000000000000000000000000";
+
+/// Reflogs live under `.flux/logs/<ref name>`, one `old new committer\tmessage` line per update.
+fn log_path(store_dir: &Path, ref_name: &str) -> PathBuf {
+    store_dir.join("logs").join(ref_name)
+}
+
+/// Records that `ref_name` moved from `old` (`None` for a new ref) to `new`.
+pub fn append(
+    store_dir: &Path,
+    ref_name: &str,
+    old: Option<&str>,
+    new: &str,
+    committer: &Signature,
+    message: &str,
+) -> Result<()> {
+    let path = log_path(store_dir, ref_name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(
+        file,
+        "{}",
+        format_entry(old.unwrap_or(ZERO_HASH), new, committer, message)
+    )?;
+    Ok(())
+}
+
+/// Reads the reflog of a ref, oldest entry first. A missing reflog is empty.
+pub fn read(store_dir: &Path, ref_name: &str) -> Result<Vec<ReflogEntry>> {
+    let path = log_path(store_dir, ref_name);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (header, message) = line.split_once('\t').unwrap_or((line, ""));
+            let (old, rest) = header.split_once(' ').context("Invalid reflog line")?;
+            let (new, committer) = rest.split_once(' ').context("Invalid reflog line")?;
+
+            Ok(ReflogEntry {
+                old: old.to_string(),
+                new: new.to_string(),
+                committer: Signature::parse(committer)?,
+                message: message.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Replaces the whole reflog, e.g. after dropping an entry from the stash stack.
+pub fn write(store_dir: &Path, ref_name: &str, entries: &[ReflogEntry]) -> Result<()> {
+    let path = log_path(store_dir, ref_name);
+    if entries.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+
+    let content: String = entries
+        .iter()
+        .map(|entry| format_entry(&entry.old, &entry.new, &entry.committer, &entry.message) + "\n")
+        .collect();
+    fs::write(path, content)?;
+    Ok(())
+}
+
+fn format_entry(old: &str, new: &str, committer: &Signature, message: &str) -> String {
+    let message = message.lines().next().unwrap_or("");
+    format!("{old} {new} {committer}\t{message}")
+}
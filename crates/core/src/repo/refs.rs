@@ -0,0 +1,297 @@
+use crate::error::{Context, Error, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single entry of the refs database.
+///
+/// `target` is `None` for refs that exist but do not point to a commit yet
+/// (the `main` branch of a freshly initialized repository).
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub target: Option<String>,
+}
+
+impl Reference {
+    /// Returns the name without its namespace, e.g. `feature/login` for `refs/heads/feature/login`.
+    pub fn short_name(&self) -> &str {
+        ["refs/heads/", "refs/tags/", "refs/remotes/"]
+            .iter()
+            .find_map(|prefix| self.name.strip_prefix(prefix))
+            .unwrap_or(&self.name)
+    }
+}
+
+/// Refs stored under `.flux`, either as loose files (`refs/heads/feature/login`)
+/// or as lines of the `packed-refs` file. Loose refs always take precedence.
+pub struct Refs {
+    store_dir: PathBuf,
+}
+
+impl Refs {
+    pub fn new(store_dir: &Path) -> Self {
+        Self {
+            store_dir: store_dir.to_path_buf(),
+        }
+    }
+
+    /// Path of the loose file backing `name` (it might not exist if the ref is packed).
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.store_dir.join(name)
+    }
+
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        if self.path(name).is_file() {
+            return Ok(true);
+        }
+        Ok(self.packed()?.contains_key(name))
+    }
+
+    /// Reads the commit hash a ref points to.
+    /// Returns `None` both for missing and for unborn refs.
+    pub fn read(&self, name: &str) -> Result<Option<String>> {
+        let path = self.path(name);
+        if path.is_file() {
+            let content =
+                fs::read_to_string(&path).with_context(|| format!("Could not read ref {name}"))?;
+            let target = content.trim();
+            // Symbolic refs, such as the `refs/remotes/origin/HEAD` written by git clone.
+            if let Some(symref_target) = target.strip_prefix("ref: ") {
+                return self.read(symref_target);
+            }
+            return Ok((!target.is_empty()).then(|| target.to_string()));
+        }
+
+        Ok(self.packed()?.remove(name))
+    }
+
+    /// Writes a loose ref, creating the intermediate directories for hierarchical names.
+    /// An empty `target` creates an unborn ref.
+    pub fn write(&self, name: &str, target: &str) -> Result<()> {
+        validate_name(name)?;
+        self.check_conflicts(name)?;
+
+        let path = self.path(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = lock_path(&path);
+        fs::write(&temp_path, target)?;
+        fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    /// Deletes a ref from both the loose storage and `packed-refs`.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.path(name);
+        let mut found = false;
+
+        if path.is_file() {
+            fs::remove_file(&path)?;
+            self.prune_empty_dirs(&path);
+            found = true;
+        }
+
+        let mut packed = self.packed()?;
+        if packed.remove(name).is_some() {
+            self.write_packed(&packed)?;
+            found = true;
+        }
+
+        if !found {
+            return Err(Error::RefNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Lists all refs whose full name starts with `prefix`, sorted by name.
+    ///
+    /// `prefix` is matched textually, so both `refs/heads/` and `refs/heads/feature/` work.
+    pub fn iter(&self, prefix: &str) -> Result<Vec<Reference>> {
+        let mut refs: BTreeMap<String, Option<String>> = self
+            .packed()?
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(name, target)| (name, Some(target)))
+            .collect();
+
+        let mut loose = Vec::new();
+        self.collect_loose(&self.store_dir.join("refs"), &mut loose)?;
+
+        for path in loose {
+            let name = self.ref_name(&path)?;
+            if !name.starts_with(prefix) {
+                continue;
+            }
+            let target = self.read(&name)?;
+            refs.insert(name, target);
+        }
+
+        Ok(refs
+            .into_iter()
+            .map(|(name, target)| Reference { name, target })
+            .collect())
+    }
+
+    /// Moves every loose ref that points to a commit into `packed-refs`.
+    /// Unborn and symbolic refs stay loose since `packed-refs` cannot represent them.
+    pub fn pack(&self) -> Result<()> {
+        let mut packed = self.packed()?;
+        let mut loose = Vec::new();
+        self.collect_loose(&self.store_dir.join("refs"), &mut loose)?;
+
+        let mut packed_paths = Vec::new();
+        for path in loose {
+            let target = fs::read_to_string(&path)?.trim().to_string();
+            if target.is_empty() || target.starts_with("ref: ") {
+                continue;
+            }
+            packed.insert(self.ref_name(&path)?, target);
+            packed_paths.push(path);
+        }
+
+        self.write_packed(&packed)?;
+
+        for path in packed_paths {
+            fs::remove_file(&path)?;
+            self.prune_empty_dirs(&path);
+        }
+        Ok(())
+    }
+
+    fn packed(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.store_dir.join("packed-refs");
+        let mut refs = BTreeMap::new();
+
+        if !path.exists() {
+            return Ok(refs);
+        }
+
+        let content = fs::read_to_string(&path).context("Could not read packed-refs")?;
+        for line in content.lines() {
+            // Header lines and peeled tag targets carry no ref names.
+            if line.starts_with('#') || line.starts_with('^') || line.trim().is_empty() {
+                continue;
+            }
+
+            let (hash, name) = line
+                .split_once(' ')
+                .with_context(|| format!("Invalid packed-refs line: {line}"))?;
+            refs.insert(name.trim().to_string(), hash.to_string());
+        }
+
+        Ok(refs)
+    }
+
+    fn write_packed(&self, refs: &BTreeMap<String, String>) -> Result<()> {
+        let mut content = String::from("# pack-refs with: peeled fully-peeled sorted \n");
+        for (name, hash) in refs {
+            content.push_str(&format!("{hash} {name}\n"));
+        }
+
+        let path = self.store_dir.join("packed-refs");
+        let temp_path = lock_path(&path);
+        fs::write(&temp_path, content)?;
+        fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    fn collect_loose(&self, dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.collect_loose(&path, out)?;
+            } else if path.extension().and_then(|e| e.to_str()) != Some("lock") {
+                out.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    fn ref_name(&self, path: &Path) -> Result<String> {
+        let rel = path
+            .strip_prefix(&self.store_dir)
+            .context("Ref outside the store directory")?;
+        let name = rel
+            .to_str()
+            .ok_or_else(|| Error::Other("Invalid UTF-8 in ref name".to_string()))?;
+        Ok(name.replace(std::path::MAIN_SEPARATOR, "/"))
+    }
+
+    /// `refs/heads/feature` and `refs/heads/feature/login` cannot coexist,
+    /// since one would need to be both a file and a directory.
+    fn check_conflicts(&self, name: &str) -> Result<()> {
+        let existing = self.iter("refs/")?;
+        for reference in existing {
+            if reference.name == name {
+                continue;
+            }
+            let is_parent = name.starts_with(&format!("{}/", reference.name));
+            let is_child = reference.name.starts_with(&format!("{name}/"));
+            if is_parent || is_child {
+                return Err(Error::RefConflict {
+                    name: name.to_string(),
+                    existing: reference.name,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn prune_empty_dirs(&self, path: &Path) {
+        let refs_root = self.store_dir.join("refs");
+        let mut dir = path.parent();
+
+        while let Some(current) = dir {
+            // Keep the namespace directories themselves (refs/heads, refs/tags...).
+            if current == refs_root || current.parent() == Some(refs_root.as_path()) {
+                break;
+            }
+            if fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+}
+
+/// `refs/tags/v1.0` must lock as `v1.0.lock`, not `v1.lock`, hence no `with_extension`.
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+/// Validates a full ref name, following a subset of `git check-ref-format`.
+pub fn validate_name(name: &str) -> Result<()> {
+    let invalid = |reason: &str| Error::Other(format!("Invalid ref name '{name}': {reason}"));
+
+    if !name.starts_with("refs/") {
+        return Err(invalid("must start with refs/"));
+    }
+    if name.ends_with('/') || name.ends_with(".lock") || name.ends_with('.') {
+        return Err(invalid("bad ending"));
+    }
+    if name.contains("..") || name.contains("//") || name.contains("@{") {
+        return Err(invalid("forbidden sequence"));
+    }
+    if name
+        .chars()
+        .any(|c| c.is_control() || " ~^:?*[\\".contains(c))
+    {
+        return Err(invalid("forbidden character"));
+    }
+    if name
+        .split('/')
+        .any(|part| part.is_empty() || part.starts_with('.'))
+    {
+        return Err(invalid("empty or hidden component"));
+    }
+    Ok(())
+}
@@ -0,0 +1,294 @@
+use crate::error::{Context, Error, Result, bail};
+use crate::objects::{commit, pack, walk};
+use crate::protocol;
+use crate::repo::bundle::Bundle;
+use crate::repo::refs;
+use crate::repo::repository::Repository;
+use crate::repo::revwalk;
+use crate::shared::types::object_type::ObjectType;
+use crate::shared::types::reset_mode::ResetMode;
+use crate::utils;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+
+impl Repository {
+    pub fn add_remote(&mut self, name: &str, url: &str) -> Result<()> {
+        refs::validate_name(&format!("refs/remotes/{name}"))
+            .with_context(|| format!("'{name}' is not a valid remote name"))?;
+        self.config.add_remote(name, url)
+    }
+
+    /// Removes a remote together with its remote-tracking branches.
+    pub fn remove_remote(&mut self, name: &str) -> Result<()> {
+        self.config.remove_remote(name)?;
+        for reference in self.refs.iter(&format!("refs/remotes/{name}/"))? {
+            self.refs.delete(&reference.name)?;
+        }
+        self.load_branches()
+    }
+
+    /// Remote names with their URLs, sorted by name.
+    pub fn list_remotes(&self) -> Vec<(String, String)> {
+        self.config.remotes().into_iter().collect()
+    }
+
+    /// Downloads the objects of the remote branches and tags that are missing locally and
+    /// updates `refs/remotes/<remote>/*`. Tags are only created, never moved.
+    /// Returns one line per updated ref.
+    pub fn fetch(&mut self, remote: &str) -> Result<Vec<String>> {
+        let url = self.remote_url(remote)?;
+        let remote_refs = if protocol::is_http_url(&url) {
+            self.fetch_http(&url)?
+        } else if let Some(bundle) = self.remote_bundle(&url) {
+            self.unbundle(&bundle)?
+        } else {
+            self.fetch_local(remote)?
+        };
+
+        let mut updates = Vec::new();
+        for (name, hash) in remote_refs {
+            if let Some(branch) = name.strip_prefix("refs/heads/") {
+                let tracking_ref = format!("refs/remotes/{remote}/{branch}");
+                let previous = self.refs.read(&tracking_ref)?;
+                if previous.as_deref() == Some(hash.as_str()) {
+                    continue;
+                }
+
+                self.refs.write(&tracking_ref, &hash)?;
+                updates.push(match previous {
+                    Some(previous) => format!(
+                        "   {}..{}  {branch} -> {remote}/{branch}",
+                        &previous[..7],
+                        &hash[..7]
+                    ),
+                    None => format!(" * [new branch]      {branch} -> {remote}/{branch}"),
+                });
+            } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+                if self.refs.exists(&name)? {
+                    continue;
+                }
+
+                self.refs.write(&name, &hash)?;
+                updates.push(format!(" * [new tag]         {tag} -> {tag}"));
+            }
+        }
+
+        self.load_branches()?;
+        Ok(updates)
+    }
+
+    /// The branch HEAD points to in the remote repository, if it has one.
+    pub(crate) fn remote_default_branch(&self, remote: &str) -> Result<Option<String>> {
+        let url = self.remote_url(remote)?;
+        let head = if protocol::is_http_url(&url) {
+            protocol::ls_refs(&url, &["HEAD"])?
+                .into_iter()
+                .find(|reference| reference.name == "HEAD")
+                .and_then(|reference| reference.symref_target)
+        } else if let Some(bundle) = self.remote_bundle(&url) {
+            bundle_head(&Bundle::read(&bundle)?.refs)
+        } else {
+            Some(self.remote_repository(remote)?.head)
+        };
+
+        Ok(head.and_then(|head| head.strip_prefix("refs/heads/").map(String::from)))
+    }
+
+    /// Updates `branch` on the remote to the local branch, copying the missing objects.
+    ///
+    /// The update has to be a fast-forward unless `force` is set. When the branch is
+    /// checked out in the remote repository its working tree is updated as well, which
+    /// is refused if it has uncommitted changes.
+    pub fn push(&mut self, remote: &str, branch: &str, force: bool) -> Result<String> {
+        let branch_ref = format!("refs/heads/{branch}");
+        let local = self
+            .refs
+            .read(&branch_ref)?
+            .with_context(|| format!("Branch '{branch}' has no commits to push"))?;
+
+        let mut target = self.remote_repository(remote)?;
+        let previous = target.refs.read(&branch_ref)?;
+        if previous.as_deref() == Some(local.as_str()) {
+            return Ok("Everything up-to-date".to_string());
+        }
+
+        let fast_forward = match &previous {
+            Some(previous) => revwalk::is_ancestor(&self.store_dir, previous, &local)?,
+            None => true,
+        };
+        if !fast_forward && !force {
+            bail!(
+                "Updates were rejected because the remote '{branch}' contains commits you do not have \
+                 (non-fast-forward), fetch them first or use --force"
+            );
+        }
+
+        let checked_out = target.head == branch_ref;
+        if checked_out && target.has_uncommitted_changes()? {
+            bail!(
+                "Refusing to update '{branch}': it is checked out in the remote repository, \
+                 which has uncommitted changes"
+            );
+        }
+
+        target.copy_missing_objects(self, &local)?;
+        if checked_out {
+            target.reset(ResetMode::Hard, Some(&local))?;
+        } else {
+            target.refs.write(&branch_ref, &local)?;
+        }
+
+        self.refs
+            .write(&format!("refs/remotes/{remote}/{branch}"), &local)?;
+        self.load_branches()?;
+
+        Ok(match previous {
+            Some(previous) if !fast_forward => {
+                format!(
+                    "+ {}...{} {branch} -> {branch} (forced update)",
+                    &previous[..7],
+                    &local[..7]
+                )
+            }
+            Some(previous) => format!(
+                "   {}..{}  {branch} -> {branch}",
+                &previous[..7],
+                &local[..7]
+            ),
+            None => format!(" * [new branch]      {branch} -> {branch}"),
+        })
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<String> {
+        self.config
+            .remotes()
+            .remove(remote)
+            .ok_or_else(|| Error::ConfigMissing(format!("remote.{remote}.url")))
+    }
+
+    /// The path of the bundle file a remote's URL points to, if it is one.
+    fn remote_bundle(&self, url: &str) -> Option<PathBuf> {
+        let path = self
+            .work_tree
+            .join(url.strip_prefix("file://").unwrap_or(url));
+        (path.is_file() && Bundle::is_bundle(&path)).then_some(path)
+    }
+
+    /// Opens the repository a remote's URL points to, a local path with or without
+    /// a `file://` prefix.
+    fn remote_repository(&self, remote: &str) -> Result<Repository> {
+        let url = self.remote_url(remote)?;
+        let path = url.strip_prefix("file://").unwrap_or(&url);
+        if path.contains("://") {
+            bail!("Unsupported remote URL '{url}'");
+        }
+
+        Repository::open(Some(
+            self.work_tree.join(path).to_string_lossy().into_owned(),
+        ))
+        .with_context(|| format!("Could not open the repository of remote '{remote}'"))
+    }
+
+    /// Copies the objects of the branches and tags of a repository on disk.
+    /// Returns the copied refs with their targets.
+    fn fetch_local(&self, remote: &str) -> Result<Vec<(String, String)>> {
+        let source = self.remote_repository(remote)?;
+        let mut remote_refs = Vec::new();
+
+        let mut references = source.refs.iter("refs/heads/")?;
+        references.extend(source.refs.iter("refs/tags/")?);
+        for reference in references {
+            if let Some(hash) = reference.target {
+                self.copy_missing_objects(&source, &hash)?;
+                remote_refs.push((reference.name, hash));
+            }
+        }
+
+        Ok(remote_refs)
+    }
+
+    /// Lists the remote refs with `ls-refs` and downloads a packfile with the missing
+    /// objects, advertising every local commit as already known.
+    fn fetch_http(&self, url: &str) -> Result<Vec<(String, String)>> {
+        let remote_refs: Vec<(String, String)> =
+            protocol::ls_refs(url, &["refs/heads/", "refs/tags/"])?
+                .into_iter()
+                .filter_map(|reference| Some((reference.name, reference.hash?)))
+                .collect();
+
+        let mut wants: Vec<String> = remote_refs
+            .iter()
+            .map(|(_, hash)| hash.clone())
+            .filter(|hash| !utils::has_object(&self.store_dir, hash))
+            .collect();
+        wants.sort();
+        wants.dedup();
+
+        if !wants.is_empty() {
+            let pack = protocol::fetch_pack(url, &wants, &self.local_commits()?)?;
+            pack::unpack(&self.store_dir, &pack)?;
+        }
+
+        if let Some(missing) = wants
+            .iter()
+            .find(|hash| !utils::has_object(&self.store_dir, hash))
+        {
+            bail!("The remote did not send object {missing}");
+        }
+        Ok(remote_refs)
+    }
+
+    /// Every commit reachable from a local ref.
+    fn local_commits(&self) -> Result<Vec<String>> {
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<String> = self
+            .refs
+            .iter("refs/")?
+            .into_iter()
+            .filter_map(|reference| reference.target)
+            .collect();
+
+        while let Some(hash) = queue.pop_front() {
+            if seen.contains(&hash) {
+                continue;
+            }
+            let object = utils::read_object(&self.store_dir, &hash)?;
+            match object.object_type {
+                ObjectType::Commit => {
+                    queue.extend(commit::get_parent_hashes(&self.store_dir, &hash)?);
+                    seen.insert(hash);
+                }
+                ObjectType::Tag => queue.push_back(walk::tag_target(&object.decompressed_content)?),
+                _ => {}
+            }
+        }
+
+        Ok(seen.into_iter().collect())
+    }
+
+    /// Copies every object reachable from `tip` in `source` that this repository lacks.
+    fn copy_missing_objects(&self, source: &Repository, tip: &str) -> Result<()> {
+        let missing = walk::collect_objects(&source.store_dir, &[tip.to_string()], |hash| {
+            utils::has_object(&self.store_dir, hash)
+        })?;
+        walk::copy_objects(&source.store_dir, &self.store_dir, &missing)
+    }
+}
+
+/// Bundles carry no symbolic refs: HEAD is matched by hash against the bundled branches.
+/// Without a HEAD, a bundle of a single branch defaults to it.
+fn bundle_head(refs: &[(String, String)]) -> Option<String> {
+    let branches: Vec<&(String, String)> = refs
+        .iter()
+        .filter(|(name, _)| name.starts_with("refs/heads/"))
+        .collect();
+
+    match refs.iter().find(|(name, _)| name == "HEAD") {
+        Some((_, head)) => branches
+            .iter()
+            .find(|(_, hash)| hash == head)
+            .map(|(name, _)| name.clone()),
+        None if branches.len() == 1 => Some(branches[0].0.clone()),
+        None => None,
+    }
+}
@@ -1,56 +1,72 @@
-use crate::objects::{commit, tree};
+use crate::error::{Context, Error, Result, bail};
+use crate::ignore::IgnoreRules;
+use crate::objects::{commit, pack, tree, walk};
+use crate::pathspec::Pathspec;
 use crate::repo::branch::Branch;
 use crate::repo::config::Config;
+use crate::repo::discovery;
 use crate::repo::index::Index;
+use crate::repo::reflog;
+use crate::repo::refs::Refs;
+use crate::shared::types::commit_info::CommitInfo;
+use crate::shared::types::index_entry::IndexEntry;
 use crate::shared::types::object_type::ObjectType;
-use crate::shared::types::tree_entry::TreeEntry;
+use crate::shared::types::reset_mode::ResetMode;
+use crate::shared::types::signature::Signature;
 use crate::utils;
 use crate::utils::write_object;
-use anyhow::{Context, bail};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
 use std::fs::{self, File};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{PermissionsExt, symlink};
 use std::path::{Path, PathBuf};
 
+pub(crate) const FLUX_DIR: &str = ".flux";
+pub(crate) const GIT_DIR: &str = ".git";
+/// Inside `.git`, flux keeps its own settings next to git's `config`.
+const GIT_FLUX_CONFIG: &str = "fluxconfig";
+const GIT_CONFIG: &str = "\
+[core]
+\trepositoryformatversion = 0
+\tfilemode = true
+\tbare = false
+\tlogallrefupdates = true
+";
+
 pub struct Repository {
     pub work_tree: PathBuf,
     pub store_dir: PathBuf,
+    /// Where flux was run from, relative to `work_tree` (empty at the top). Paths
+    /// given by the user are relative to it, see [`Repository::resolve_path`].
+    pub prefix: String,
     pub config: Config,
     pub index: Index,
+    pub refs: Refs,
     pub head: String,
     pub branches: Vec<Branch>,
 }
 
 // TODO: really needs a refactor!!
 impl Repository {
-
-    fn load_branches(&mut self) -> anyhow::Result<()> {
-        let heads_dir = self.store_dir.join("refs/heads");
+    pub(crate) fn load_branches(&mut self) -> Result<()> {
         let current_branch_name = self.branch_name();
 
-        let mut branches = Vec::new();
-
-        for entry in fs::read_dir(&heads_dir)? {
-            let entry = entry?;
-            let name = entry
-                .file_name()
-                .into_string()
-                .map_err(|_| anyhow::anyhow!("Invalid UTF-8 in branch name"))?;
-
-            let ref_path = entry.path();
-
-            let last_commit_hash = fs::read_to_string(&ref_path)
-                .ok()
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty());
-
-            branches.push(Branch {
-                name: name.clone(),
-                is_current: Some(&name) == current_branch_name.as_ref(),
-                last_commit_hash,
-                ref_path,
-            });
-        }
+        self.branches = self
+            .refs
+            .iter("refs/heads/")?
+            .into_iter()
+            .map(|reference| {
+                let name = reference.short_name().to_string();
+                Branch {
+                    is_current: Some(&name) == current_branch_name.as_ref(),
+                    last_commit_hash: reference.target,
+                    ref_path: self.refs.path(&reference.name),
+                    name,
+                }
+            })
+            .collect();
 
-        self.branches = branches;
         Ok(())
     }
 
@@ -58,101 +74,215 @@ impl Repository {
         self.head.strip_prefix("refs/heads/").map(String::from)
     }
 
-    fn head_commit(&self) -> anyhow::Result<Option<String>> {
-        let branch_path = self.store_dir.join(&self.head);
-        Ok(fs::read_to_string(branch_path)
-            .ok()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty()))
+    pub(crate) fn head_commit(&self) -> Result<Option<String>> {
+        self.refs.read(&self.head)
+    }
+
+    pub(crate) fn commit_files(
+        &self,
+        commit_hash: Option<&str>,
+    ) -> Result<BTreeMap<String, IndexEntry>> {
+        let Some(commit_hash) = commit_hash else {
+            return Ok(BTreeMap::new());
+        };
+
+        tree::flatten_tree(&self.store_dir, &self.commit_tree_hash(commit_hash)?)
     }
 
-    fn restore_working_tree(&self, commit_hash: &str) -> anyhow::Result<()> {
+    pub(crate) fn commit_tree_hash(&self, commit_hash: &str) -> Result<String> {
         let commit = utils::read_object(&self.store_dir, commit_hash)?;
+        commit::get_tree_hash(commit)?.context("Commit has no tree")
+    }
 
-        if commit.object_type != ObjectType::Commit {
-            bail!("Expected commit object");
+    /// Files of a tree located at `path`, which can name either a single file or a
+    /// directory. The path is looked up one `parse_tree` per component.
+    fn tree_files_at(&self, tree_hash: &str, path: &str) -> Result<BTreeMap<String, IndexEntry>> {
+        if path.is_empty() {
+            return tree::flatten_tree(&self.store_dir, tree_hash);
         }
 
-        let content = String::from_utf8(commit.decompressed_content)?;
-        let tree_hash = content
-            .lines()
-            .find(|line| line.starts_with("tree "))
-            .and_then(|line| line.strip_prefix("tree "))
-            .context("Commit has no tree")?
-            .trim()
-            .to_string();
+        let mut files = BTreeMap::new();
+        match tree::lookup_path(&self.store_dir, tree_hash, path)? {
+            Some(entry) if tree::is_tree_mode(&entry.mode) => {
+                for (sub_path, file) in tree::flatten_tree(&self.store_dir, &entry.hash)? {
+                    files.insert(format!("{path}/{sub_path}"), file);
+                }
+            }
+            Some(entry) => {
+                files.insert(
+                    path.to_string(),
+                    IndexEntry {
+                        hash: entry.hash,
+                        mode: entry.mode,
+                    },
+                );
+            }
+            None => {}
+        }
 
-        self.restore_tree(&tree_hash, &self.work_tree)?;
+        Ok(files)
+    }
 
-        Ok(())
+    fn staged_paths_matching(&self, pathspec: &Pathspec) -> Vec<String> {
+        self.index
+            .map
+            .keys()
+            .filter(|candidate| pathspec.matches(candidate))
+            .cloned()
+            .collect()
     }
 
-    fn restore_tree(&self, tree_hash: &str, target_dir: &Path) -> anyhow::Result<()> {
-        let entries = tree::parse_tree(&self.store_dir, tree_hash)?;
+    /// Files tracked either by the index or by the current HEAD commit.
+    pub(crate) fn tracked_files(&self) -> Result<BTreeMap<String, IndexEntry>> {
+        let mut files = self.commit_files(self.head_commit()?.as_deref())?;
+        files.extend(self.index.entries());
+        Ok(files)
+    }
 
-        for entry in entries {
-            let target_path = target_dir.join(&entry.name);
+    /// Makes the working tree match `target`: files that were tracked in `current`
+    /// but are missing from `target` are removed, everything in `target` is (re)written.
+    /// Untracked files are left alone.
+    pub(crate) fn checkout_files(
+        &self,
+        current: &BTreeMap<String, IndexEntry>,
+        target: &BTreeMap<String, IndexEntry>,
+    ) -> Result<()> {
+        for path in current.keys().filter(|path| !target.contains_key(*path)) {
+            self.remove_worktree_file(path)?;
+        }
 
-            if entry.mode.starts_with("040") {
-                fs::create_dir_all(&target_path)?;
-                self.restore_tree(&entry.hash, &target_path)?;
-            } else {
-                let blob = utils::read_object(&self.store_dir, &entry.hash)?;
+        for (path, entry) in target {
+            self.write_worktree_file(path, entry)?;
+        }
 
-                if blob.object_type != ObjectType::Blob {
-                    bail!("Expected blob object");
-                }
+        Ok(())
+    }
 
-                fs::write(&target_path, blob.decompressed_content)?;
+    /// Fails if checking out `target` would overwrite a file that is not in `current`,
+    /// like git refuses to clobber untracked files.
+    pub(crate) fn check_untracked_overwrite(
+        &self,
+        current: &BTreeMap<String, IndexEntry>,
+        target: &BTreeMap<String, IndexEntry>,
+    ) -> Result<()> {
+        for path in target.keys().filter(|path| !current.contains_key(*path)) {
+            if self.work_tree.join(path).exists() {
+                bail!(
+                    Error::DirtyWorkTree,
+                    "Untracked file '{path}' would be overwritten, move or remove it first"
+                );
             }
         }
-
         Ok(())
     }
 
-    fn clear_working_tree(&self) -> anyhow::Result<()> {
-        for entry in fs::read_dir(&self.work_tree)? {
-            let entry = entry?;
-            let path = entry.path();
+    pub(crate) fn write_worktree_file(&self, path: &str, entry: &IndexEntry) -> Result<()> {
+        let blob = utils::read_object(&self.store_dir, &entry.hash)?;
 
-            if path.file_name().and_then(|n| n.to_str()) == Some(".flux") {
-                continue;
-            }
+        if blob.object_type != ObjectType::Blob {
+            bail!("Expected blob object");
+        }
 
-            if path.is_file() {
-                fs::remove_file(path)?;
-            } else if path.is_dir() {
-                fs::remove_dir_all(path)?;
-            }
+        let target_path = self.work_tree.join(path);
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // never write through an old symlink, and make room for a new one
+        let is_link = entry.mode == "120000";
+        if fs::symlink_metadata(&target_path)
+            .is_ok_and(|metadata| metadata.is_symlink() || (is_link && metadata.is_file()))
+        {
+            fs::remove_file(&target_path)?;
+        }
+        if is_link {
+            symlink(OsStr::from_bytes(&blob.decompressed_content), &target_path)?;
+            return Ok(());
         }
+        fs::write(&target_path, blob.decompressed_content)?;
+
+        let mode = if entry.mode == "100755" { 0o755 } else { 0o644 };
+        fs::set_permissions(&target_path, fs::Permissions::from_mode(mode))?;
 
         Ok(())
     }
 
-    fn has_uncommitted_changes(&self) -> bool {
-        !self.index.is_empty()
+    pub(crate) fn remove_worktree_file(&self, path: &str) -> Result<()> {
+        let full_path = self.work_tree.join(path);
+        if utils::is_worktree_file(&full_path) {
+            fs::remove_file(&full_path)?;
+        }
+
+        let mut dir = full_path.parent();
+        while let Some(current) = dir {
+            if current == self.work_tree || fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+
+        Ok(())
     }
 
-    fn add_path(&mut self, path: &Path) -> anyhow::Result<()> {
-        let metadata = fs::metadata(path)?;
+    /// Whether the index differs from HEAD, or a tracked file differs from the index.
+    pub(crate) fn has_uncommitted_changes(&self) -> Result<bool> {
+        let head_files = self.commit_files(self.head_commit()?.as_deref())?;
+        if head_files != self.index.entries() {
+            return Ok(true);
+        }
 
-        if metadata.is_file() {
-            self.add_file(path)?;
-        } else if metadata.is_dir() {
-            if path.ends_with(".flux") {
-                return Ok(());
+        for (path, entry) in &self.index.map {
+            let full_path = self.work_tree.join(path);
+            if !utils::is_worktree_file(&full_path)
+                || utils::get_hash(&self.store_dir, &self.work_tree, &full_path)? != entry.hash
+            {
+                return Ok(true);
             }
+        }
 
-            for entry in fs::read_dir(path)? {
+        Ok(false)
+    }
+
+    /// The files of the working tree that are tracked or not ignored by `.fluxignore`,
+    /// as a sorted list of relative paths.
+    pub(crate) fn worktree_files(&self) -> Result<Vec<String>> {
+        let files = self.all_worktree_files()?;
+        let rules = IgnoreRules::load(&self.work_tree, &files)?;
+        Ok(files
+            .into_iter()
+            .filter(|path| self.index.map.contains_key(path) || !rules.is_ignored(path, false))
+            .collect())
+    }
+
+    /// Every file of the working tree outside `.flux`, `.git` and nested repositories,
+    /// ignored ones included, as a sorted list of relative paths.
+    pub(crate) fn all_worktree_files(&self) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.work_tree.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
                 let entry = entry?;
-                self.add_path(&entry.path())?;
+                let path = entry.path();
+                if entry.file_type()?.is_dir() {
+                    if !is_store_dir(&path) && discovery::store_in(&path).is_none() {
+                        dirs.push(path);
+                    }
+                    continue;
+                }
+
+                let rel_path = path
+                    .strip_prefix(&self.work_tree)
+                    .context("Path is outside work tree")?;
+                files.push(rel_path.to_str().context("Non UTF-8 path")?.to_string());
             }
         }
 
-        Ok(())
+        files.sort();
+        Ok(files)
     }
 
-    fn add_file(&mut self, path: &Path) -> anyhow::Result<()> {
+    fn add_file(&mut self, path: &Path) -> Result<()> {
         let res = utils::write_object(&self.store_dir, &self.work_tree, path)?;
 
         let rel_path = path
@@ -161,63 +291,119 @@ impl Repository {
 
         let rel_path = rel_path.to_str().context("Non UTF-8 path")?;
 
-        self.index.add(rel_path.into(), res.hash)?;
+        self.index.add(rel_path.into(), res.hash, res.mode)?;
         Ok(())
     }
 
-    pub fn show_branches(&self) -> anyhow::Result<String> {
-        let files = fs::read_dir(self.store_dir.join("refs/heads"))
-            .context("Could not open refs/heads directory")?;
-
-        let current = self.branch_name();
-
+    /// Formats the local branches, plus the remote-tracking ones when `all` is set.
+    /// A `prefix` such as `feature/` narrows the listing down.
+    pub fn show_branches(&self, all: bool, prefix: Option<&str>) -> Result<String> {
+        let prefix = prefix.unwrap_or("");
         let mut res = String::new();
 
-        for file in files {
-            let file = file.context("Could not read branch entry")?;
-
-            let name = file
-                .file_name()
-                .into_string()
-                .map_err(|_| anyhow::anyhow!("Invalid UTF-8 in branch name"))?;
-
-            if Some(&name) == current.as_ref() {
+        for branch in self.branches.iter().filter(|b| b.name.starts_with(prefix)) {
+            if branch.is_current {
                 res.push_str("(*) ");
             } else {
                 res.push_str("  ");
             }
 
-            res.push_str(&name);
+            res.push_str(&branch.name);
             res.push('\n');
         }
 
+        if all {
+            for name in self.list_refs("refs/remotes/", Some(prefix))? {
+                res.push_str(&format!("  remotes/{name}\n"));
+            }
+        }
+
         Ok(res)
     }
 
-    pub fn init(path: Option<String>, force: bool) -> anyhow::Result<Self> {
+    /// Lists the short names of the refs under the given namespace (e.g. `refs/tags/`),
+    /// optionally narrowed down by a further name prefix.
+    pub fn list_refs(&self, namespace: &str, prefix: Option<&str>) -> Result<Vec<String>> {
+        let prefix = format!("{}{}", namespace, prefix.unwrap_or(""));
+        Ok(self
+            .refs
+            .iter(&prefix)?
+            .iter()
+            .map(|reference| reference.short_name().to_string())
+            .collect())
+    }
+
+    pub fn pack_refs(&self) -> Result<()> {
+        self.refs.pack()
+    }
+
+    pub fn init(path: Option<String>, force: bool) -> Result<Self> {
         let work_tree = path
             .map(PathBuf::from)
             .unwrap_or_else(|| PathBuf::from("."));
-        let store_dir = work_tree.join(".flux");
+        let store_dir = work_tree.join(FLUX_DIR);
 
         if store_dir.join("config").exists() && !force {
             bail!("Repository already initialized");
         }
 
         fs::create_dir_all(&store_dir)?;
-        fs::create_dir(&store_dir.join("objects"))?;
-        fs::create_dir(&store_dir.join("refs"))?;
-        fs::create_dir(&store_dir.join("refs/heads"))?;
-        File::create(&store_dir.join("refs/heads/main"))?;
-        let config = Config::default(&store_dir.join("config"))?;
-        fs::write(&store_dir.join("HEAD"), "ref: refs/heads/main\n")?;
-        fs::write(&store_dir.join("index"), "{}")?;
+        fs::create_dir(store_dir.join("objects"))?;
+        fs::create_dir(store_dir.join("refs"))?;
+        fs::create_dir(store_dir.join("refs/heads"))?;
+        fs::create_dir(store_dir.join("refs/tags"))?;
+        File::create(store_dir.join("refs/heads/main"))?;
+        let config = Config::default(store_dir.join("config"))?;
+        fs::write(store_dir.join("HEAD"), "ref: refs/heads/main\n")?;
+        fs::write(store_dir.join("index"), "{}")?;
         let index = Index::empty(&store_dir)?;
 
+        Self::with_store(work_tree, store_dir, config, index)
+    }
+
+    /// Initializes a repository in the layout git uses (`.git`, binary index),
+    /// so that both git and flux can work on it.
+    pub fn init_git(path: Option<String>, force: bool) -> Result<Self> {
+        let work_tree = path
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let store_dir = work_tree.join(GIT_DIR);
+
+        if store_dir.join("HEAD").exists() && !force {
+            bail!("Repository already initialized");
+        }
+
+        fs::create_dir_all(store_dir.join("objects/info"))?;
+        fs::create_dir_all(store_dir.join("objects/pack"))?;
+        fs::create_dir_all(store_dir.join("refs/heads"))?;
+        fs::create_dir_all(store_dir.join("refs/tags"))?;
+        if !store_dir.join("config").exists() {
+            fs::write(store_dir.join("config"), GIT_CONFIG)?;
+        }
+        // Git would report an empty ref file as broken, so `main` stays unborn until
+        // the first commit.
+        fs::write(store_dir.join("HEAD"), "ref: refs/heads/main\n")?;
+        let config = Config::default(store_dir.join(GIT_FLUX_CONFIG))?;
+        let index = Index::load(&store_dir)?;
+
+        Self::with_store(work_tree, store_dir, config, index)
+    }
+
+    /// A freshly initialized repository, on the unborn `main` branch.
+    fn with_store(
+        work_tree: PathBuf,
+        store_dir: PathBuf,
+        config: Config,
+        index: Index,
+    ) -> Result<Self> {
+        let refs = Refs::new(&store_dir);
+
         let mut repo = Self {
             work_tree,
             index,
+            refs,
             store_dir,
+            prefix: String::new(),
             config,
             head: "refs/heads/main".to_string(),
             branches: Vec::new(),
@@ -227,18 +413,29 @@ impl Repository {
         Ok(repo)
     }
 
-    pub fn open(path: Option<String>) -> anyhow::Result<Self> {
-        let work_tree = path
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."));
-        let store_dir = work_tree.join(".flux");
+    /// Opens the repository containing `path` (the current directory by default),
+    /// which can be any directory of its work tree. A `.flux` store is preferred;
+    /// otherwise flux works directly on the `.git` directory.
+    pub fn open(path: Option<String>) -> Result<Self> {
+        let discovery::Location {
+            work_tree,
+            store_dir,
+            prefix,
+        } = discovery::discover(path.as_deref())?;
 
-        if !store_dir.exists() {
-            bail!("Not a repository");
-        }
+        let config = if discovery::is_git_store(&store_dir) {
+            let config_path = store_dir.join(GIT_FLUX_CONFIG);
+            let mut config = if config_path.exists() {
+                Config::from(&config_path)?
+            } else {
+                Config::default(&config_path)?
+            };
+            config.inherit_git_config(&store_dir.join("config"))?;
+            config
+        } else {
+            Config::from(store_dir.join("config"))?
+        };
 
-        let config_path = store_dir.join("config");
-        let config = Config::from(&config_path)?;
         let index = Index::load(&store_dir)?;
 
         let head_content = fs::read_to_string(store_dir.join("HEAD"))?;
@@ -252,11 +449,15 @@ impl Repository {
             bail!("Detached HEAD not supported");
         };
 
+        let refs = Refs::new(&store_dir);
+
         let mut repo = Self {
             work_tree,
             store_dir,
+            prefix,
             config,
             index,
+            refs,
             head,
             branches: Vec::new(),
         };
@@ -265,13 +466,17 @@ impl Repository {
         Ok(repo)
     }
 
-    pub fn set(&mut self, key: String, value: String) -> Result<(), anyhow::Error> {
-        self.config.set(key, value)?;
+    pub fn set(&mut self, key: String, value: String) -> Result<()> {
+        self.config.set(&key, &value)?;
         Ok(())
     }
 
-    pub fn hash_object(&self, path: String, write: bool) -> anyhow::Result<String> {
-        let full_path = self.work_tree.join(&path);
+    pub fn unset(&mut self, key: &str) -> Result<()> {
+        self.config.unset(key)
+    }
+
+    pub fn hash_object(&self, path: String, write: bool) -> Result<String> {
+        let full_path = self.work_tree.join(self.resolve_path(&path)?);
         let hash = if write {
             let result = write_object(&self.store_dir, &self.work_tree, &full_path)?;
             result.hash
@@ -282,7 +487,7 @@ impl Repository {
         Ok(hash)
     }
 
-    pub fn cat_file(&self, object_hash: &str) -> anyhow::Result<()> {
+    pub fn cat_file(&self, object_hash: &str) -> Result<()> {
         let object = utils::read_object(&self.store_dir, object_hash)?;
 
         match object.object_type {
@@ -303,8 +508,8 @@ impl Repository {
         Ok(())
     }
 
-    pub fn ls_tree(&self, tree_hash: &str) -> anyhow::Result<String> {
-        Ok(tree::ls_tree(&self.store_dir, tree_hash)?)
+    pub fn ls_tree(&self, tree_hash: &str) -> Result<String> {
+        tree::ls_tree(&self.store_dir, tree_hash)
     }
 
     pub fn commit_tree(
@@ -312,156 +517,471 @@ impl Repository {
         tree_hash: String,
         message: String,
         parent_hash: Option<String>,
-    ) -> anyhow::Result<String> {
-        let (user_name, user_email) = self.config.get();
+    ) -> Result<String> {
         let object = utils::read_object(&self.store_dir, &tree_hash)?;
         let hash = match object.object_type {
-            ObjectType::Tree => commit::commit_tree(
+            ObjectType::Tree => commit::write_commit(
                 &self.store_dir,
-                user_name,
-                user_email,
-                tree_hash,
-                parent_hash,
-                message,
+                &tree_hash,
+                &parent_hash.into_iter().collect::<Vec<_>>(),
+                &self.config.author(None)?,
+                &self.config.committer()?,
+                &message,
             )?,
             _ => bail!("Can only commit tree objects"),
         };
         Ok(hash)
     }
 
-    pub fn add(&mut self, path: &str) -> anyhow::Result<()> {
-        let full_path = self.work_tree.join(path);
-        self.add_path(&full_path)?;
-        self.index.flush()?;
-        Ok(())
+    /// Stages the files selected by the pathspec `paths`: new and modified files of the
+    /// working tree, and the removal of tracked files that were deleted from it.
+    pub fn add(&mut self, paths: &[String]) -> Result<()> {
+        self.stage(paths, true)
     }
 
-    pub fn delete(&mut self, path: &str) -> anyhow::Result<()> {
-        let path = self.work_tree.join(path);
-        if let Some(s) = path.to_str() {
-            self.index.remove(s.into())?;
-            self.index.flush()?;
-        } else {
-            bail!("Could not remove file from index.")
+    /// Stages the modifications and deletions of the tracked files selected by the
+    /// pathspec `paths`, all of them when it is empty. New files are left out.
+    pub fn add_tracked(&mut self, paths: &[String]) -> Result<()> {
+        self.stage(paths, false)
+    }
+
+    fn stage(&mut self, paths: &[String], untracked: bool) -> Result<()> {
+        let pathspec = self.pathspec(paths)?;
+        let tracked = self.staged_paths_matching(&pathspec);
+        let files: Vec<String> = self
+            .worktree_files()?
+            .into_iter()
+            .filter(|path| pathspec.matches(path))
+            .filter(|path| untracked || self.index.get(path).is_some())
+            .collect();
+        pathspec.check_matched(tracked.iter().chain(&files))?;
+
+        for path in tracked
+            .iter()
+            .filter(|path| files.binary_search(path).is_err())
+        {
+            self.index.remove(path.clone())?;
+        }
+        for file in &files {
+            self.add_file(&self.work_tree.join(file))?;
         }
+        self.index.flush()
+    }
 
-        Ok(())
+    /// Removes the index entries selected by the pathspec `paths`.
+    pub fn delete(&mut self, paths: &[String]) -> Result<()> {
+        let pathspec = self.pathspec(paths)?;
+        let staged = self.staged_paths_matching(&pathspec);
+        pathspec.check_matched(&staged)?;
+
+        for path in staged {
+            self.index.remove(path)?;
+        }
+        self.index.flush()
     }
 
-    pub fn tree_from_index(&self) -> anyhow::Result<String> {
-        let mut entries = Vec::new();
+    pub fn tree_from_index(&self) -> Result<String> {
+        tree::write_tree_from_entries(&self.store_dir, &self.index.entries())
+    }
 
-        for (path, hash) in &self.index.map {
-            let name = Path::new(path)
-                .file_name()
-                .and_then(|n| n.to_str())
-                .context("Invalid filename in index")?
-                .to_string();
+    pub fn commit(&mut self, message: String) -> Result<String> {
+        self.commit_as(None, message)
+    }
 
-            let object = utils::read_object(&self.store_dir, hash)?;
+    /// Commits the index with `author` (`Name <email>`) as the author instead of the
+    /// configured user.
+    pub fn commit_by(&mut self, author: &str, message: String) -> Result<String> {
+        let author = self.config.author(Some(author))?;
+        self.commit_as(Some(author), message)
+    }
 
-            let (mode, entry_type) = match object.object_type {
-                ObjectType::Blob => ("100644".to_string(), "blob".to_string()),
-                ObjectType::Tree => ("040000".to_string(), "tree".to_string()),
-                _ => bail!("Unexpected object type in index"),
-            };
+    /// Commits the index on top of HEAD. The author defaults to the configured user,
+    /// who is always the committer. See [`Config::author`] for how they are found.
+    pub(crate) fn commit_as(
+        &mut self,
+        author: Option<Signature>,
+        message: String,
+    ) -> Result<String> {
+        let index_tree_hash = self.tree_from_index()?;
+        let parent = self.head_commit()?;
 
-            entries.push(TreeEntry {
-                mode,
-                entry_type,
-                hash: hash.clone(),
-                name,
-            });
+        let unchanged = match &parent {
+            Some(parent) => self.commit_tree_hash(parent)? == index_tree_hash,
+            None => self.index.is_empty(),
+        };
+
+        if unchanged {
+            return Err(Error::NothingToCommit);
         }
 
-        let tree_content = tree::build_tree_content(entries);
-        let result = tree::hash_tree(tree_content)?;
-        utils::store_object(
+        let committer = self.config.committer()?;
+        let author = match author {
+            Some(author) => author,
+            None => self.config.author(None)?,
+        };
+        let parents: Vec<String> = parent.into_iter().collect();
+
+        let commit_hash = commit::write_commit(
             &self.store_dir,
-            &result.object_hash,
-            &result.compressed_content,
+            &index_tree_hash,
+            &parents,
+            &author,
+            &committer,
+            &message,
         )?;
 
-        Ok(result.object_hash)
-    }
-
-    pub fn commit(&mut self, message: String) -> anyhow::Result<String> {
-        if self.index.is_empty() {
-            bail!("Nothing to commit");
-        }
+        self.refs.write(&self.head, &commit_hash)?;
 
-        let index_tree_hash = self.tree_from_index()?;
-        let (user_name, user_email) = self.config.get();
-        let parent = self.head_commit()?;
+        Ok(commit_hash)
+    }
 
-        let commit_hash = commit::commit_tree(
+    /// Replaces the HEAD commit with a commit of the index that has the same parents.
+    /// The message and the author are kept unless given; the committer is the
+    /// configured user. The update is recorded in the reflog of HEAD's ref.
+    pub fn amend(&mut self, message: Option<String>, author: Option<Signature>) -> Result<String> {
+        let head = self
+            .head_commit()?
+            .context("Nothing to amend: HEAD does not point to a commit yet")?;
+        let amended = commit::parse_commit(&self.store_dir, &head)?;
+
+        let committer = self.config.committer()?;
+        let author = author.unwrap_or(amended.author);
+        let message = message.unwrap_or(amended.message);
+        let commit_hash = commit::write_commit(
             &self.store_dir,
-            user_name,
-            user_email,
-            index_tree_hash,
-            parent,
-            message,
+            &self.tree_from_index()?,
+            &amended.parents,
+            &author,
+            &committer,
+            &message,
         )?;
 
-        let branch_path = self.store_dir.join(&self.head);
-        fs::write(branch_path, &commit_hash)?;
-        self.index.clear()?;
+        self.refs.write(&self.head, &commit_hash)?;
+        let summary = message.lines().next().unwrap_or_default();
+        reflog::append(
+            &self.store_dir,
+            &self.head,
+            Some(&head),
+            &commit_hash,
+            &committer,
+            &format!("commit (amend): {summary}"),
+        )?;
 
         Ok(commit_hash)
     }
 
-    pub fn log(&self, _reference: Option<String>) -> anyhow::Result<()> {
-        let mut current = self.head_commit()?;
+    /// Resolves a revision to a commit hash.
+    ///
+    /// Supports `HEAD`/`@`, full or abbreviated hashes, branch, tag and remote-tracking
+    /// branch names, full ref names, and any chain of `~N` / `^N` suffixes (`main~2^2`).
+    pub fn resolve_revision(&self, revision: &str) -> Result<String> {
+        let split = revision.find(['~', '^']).unwrap_or(revision.len());
+        let (base, mut suffix) = revision.split_at(split);
+
+        let mut hash = self.resolve_base_revision(base)?;
+
+        while !suffix.is_empty() {
+            let operator = &suffix[..1];
+            suffix = &suffix[1..];
+            let digits = suffix
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(suffix.len());
+            let count = match &suffix[..digits] {
+                "" => 1,
+                number => number.parse::<usize>()?,
+            };
+            suffix = &suffix[digits..];
+
+            if operator == "~" {
+                for _ in 0..count {
+                    hash = commit::get_parent_hash(&self.store_dir, hash)?.with_context(|| {
+                        format!("Revision '{revision}' goes past the root commit")
+                    })?;
+                }
+            } else if count > 0 {
+                let parents = commit::get_parent_hashes(&self.store_dir, &hash)?;
+                hash = parents
+                    .get(count - 1)
+                    .cloned()
+                    .with_context(|| format!("Revision '{revision}' has no parent {count}"))?;
+            }
+        }
+
+        Ok(hash)
+    }
+
+    fn resolve_base_revision(&self, base: &str) -> Result<String> {
+        if base == "HEAD" || base == "@" || base.is_empty() {
+            return self
+                .head_commit()?
+                .context("HEAD does not point to a commit yet");
+        }
+
+        let candidates = [
+            base.to_string(),
+            format!("refs/{base}"),
+            format!("refs/heads/{base}"),
+            format!("refs/tags/{base}"),
+            format!("refs/remotes/{base}"),
+        ];
+        for name in candidates.iter().filter(|name| name.starts_with("refs/")) {
+            if let Some(hash) = self.refs.read(name)? {
+                return self.peel_tags(hash);
+            }
+        }
+
+        if base.len() >= 4 && base.len() <= 40 && base.chars().all(|c| c.is_ascii_hexdigit()) {
+            return self.resolve_abbreviated_hash(&base.to_lowercase());
+        }
+
+        Err(Error::RevisionNotFound(base.to_string()))
+    }
+
+    /// Follows annotated tags down to the object they point to.
+    fn peel_tags(&self, mut hash: String) -> Result<String> {
+        loop {
+            let object = utils::read_object(&self.store_dir, &hash)?;
+            if object.object_type != ObjectType::Tag {
+                return Ok(hash);
+            }
+            hash = walk::tag_target(&object.decompressed_content)?;
+        }
+    }
+
+    fn resolve_abbreviated_hash(&self, prefix: &str) -> Result<String> {
+        let (dir, rest) = prefix.split_at(2);
+        let object_dir = self.store_dir.join("objects").join(dir);
+
+        let mut matches = Vec::new();
+        if object_dir.is_dir() {
+            for entry in fs::read_dir(&object_dir)? {
+                let name = entry?.file_name().to_string_lossy().to_string();
+                if name.starts_with(rest) && !name.ends_with(".tmp") {
+                    matches.push(format!("{dir}{name}"));
+                }
+            }
+        }
+        for hash in pack::packed_hashes(&self.store_dir, prefix)? {
+            if !matches.contains(&hash) {
+                matches.push(hash);
+            }
+        }
+
+        match matches.as_slice() {
+            [hash] => Ok(hash.clone()),
+            [] => Err(Error::RevisionNotFound(prefix.to_string())),
+            _ => bail!("Ambiguous revision '{prefix}'"),
+        }
+    }
+
+    /// Moves the current branch to `revision` (HEAD by default).
+    /// `Mixed` also resets the index to the target tree, `Hard` resets the working tree as well.
+    pub fn reset(&mut self, mode: ResetMode, revision: Option<&str>) -> Result<String> {
+        let target = self.resolve_revision(revision.unwrap_or("HEAD"))?;
+        let previously_tracked = self.tracked_files()?;
+
+        self.refs.write(&self.head, &target)?;
+
+        if mode != ResetMode::Soft {
+            let files = self.commit_files(Some(&target))?;
+
+            if mode == ResetMode::Hard {
+                self.checkout_files(&previously_tracked, &files)?;
+            }
+
+            self.index.replace(files)?;
+        }
+
+        self.load_branches()?;
+        Ok(target)
+    }
+
+    /// Resets the index entries matching `paths` to their state in `revision` (HEAD by default),
+    /// leaving the branch and the working tree untouched. Paths missing from the revision are unstaged.
+    pub fn reset_paths(&mut self, revision: Option<&str>, paths: &[String]) -> Result<()> {
+        let files = match revision {
+            Some(revision) => self.commit_files(Some(&self.resolve_revision(revision)?))?,
+            None => self.commit_files(self.head_commit()?.as_deref())?,
+        };
+
+        let pathspec = self.pathspec(paths)?;
+        let staged = self.staged_paths_matching(&pathspec);
+        let committed: BTreeMap<String, IndexEntry> = files
+            .into_iter()
+            .filter(|(path, _)| pathspec.matches(path))
+            .collect();
+        pathspec.check_matched(staged.iter().chain(committed.keys()))?;
+
+        for staged_path in staged {
+            self.index.remove(staged_path)?;
+        }
+        for (committed_path, entry) in committed {
+            self.index.add(committed_path, entry.hash, entry.mode)?;
+        }
+
+        self.index.flush()
+    }
+
+    /// Restores `paths` in the working tree and/or the index.
+    ///
+    /// Without `source`, the working tree is restored from the index and the index from HEAD.
+    /// Files missing from the source are removed. When neither `staged` nor `worktree`
+    /// is set only the working tree is restored.
+    pub fn restore(
+        &mut self,
+        source: Option<&str>,
+        staged: bool,
+        worktree: bool,
+        paths: &[String],
+    ) -> Result<()> {
+        let worktree = worktree || !staged;
+        let from_index = source.is_none() && !staged;
+
+        let source_tree = match source {
+            Some(revision) => Some(self.commit_tree_hash(&self.resolve_revision(revision)?)?),
+            None if staged => match self.head_commit()? {
+                Some(head) => Some(self.commit_tree_hash(&head)?),
+                None => None,
+            },
+            None => None,
+        };
+
+        let pathspec = self.pathspec(paths)?;
+        let tracked = self.staged_paths_matching(&pathspec);
+
+        let restored: BTreeMap<String, IndexEntry> = if from_index {
+            tracked
+                .iter()
+                .filter_map(|p| self.index.get(p).map(|entry| (p.clone(), entry.clone())))
+                .collect()
+        } else if let Some(tree_hash) = &source_tree {
+            let files = match pathspec.literal_paths() {
+                Some(paths) => {
+                    let mut files = BTreeMap::new();
+                    for path in paths {
+                        files.extend(self.tree_files_at(tree_hash, path)?);
+                    }
+                    files
+                }
+                None => tree::flatten_tree(&self.store_dir, tree_hash)?,
+            };
+            files
+                .into_iter()
+                .filter(|(path, _)| pathspec.matches(path))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+        pathspec.check_matched(tracked.iter().chain(restored.keys()))?;
+
+        if worktree {
+            for removed in tracked.iter().filter(|p| !restored.contains_key(*p)) {
+                self.remove_worktree_file(removed)?;
+            }
+            for (restored_path, entry) in &restored {
+                self.write_worktree_file(restored_path, entry)?;
+            }
+        }
+
+        if staged {
+            for removed in tracked {
+                self.index.remove(removed)?;
+            }
+            for (restored_path, entry) in restored {
+                self.index.add(restored_path, entry.hash, entry.mode)?;
+            }
+            self.index.flush()?;
+        }
+
+        Ok(())
+    }
 
-        while let Some(hash) = current {
-            self.cat_file(&hash)?;
-            current = commit::get_parent_hash(&self.store_dir, hash)?;
+    pub fn log(&self, revisions: &[String], pathspec: &Pathspec) -> Result<()> {
+        for info in self.log_commits(revisions, pathspec)? {
+            self.cat_file(&info.hash)?;
         }
 
         Ok(())
     }
 
-    pub fn switch_branch(&mut self, branch_name: &str, force: bool) -> anyhow::Result<()> {
+    /// The commits of `revisions`, limited to those changing the files selected by
+    /// `pathspec` when it is not empty.
+    pub fn log_commits(
+        &self,
+        revisions: &[String],
+        pathspec: &Pathspec,
+    ) -> Result<Vec<CommitInfo>> {
+        let mut commits = Vec::new();
+        for info in self.revwalk(revisions)?.commits()? {
+            if pathspec.is_empty() || self.changes_paths(&info, pathspec)? {
+                commits.push(info);
+            }
+        }
+
+        Ok(commits)
+    }
+
+    /// Whether the files of `info` selected by `pathspec` differ from those of each of
+    /// its parents: a merge that takes them as they are from one side changes nothing.
+    fn changes_paths(&self, info: &CommitInfo, pathspec: &Pathspec) -> Result<bool> {
+        let selected = |tree_hash: &str| -> Result<BTreeMap<String, IndexEntry>> {
+            Ok(tree::flatten_tree(&self.store_dir, tree_hash)?
+                .into_iter()
+                .filter(|(path, _)| pathspec.matches(path))
+                .collect())
+        };
+
+        let files = selected(&info.tree)?;
+        if info.parents.is_empty() {
+            return Ok(!files.is_empty());
+        }
+        for parent in &info.parents {
+            if selected(&self.commit_tree_hash(parent)?)? == files {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn switch_branch(&mut self, branch_name: &str, force: bool) -> Result<()> {
         let branch_ref = format!("refs/heads/{}", branch_name);
-        let branch_path = self.store_dir.join(&branch_ref);
 
-        if !branch_path.exists() {
-            bail!("Branch '{}' does not exist", branch_name);
+        if !self.refs.exists(&branch_ref)? {
+            return Err(Error::RefNotFound(branch_ref));
         }
 
-        if self.has_uncommitted_changes() && !force {
-            bail!("The current branch has uncommited changes");
+        if self.has_uncommitted_changes()? && !force {
+            bail!(
+                Error::DirtyWorkTree,
+                "The current branch has uncommited changes"
+            );
         }
 
+        let previously_tracked = self.tracked_files()?;
+
         fs::write(
             self.store_dir.join("HEAD"),
             format!("ref: {}\n", branch_ref),
         )?;
         self.head = branch_ref;
 
-        if let Some(commit_hash) = self.head_commit()? {
-            self.restore_working_tree(&commit_hash)?;
-        } else {
-            self.clear_working_tree()?;
-        }
+        let files = self.commit_files(self.head_commit()?.as_deref())?;
+        self.checkout_files(&previously_tracked, &files)?;
+        self.index.replace(files)?;
 
         self.load_branches()?;
         Ok(())
     }
 
-    pub fn new_branch(&mut self, branch_name: &str) -> anyhow::Result<()> {
+    pub fn new_branch(&mut self, branch_name: &str) -> Result<()> {
         let branch_ref = format!("refs/heads/{}", branch_name);
-        let branch_head_path = self.store_dir.join(&branch_ref);
 
-        if branch_head_path.exists() {
-            bail!("Branch '{}' already exists", branch_name);
+        if self.refs.exists(&branch_ref)? {
+            return Err(Error::RefExists(branch_ref));
         }
 
-        File::create(&branch_head_path)?;
-
-        if let Some(commit_hash) = self.head_commit()? {
-            fs::write(&branch_head_path, commit_hash)?;
-        }
+        let commit_hash = self.head_commit()?.unwrap_or_default();
+        self.refs.write(&branch_ref, &commit_hash)?;
 
         fs::write(
             self.store_dir.join("HEAD"),
@@ -486,3 +1006,8 @@ impl Repository {
             .collect()
     }
 }
+
+/// Whether `path` is a `.flux` or `.git` store directory.
+pub(crate) fn is_store_dir(path: &Path) -> bool {
+    path.ends_with(FLUX_DIR) || path.ends_with(GIT_DIR)
+}
@@ -0,0 +1,272 @@
+use crate::error::Result;
+use crate::objects::commit;
+use crate::repo::repository::Repository;
+use crate::shared::types::commit_info::CommitInfo;
+use crate::utils;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// The order in which a [`RevWalk`] lists commits. Both put every commit before its
+/// parents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sort {
+    /// Newest committer date first.
+    #[default]
+    Date,
+    /// Each line of history is listed in one piece: after a merge come the commits of
+    /// the merged branch, then those of the branch it was merged into.
+    Topological,
+}
+
+/// Lists the commits reachable from a set of tips but not from a set of hidden commits,
+/// like `git rev-list`.
+#[derive(Debug, Clone)]
+pub struct RevWalk {
+    store_dir: PathBuf,
+    tips: Vec<String>,
+    hidden: Vec<String>,
+    pub sort: Sort,
+    /// List parents before their children instead.
+    pub reverse: bool,
+    /// Only follow the first parent of merge commits.
+    pub first_parent: bool,
+}
+
+impl RevWalk {
+    pub fn new(store_dir: &Path) -> Self {
+        Self {
+            store_dir: store_dir.to_path_buf(),
+            tips: Vec::new(),
+            hidden: Vec::new(),
+            sort: Sort::default(),
+            reverse: false,
+            first_parent: false,
+        }
+    }
+
+    /// Lists the commits reachable from `hash`.
+    pub fn push(&mut self, hash: &str) {
+        self.tips.push(hash.to_string());
+    }
+
+    /// Leaves out `hash` and every commit reachable from it.
+    pub fn hide(&mut self, hash: &str) {
+        self.hidden.push(hash.to_string());
+    }
+
+    pub fn commits(&self) -> Result<Vec<CommitInfo>> {
+        let hidden = ancestors(&self.store_dir, &self.hidden)?;
+
+        let mut commits: HashMap<String, CommitInfo> = HashMap::new();
+        let mut children: HashMap<String, usize> = HashMap::new();
+        let mut queue: VecDeque<String> = self.tips.iter().cloned().collect();
+        while let Some(hash) = queue.pop_front() {
+            if hidden.contains(&hash) || commits.contains_key(&hash) {
+                continue;
+            }
+            let mut info = commit::parse_commit(&self.store_dir, &hash)?;
+            if self.first_parent {
+                info.parents.truncate(1);
+            }
+            for parent in &info.parents {
+                *children.entry(parent.clone()).or_default() += 1;
+                queue.push_back(parent.clone());
+            }
+            commits.insert(hash, info);
+        }
+
+        // Commits become ready once all their children have been listed.
+        let mut ready: Vec<&CommitInfo> = commits
+            .values()
+            .filter(|info| !children.contains_key(&info.hash))
+            .collect();
+        ready.sort_by_key(|info| (info.committer.timestamp, tip_rank(&self.tips, &info.hash)));
+
+        let mut ordered = Vec::with_capacity(commits.len());
+        let mut stack = ready.clone();
+        let mut heap: BinaryHeap<(i64, usize, &str)> = ready
+            .iter()
+            .enumerate()
+            .map(|(seq, info)| (info.committer.timestamp, seq, info.hash.as_str()))
+            .collect();
+        let mut seq = ready.len();
+
+        loop {
+            let next = match self.sort {
+                Sort::Date => heap.pop().map(|(_, _, hash)| &commits[hash]),
+                Sort::Topological => stack.pop(),
+            };
+            let Some(info) = next else {
+                break;
+            };
+            ordered.push(info.clone());
+
+            for parent in &info.parents {
+                let Some(remaining) = children.get_mut(parent) else {
+                    continue;
+                };
+                *remaining -= 1;
+                if *remaining == 0
+                    && let Some(parent) = commits.get(parent)
+                {
+                    stack.push(parent);
+                    heap.push((parent.committer.timestamp, seq, parent.hash.as_str()));
+                    seq += 1;
+                }
+            }
+        }
+
+        if self.reverse {
+            ordered.reverse();
+        }
+        Ok(ordered)
+    }
+}
+
+/// Earlier tips sort last so that, among equally old tips, they are listed first.
+fn tip_rank(tips: &[String], hash: &str) -> std::cmp::Reverse<usize> {
+    std::cmp::Reverse(
+        tips.iter()
+            .position(|tip| tip == hash)
+            .unwrap_or(usize::MAX),
+    )
+}
+
+/// Every commit reachable from `tips`, including the tips themselves.
+pub fn ancestors(store_dir: &Path, tips: &[String]) -> Result<HashSet<String>> {
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<String> = tips.iter().cloned().collect();
+    while let Some(hash) = queue.pop_front() {
+        if seen.insert(hash.clone()) {
+            queue.extend(commit::get_parent_hashes(store_dir, &hash)?);
+        }
+    }
+    Ok(seen)
+}
+
+/// Whether `ancestor` is reachable from `descendant` through parent links. A commit
+/// counts as its own ancestor.
+pub fn is_ancestor(store_dir: &Path, ancestor: &str, descendant: &str) -> Result<bool> {
+    if !utils::has_object(store_dir, ancestor) {
+        return Ok(false);
+    }
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([descendant.to_string()]);
+    while let Some(hash) = queue.pop_front() {
+        if hash == ancestor {
+            return Ok(true);
+        }
+        if seen.insert(hash.clone()) {
+            queue.extend(commit::get_parent_hashes(store_dir, &hash)?);
+        }
+    }
+    Ok(false)
+}
+
+/// The best common ancestors of `a` and `b`: common ancestors that are not reachable
+/// from another common ancestor. Criss-cross merges have more than one. Newest first.
+pub fn merge_bases(store_dir: &Path, a: &str, b: &str) -> Result<Vec<String>> {
+    let from_a = ancestors(store_dir, &[a.to_string()])?;
+    let from_b = ancestors(store_dir, &[b.to_string()])?;
+    let common: HashSet<&String> = from_a.intersection(&from_b).collect();
+
+    // Every ancestor of a common ancestor is common too, so what the parents of the
+    // common ancestors reach is exactly the set of redundant ones.
+    let mut parents = Vec::new();
+    for hash in &common {
+        parents.extend(commit::get_parent_hashes(store_dir, hash)?);
+    }
+    let redundant = ancestors(store_dir, &parents)?;
+
+    let mut bases = Vec::new();
+    for hash in common.into_iter().filter(|hash| !redundant.contains(*hash)) {
+        let info = commit::parse_commit(store_dir, hash)?;
+        bases.push((info.committer.timestamp, hash.clone()));
+    }
+    bases.sort_by(|x, y| y.cmp(x));
+    Ok(bases.into_iter().map(|(_, hash)| hash).collect())
+}
+
+/// How many commits `a` has that `b` lacks, and how many `b` has that `a` lacks.
+pub fn ahead_behind(store_dir: &Path, a: &str, b: &str) -> Result<(usize, usize)> {
+    let from_a = ancestors(store_dir, &[a.to_string()])?;
+    let from_b = ancestors(store_dir, &[b.to_string()])?;
+    Ok((
+        from_a.difference(&from_b).count(),
+        from_b.difference(&from_a).count(),
+    ))
+}
+
+impl Repository {
+    /// A walk over the given revisions: names, `--all` for every ref, `^A` exclusions,
+    /// `A..B` (reachable from B but not from A) and `A...B` (reachable from either but
+    /// not from both). An omitted side of a range is HEAD, and so are no revisions at all.
+    pub fn revwalk(&self, revisions: &[String]) -> Result<RevWalk> {
+        let mut walk = RevWalk::new(&self.store_dir);
+        if revisions.is_empty() {
+            walk.push(&self.resolve_revision("HEAD")?);
+        }
+
+        for revision in revisions {
+            if revision == "--all" {
+                for tip in self.all_tips()? {
+                    walk.push(&tip);
+                }
+            } else if let Some(revision) = revision.strip_prefix('^') {
+                walk.hide(&self.resolve_revision(revision)?);
+            } else if let Some((a, b)) = revision.split_once("...") {
+                let a = self.resolve_revision(a)?;
+                let b = self.resolve_revision(b)?;
+                for base in merge_bases(&self.store_dir, &a, &b)? {
+                    walk.hide(&base);
+                }
+                walk.push(&a);
+                walk.push(&b);
+            } else if let Some((a, b)) = revision.split_once("..") {
+                walk.hide(&self.resolve_revision(a)?);
+                walk.push(&self.resolve_revision(b)?);
+            } else {
+                walk.push(&self.resolve_revision(revision)?);
+            }
+        }
+
+        Ok(walk)
+    }
+
+    /// HEAD and the commits of every branch, remote-tracking branch and tag.
+    fn all_tips(&self) -> Result<Vec<String>> {
+        let mut tips: Vec<String> = self.head_commit()?.into_iter().collect();
+        for prefix in ["refs/heads/", "refs/remotes/", "refs/tags/"] {
+            for reference in self.refs.iter(prefix)? {
+                if let Some(target) = &reference.target
+                    && let Some(commit) = self.peeled_commit(target)?
+                {
+                    tips.push(commit);
+                }
+            }
+        }
+        Ok(tips)
+    }
+
+    /// The best common ancestor of two revisions, `None` for unrelated histories.
+    pub fn merge_base(&self, a: &str, b: &str) -> Result<Option<String>> {
+        let a = self.resolve_revision(a)?;
+        let b = self.resolve_revision(b)?;
+        Ok(merge_bases(&self.store_dir, &a, &b)?.into_iter().next())
+    }
+
+    /// Whether revision `ancestor` is reachable from revision `descendant`.
+    pub fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+        let ancestor = self.resolve_revision(ancestor)?;
+        let descendant = self.resolve_revision(descendant)?;
+        is_ancestor(&self.store_dir, &ancestor, &descendant)
+    }
+
+    /// How many commits revision `a` is ahead of and behind revision `b`.
+    pub fn ahead_behind(&self, a: &str, b: &str) -> Result<(usize, usize)> {
+        let a = self.resolve_revision(a)?;
+        let b = self.resolve_revision(b)?;
+        ahead_behind(&self.store_dir, &a, &b)
+    }
+}
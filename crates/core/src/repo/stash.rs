@@ -0,0 +1,252 @@
+use crate::error::{Context, Error, Result, bail};
+use crate::objects::{commit, tree};
+use crate::repo::reflog;
+use crate::repo::repository::Repository;
+use crate::shared::types::commit_info::CommitInfo;
+use crate::shared::types::index_entry::IndexEntry;
+use crate::shared::types::reflog_entry::ReflogEntry;
+use crate::shared::types::reset_mode::ResetMode;
+use crate::utils;
+use std::collections::BTreeMap;
+
+const STASH_REF: &str = "refs/stash";
+
+impl Repository {
+    /// Saves the index and the tracked working tree files (plus untracked files if asked)
+    /// as a stash commit, then resets the working tree to HEAD.
+    ///
+    /// Like git, the stash commit's tree is the working tree state, its first parent is HEAD,
+    /// its second parent a commit of the index and an optional third one holds untracked files.
+    pub fn stash_push(&mut self, message: Option<&str>, include_untracked: bool) -> Result<String> {
+        let head = self
+            .head_commit()?
+            .context("You do not have the initial commit yet")?;
+        let tracked = self.tracked_files()?;
+
+        let untracked: Vec<String> = if include_untracked {
+            self.worktree_files()?
+                .into_iter()
+                .filter(|path| !tracked.contains_key(path))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        if !self.has_uncommitted_changes()? && untracked.is_empty() {
+            bail!("No local changes to save");
+        }
+
+        let committer = self.config.committer()?;
+        let branch = self
+            .head
+            .strip_prefix("refs/heads/")
+            .unwrap_or(&self.head)
+            .to_string();
+        let head_info = commit::parse_commit(&self.store_dir, &head)?;
+        let description = format!("{branch}: {} {}", &head[..7], head_info.summary());
+
+        let index_tree = self.tree_from_index()?;
+        let index_commit = commit::write_commit(
+            &self.store_dir,
+            &index_tree,
+            std::slice::from_ref(&head),
+            &committer,
+            &committer,
+            &format!("index on {description}\n"),
+        )?;
+
+        let worktree_tree = self.snapshot_files(tracked.keys())?;
+        let mut parents = vec![head, index_commit];
+
+        if !untracked.is_empty() {
+            let untracked_tree = self.snapshot_files(&untracked)?;
+            parents.push(commit::write_commit(
+                &self.store_dir,
+                &untracked_tree,
+                &[],
+                &committer,
+                &committer,
+                &format!("untracked files on {description}\n"),
+            )?);
+        }
+
+        let message = match message {
+            Some(message) => format!("On {branch}: {message}"),
+            None => format!("WIP on {description}"),
+        };
+        let stash = commit::write_commit(
+            &self.store_dir,
+            &worktree_tree,
+            &parents,
+            &committer,
+            &committer,
+            &format!("{message}\n"),
+        )?;
+
+        let previous = self.refs.read(STASH_REF)?;
+        self.refs.write(STASH_REF, &stash)?;
+        reflog::append(
+            &self.store_dir,
+            STASH_REF,
+            previous.as_deref(),
+            &stash,
+            &committer,
+            &message,
+        )?;
+
+        self.reset(ResetMode::Hard, None)?;
+        for path in &untracked {
+            self.remove_worktree_file(path)?;
+        }
+
+        Ok(message)
+    }
+
+    /// Lists the stashes, most recent first, as `stash@{n}: message`.
+    pub fn stash_list(&self) -> Result<Vec<String>> {
+        Ok(self
+            .stash_entries()?
+            .iter()
+            .enumerate()
+            .map(|(n, entry)| format!("stash@{{{n}}}: {}", entry.message))
+            .collect())
+    }
+
+    /// The files changed by a stash relative to the commit it was created on,
+    /// as `M`/`A`/`D` status lines.
+    pub fn stash_show(&self, n: usize) -> Result<String> {
+        let stash = self.stash_commit(n)?;
+        let base = self.commit_files(stash.parents.first().map(String::as_str))?;
+        let stashed = self.commit_files(Some(&stash.hash))?;
+
+        let mut res = String::new();
+        for (path, entry) in &stashed {
+            match base.get(path) {
+                None => res.push_str(&format!("A\t{path}\n")),
+                Some(old) if old != entry => res.push_str(&format!("M\t{path}\n")),
+                Some(_) => {}
+            }
+        }
+        for path in base.keys().filter(|path| !stashed.contains_key(*path)) {
+            res.push_str(&format!("D\t{path}\n"));
+        }
+
+        Ok(res)
+    }
+
+    /// Re-applies a stash on top of HEAD with a three-way merge and returns the conflicted
+    /// paths. The changes end up in the working tree; only files new to HEAD are staged.
+    pub fn stash_apply(&mut self, n: usize) -> Result<Vec<String>> {
+        let stash = self.stash_commit(n)?;
+        if self.has_uncommitted_changes()? {
+            bail!(
+                Error::DirtyWorkTree,
+                "Your local changes would be overwritten, commit or stash them first"
+            );
+        }
+
+        let untracked = self.commit_files(stash.parents.get(2).map(String::as_str))?;
+        for path in untracked.keys() {
+            if self.work_tree.join(path).exists() {
+                bail!("'{path}' already exists, no checkout");
+            }
+        }
+
+        let merge = self.apply_changes(
+            stash.parents.first().map(String::as_str),
+            Some(&stash.hash),
+            "Stashed changes",
+        )?;
+
+        let mut staged = merge.files;
+        staged.extend(self.commit_files(self.head_commit()?.as_deref())?);
+        self.index.replace(staged)?;
+
+        for (path, entry) in &untracked {
+            self.write_worktree_file(path, entry)?;
+        }
+
+        Ok(merge.conflicts)
+    }
+
+    /// Applies a stash and drops it, unless applying it ran into conflicts.
+    pub fn stash_pop(&mut self, n: usize) -> Result<Vec<String>> {
+        let conflicts = self.stash_apply(n)?;
+        if conflicts.is_empty() {
+            self.stash_drop(n)?;
+        }
+        Ok(conflicts)
+    }
+
+    /// Removes a stash from the stack. `refs/stash` keeps pointing at the newest remaining one.
+    pub fn stash_drop(&mut self, n: usize) -> Result<String> {
+        let mut entries = reflog::read(&self.store_dir, STASH_REF)?;
+        if n >= entries.len() {
+            bail!("stash@{{{n}}} does not exist");
+        }
+
+        let dropped = entries.remove(entries.len() - 1 - n);
+        reflog::write(&self.store_dir, STASH_REF, &entries)?;
+
+        match entries.last() {
+            Some(top) => self.refs.write(STASH_REF, &top.new)?,
+            None => self.refs.delete(STASH_REF)?,
+        }
+
+        Ok(dropped.new)
+    }
+
+    /// The stash stack, newest first.
+    fn stash_entries(&self) -> Result<Vec<ReflogEntry>> {
+        let mut entries = reflog::read(&self.store_dir, STASH_REF)?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    fn stash_commit(&self, n: usize) -> Result<CommitInfo> {
+        let entry = self
+            .stash_entries()?
+            .into_iter()
+            .nth(n)
+            .with_context(|| format!("stash@{{{n}}} does not exist"))?;
+        commit::parse_commit(&self.store_dir, &entry.new)
+    }
+
+    /// Stores the current content of the given working tree files and builds a tree of them.
+    /// Files missing from the working tree are left out.
+    fn snapshot_files<'a>(&self, paths: impl IntoIterator<Item = &'a String>) -> Result<String> {
+        let mut files = BTreeMap::new();
+        for path in paths {
+            let full_path = self.work_tree.join(path);
+            if !utils::is_worktree_file(&full_path) {
+                continue;
+            }
+
+            let res = utils::write_object(&self.store_dir, &self.work_tree, &full_path)?;
+            files.insert(
+                path.clone(),
+                IndexEntry {
+                    hash: res.hash,
+                    mode: res.mode,
+                },
+            );
+        }
+
+        tree::write_tree_from_entries(&self.store_dir, &files)
+    }
+}
+
+/// Parses a stash reference: `stash@{2}`, a plain `2`, or nothing for the latest stash.
+pub fn parse_stash_index(stash: Option<&str>) -> Result<usize> {
+    let Some(stash) = stash else {
+        return Ok(0);
+    };
+
+    let number = stash
+        .strip_prefix("stash@{")
+        .and_then(|rest| rest.strip_suffix('}'))
+        .unwrap_or(stash);
+    number
+        .parse()
+        .with_context(|| format!("Invalid stash reference '{stash}'"))
+}
@@ -0,0 +1,145 @@
+use crate::diff;
+use crate::error::Result;
+use crate::pathspec::Pathspec;
+use crate::repo::repository::Repository;
+use crate::shared::types::file_status::{Change, FileStatus};
+use crate::shared::types::index_entry::IndexEntry;
+use crate::utils;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Unchanged lines shown around each change of a diff.
+const CONTEXT_LINES: usize = 3;
+
+impl Repository {
+    /// The files selected by `pathspec` that differ between HEAD, the index and the
+    /// working tree, sorted by path.
+    pub fn status(&self, pathspec: &Pathspec) -> Result<Vec<FileStatus>> {
+        let head = self.selected_head_files(pathspec)?;
+        let index = selected(self.index.entries(), pathspec);
+        let worktree: BTreeSet<String> = self
+            .worktree_files()?
+            .into_iter()
+            .filter(|path| pathspec.matches(path))
+            .collect();
+
+        let paths: BTreeSet<&String> = head.keys().chain(index.keys()).chain(&worktree).collect();
+        let mut statuses = Vec::new();
+        for path in paths {
+            let staged = change(head.get(path), index.get(path));
+            let untracked = !index.contains_key(path) && worktree.contains(path);
+            let unstaged = match index.get(path) {
+                Some(_) if !worktree.contains(path) => Some(Change::Deleted),
+                Some(entry) if self.worktree_hash(path)? != entry.hash => Some(Change::Modified),
+                _ => None,
+            };
+
+            if staged.is_some() || unstaged.is_some() || untracked {
+                statuses.push(FileStatus {
+                    path: path.clone(),
+                    staged,
+                    unstaged,
+                    untracked,
+                });
+            }
+        }
+
+        Ok(statuses)
+    }
+
+    /// A unified diff of the files selected by `pathspec`, from the index to the working
+    /// tree, or from HEAD to the index when `cached` is set. Untracked files are left out.
+    pub fn diff(&self, cached: bool, pathspec: &Pathspec) -> Result<String> {
+        let head = self.selected_head_files(pathspec)?;
+        let mut res = String::new();
+
+        for status in self.status(pathspec)? {
+            let path = &status.path;
+            let index = self.index.get(path);
+            let (change, old, new) = if cached {
+                (status.staged, head.get(path), index)
+            } else {
+                (status.unstaged, index, index)
+            };
+            let Some(change) = change else {
+                continue;
+            };
+
+            let old_content = match old {
+                Some(entry) if change != Change::Added => self.blob_content(&entry.hash)?,
+                _ => Vec::new(),
+            };
+            let new_content = match new {
+                _ if change == Change::Deleted => Vec::new(),
+                _ if !cached => utils::read_worktree_file(&self.work_tree.join(path))?,
+                Some(entry) => self.blob_content(&entry.hash)?,
+                None => Vec::new(),
+            };
+
+            res.push_str(&format!("diff --git a/{path} b/{path}\n"));
+            match (change, old, new) {
+                (Change::Added, _, Some(entry)) => {
+                    res.push_str(&format!("new file mode {}\n", entry.mode))
+                }
+                (Change::Deleted, Some(entry), _) => {
+                    res.push_str(&format!("deleted file mode {}\n", entry.mode))
+                }
+                _ => {}
+            }
+            res.push_str(&file_patch(path, change, &old_content, &new_content));
+        }
+
+        Ok(res)
+    }
+
+    fn selected_head_files(&self, pathspec: &Pathspec) -> Result<BTreeMap<String, IndexEntry>> {
+        let files = self.commit_files(self.head_commit()?.as_deref())?;
+        Ok(selected(files, pathspec))
+    }
+
+    fn worktree_hash(&self, path: &str) -> Result<String> {
+        utils::get_hash(&self.store_dir, &self.work_tree, &self.work_tree.join(path))
+    }
+
+    fn blob_content(&self, hash: &str) -> Result<Vec<u8>> {
+        Ok(utils::read_object(&self.store_dir, hash)?.decompressed_content)
+    }
+}
+
+fn selected(
+    files: BTreeMap<String, IndexEntry>,
+    pathspec: &Pathspec,
+) -> BTreeMap<String, IndexEntry> {
+    files
+        .into_iter()
+        .filter(|(path, _)| pathspec.matches(path))
+        .collect()
+}
+
+fn change(from: Option<&IndexEntry>, to: Option<&IndexEntry>) -> Option<Change> {
+    match (from, to) {
+        (None, Some(_)) => Some(Change::Added),
+        (Some(_), None) => Some(Change::Deleted),
+        (Some(from), Some(to)) if from != to => Some(Change::Modified),
+        _ => None,
+    }
+}
+
+/// The `---`/`+++` lines and the hunks of a file, or a note when either side is binary.
+fn file_patch(path: &str, change: Change, old: &[u8], new: &[u8]) -> String {
+    let old_name = match change {
+        Change::Added => "/dev/null".to_string(),
+        _ => format!("a/{path}"),
+    };
+    let new_name = match change {
+        Change::Deleted => "/dev/null".to_string(),
+        _ => format!("b/{path}"),
+    };
+
+    match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+        (Ok(old), Ok(new)) if !old.contains('\0') && !new.contains('\0') => format!(
+            "--- {old_name}\n+++ {new_name}\n{}",
+            diff::unified(old, new, CONTEXT_LINES)
+        ),
+        _ => format!("Binary files {old_name} and {new_name} differ\n"),
+    }
+}
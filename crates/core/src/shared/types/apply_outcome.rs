@@ -0,0 +1,8 @@
+/// Result of replaying changes (cherry-pick, revert, rebase) on top of HEAD.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyOutcome {
+    /// The changes applied cleanly and were committed
+    Committed(String),
+    /// The changes were applied with conflicts in these paths, which need to be resolved
+    Conflicts(Vec<String>),
+}
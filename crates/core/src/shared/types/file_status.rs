@@ -0,0 +1,41 @@
+/// How a file differs from one state to the next, HEAD to index or index to working tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    /// The letter git uses for the change in its short status.
+    pub fn code(self) -> char {
+        match self {
+            Change::Added => 'A',
+            Change::Modified => 'M',
+            Change::Deleted => 'D',
+        }
+    }
+}
+
+/// The state of a file that differs between HEAD, the index and the working tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStatus {
+    pub path: String,
+    /// The change from HEAD to the index.
+    pub staged: Option<Change>,
+    /// The change from the index to the working tree.
+    pub unstaged: Option<Change>,
+    /// The file is in the working tree only.
+    pub untracked: bool,
+}
+
+impl FileStatus {
+    /// The two letters of `flux status`, `??` for untracked files.
+    pub fn short(&self) -> String {
+        if self.untracked {
+            return "??".to_string();
+        }
+        let code = |change: Option<Change>| change.map_or(' ', Change::code);
+        format!("{}{}", code(self.staged), code(self.unstaged))
+    }
+}
@@ -0,0 +1,6 @@
+/// A staged file: the blob it points to and its tree mode (`100644` or `100755`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub hash: String,
+    pub mode: String,
+}
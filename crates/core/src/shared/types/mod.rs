@@ -4,3 +4,10 @@ pub mod object_type;
 pub mod tree_entry;
 pub mod write_result;
 pub mod ref_type;
+pub mod index_entry;
+pub mod reset_mode;
+pub mod signature;
+pub mod commit_info;
+pub mod apply_outcome;
+pub mod reflog_entry;
+pub mod file_status;
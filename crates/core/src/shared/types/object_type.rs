@@ -1,8 +1,19 @@
-
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ObjectType {
     Blob,
     Tree,
     Commit,
     Tag,
 }
+
+impl ObjectType {
+    /// The type name used in object headers (`blob 12\0...`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectType::Blob => "blob",
+            ObjectType::Tree => "tree",
+            ObjectType::Commit => "commit",
+            ObjectType::Tag => "tag",
+        }
+    }
+}
@@ -0,0 +1,10 @@
+use crate::shared::types::signature::Signature;
+
+/// One line of a reflog: a ref moving from `old` to `new`.
+#[derive(Debug, Clone)]
+pub struct ReflogEntry {
+    pub old: String,
+    pub new: String,
+    pub committer: Signature,
+    pub message: String,
+}
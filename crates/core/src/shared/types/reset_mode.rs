@@ -0,0 +1,9 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetMode {
+    /// Only move the branch ref
+    Soft,
+    /// Move the branch ref and reset the index
+    Mixed,
+    /// Move the branch ref, reset the index and the working tree
+    Hard,
+}
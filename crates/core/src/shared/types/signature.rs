@@ -0,0 +1,66 @@
+use crate::error::{Context, Result};
+use chrono::{DateTime, FixedOffset, Local};
+use std::fmt;
+
+/// The `Name <email> timestamp timezone` part of an author or committer line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    pub timestamp: i64,
+    pub timezone: String,
+}
+
+impl Signature {
+    pub fn now(name: String, email: String) -> Self {
+        Self::at(name, email, Local::now().fixed_offset())
+    }
+
+    pub fn at(name: String, email: String, time: DateTime<FixedOffset>) -> Self {
+        Self {
+            name,
+            email,
+            timestamp: time.timestamp(),
+            timezone: time.format("%z").to_string(),
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Self> {
+        let (name, rest) = line.split_once(" <").context("Invalid signature")?;
+        let (email, rest) = rest.split_once("> ").context("Invalid signature")?;
+        let (timestamp, timezone) = rest.trim().split_once(' ').context("Invalid signature")?;
+
+        Ok(Self {
+            name: name.to_string(),
+            email: email.to_string(),
+            timestamp: timestamp.parse().context("Invalid signature timestamp")?,
+            timezone: timezone.to_string(),
+        })
+    }
+
+    /// The signature's time in its own timezone, `None` if the timezone is malformed.
+    pub fn time(&self) -> Option<DateTime<FixedOffset>> {
+        let (sign, digits) = match self.timezone.split_at_checked(1)? {
+            ("+", digits) => (1, digits),
+            ("-", digits) => (-1, digits),
+            _ => return None,
+        };
+        if digits.len() != 4 {
+            return None;
+        }
+        let hours: i32 = digits[..2].parse().ok()?;
+        let minutes: i32 = digits[2..].parse().ok()?;
+        let offset = FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))?;
+        Some(DateTime::from_timestamp(self.timestamp, 0)?.with_timezone(&offset))
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} <{}> {} {}",
+            self.name, self.email, self.timestamp, self.timezone
+        )
+    }
+}
@@ -1,19 +1,20 @@
-use crate::objects::{blob, tree};
+use crate::error::{Context, Error, Result, bail};
+use crate::objects::{blob, pack, tree};
 use crate::shared::types::generic_object::GenericObject;
 use crate::shared::types::hash_result::HashResult;
 use crate::shared::types::object_type::ObjectType;
 use crate::shared::types::write_result::WriteResult;
-use anyhow::{Context, bail};
 use flate2::{Compression, bufread::ZlibDecoder, write::ZlibEncoder};
 use sha1::{Digest, Sha1};
 use std::io::Write;
+use std::os::unix::ffi::OsStringExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
 use std::{fs, io::Read, path::Path};
 
 /// Decompresses zlib-compressed data using the DEFLATE algorithm.
 /// Takes compressed bytes and returns the original uncompressed data
-pub fn decompress(compressed: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+pub fn decompress(compressed: Vec<u8>) -> Result<Vec<u8>> {
     let mut decoder = ZlibDecoder::new(&compressed[..]);
     let mut result = Vec::new();
     decoder.read_to_end(&mut result)?;
@@ -21,18 +22,18 @@ pub fn decompress(compressed: Vec<u8>) -> anyhow::Result<Vec<u8>> {
 }
 
 /// Computes the SHA-1 hash of the given data and returns it.
-pub fn hash(data: &Vec<u8>) -> anyhow::Result<String> {
+pub fn hash(data: &[u8]) -> Result<String> {
     let mut hasher = Sha1::new();
-    hasher.update(&data);
+    hasher.update(data);
     let object_hash = format!("{:x}", hasher.finalize());
     Ok(object_hash)
 }
 
 /// Compresses data using zlib compression with default compression level.
 /// Returns the compressed bytes.
-pub fn compress(data: &Vec<u8>) -> anyhow::Result<Vec<u8>> {
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
     let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(&data)?;
+    encoder.write_all(data)?;
     let compressed_content = encoder.finish()?;
     Ok(compressed_content)
 }
@@ -40,45 +41,67 @@ pub fn compress(data: &Vec<u8>) -> anyhow::Result<Vec<u8>> {
 /// Reads a git object from `.flux/objects` given its hash.
 ///
 /// Locates the object on disk, decompresses it, parses the header and validates the content size.  
+/// Objects without a loose file are looked up in the packs of `objects/pack`.
 /// Returns a `GenericObject` containing:
 /// - `object_type`
 /// - `size`
 /// - `decompressed_content`
-pub fn read_object(store_dir: &Path, object_hash: &str) -> anyhow::Result<GenericObject> {
-    let (dir, file) = object_hash.split_at(2);
-    let object_path = store_dir.join("objects").join(dir).join(file);
+pub fn read_object(store_dir: &Path, object_hash: &str) -> Result<GenericObject> {
+    if object_hash.len() <= 2 {
+        bail!("Invalid object hash '{object_hash}'");
+    }
+    let object_path = object_path(store_dir, object_hash);
+
+    if !object_path.is_file() {
+        let (object_type, decompressed_content) = pack::read_packed_object(store_dir, object_hash)?
+            .ok_or_else(|| Error::ObjectNotFound(object_hash.to_string()))?;
+        return Ok(GenericObject {
+            object_type,
+            size: decompressed_content.len(),
+            decompressed_content,
+        });
+    }
+
+    let corrupt = |reason: String| Error::CorruptObject {
+        object: format!("object {object_hash}"),
+        reason,
+    };
 
     let compressed_content = fs::read(object_path)?;
-    let decompressed = decompress(compressed_content)?;
+    let decompressed =
+        decompress(compressed_content).map_err(|error| corrupt(error.to_string()))?;
 
     let null_pos = decompressed
         .iter()
         .position(|&b| b == b'\0')
-        .ok_or_else(|| anyhow::anyhow!("Invalid object: no null byte"))?;
+        .ok_or_else(|| corrupt("no null byte".to_string()))?;
 
-    let header = String::from_utf8(decompressed[..null_pos].to_vec())?;
+    let header = String::from_utf8_lossy(&decompressed[..null_pos]).to_string();
     let parts: Vec<&str> = header.split(' ').collect();
 
     if parts.len() != 2 {
-        bail!("Invalid object header");
+        return Err(corrupt("invalid object header".to_string()));
     }
 
     let object_type = match parts[0] {
         "blob" => ObjectType::Blob,
         "tree" => ObjectType::Tree,
         "commit" => ObjectType::Commit,
-        _ => bail!("Unknown object type: {}", parts[0]),
+        "tag" => ObjectType::Tag,
+        _ => return Err(corrupt(format!("unknown object type {}", parts[0]))),
     };
 
-    let size: usize = parts[1].parse()?;
+    let size: usize = parts[1]
+        .parse()
+        .map_err(|_| corrupt("invalid object size".to_string()))?;
     let decompressed_content = decompressed[null_pos + 1..].to_vec();
 
     if decompressed_content.len() != size {
-        bail!(
-            "Size mismatch: expected {}, got {}",
+        return Err(corrupt(format!(
+            "size mismatch: expected {}, got {}",
             size,
             decompressed_content.len()
-        );
+        )));
     }
 
     Ok(GenericObject {
@@ -88,8 +111,19 @@ pub fn read_object(store_dir: &Path, object_hash: &str) -> anyhow::Result<Generi
     })
 }
 
+/// Path of the loose file of an object inside `.flux/objects`.
+pub fn object_path(store_dir: &Path, hash: &str) -> PathBuf {
+    let (dir, file) = hash.split_at(2);
+    store_dir.join("objects").join(dir).join(file)
+}
+
+pub fn has_object(store_dir: &Path, hash: &str) -> bool {
+    hash.len() > 2
+        && (object_path(store_dir, hash).is_file() || pack::has_packed_object(store_dir, hash))
+}
+
 /// Writes a git object to the `.flux/objects` directory, given the object's `compressed` contents
-pub fn store_object(store_dir: &Path, hash: &str, compressed_data: &[u8]) -> anyhow::Result<()> {
+pub fn store_object(store_dir: &Path, hash: &str, compressed_data: &[u8]) -> Result<()> {
     let (dir, file) = hash.split_at(2);
     let object_dir = store_dir.join("objects").join(dir);
     let object_path = object_dir.join(file);
@@ -103,24 +137,57 @@ pub fn store_object(store_dir: &Path, hash: &str, compressed_data: &[u8]) -> any
     Ok(())
 }
 
-/// Writes either a `file` or a `dir` to the object storage inside `.flux/objects` given it's path
-pub fn write_object(
+/// Hashes and stores an object of any type given its uncompressed content (without header).
+/// Returns the object hash.
+pub fn write_raw_object(
     store_dir: &Path,
-    work_tree: &Path,
-    full_path: &Path,
-) -> anyhow::Result<WriteResult> {
-    let metadata = fs::metadata(&full_path).context("Failed to read file metadata")?;
+    object_type: ObjectType,
+    content: &[u8],
+) -> Result<String> {
+    let mut store = format!("{} {}\0", object_type.as_str(), content.len()).into_bytes();
+    store.extend_from_slice(content);
+
+    let object_hash = hash(&store)?;
+    if !has_object(store_dir, &object_hash) {
+        store_object(store_dir, &object_hash, &compress(&store)?)?;
+    }
+    Ok(object_hash)
+}
+
+/// Whether `path` is a file of the working tree: a regular file or a symlink, which
+/// is not followed.
+pub fn is_worktree_file(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.is_dir())
+}
+
+/// The content of a working tree file as it is stored in a blob: the target of a
+/// symlink, the bytes of any other file.
+pub fn read_worktree_file(path: &Path) -> Result<Vec<u8>> {
+    if fs::symlink_metadata(path)?.is_symlink() {
+        return Ok(fs::read_link(path)?.into_os_string().into_vec());
+    }
+    Ok(fs::read(path)?)
+}
+
+/// Writes either a `file` or a `dir` to the object storage inside `.flux/objects` given it's path
+pub fn write_object(store_dir: &Path, work_tree: &Path, full_path: &Path) -> Result<WriteResult> {
+    let metadata = fs::symlink_metadata(full_path).context("Failed to read file metadata")?;
     let mode: String;
     let result: HashResult;
 
-    if metadata.is_file() {
+    if metadata.is_symlink() {
+        mode = "120000".to_string();
+        let blob = blob::hash_blob(read_worktree_file(full_path)?)?;
+        store_object(store_dir, &blob.object_hash, &blob.compressed_content)?;
+        result = blob;
+    } else if metadata.is_file() {
         let perm = metadata.permissions().mode();
         if perm & 0o111 != 0 {
             mode = "100755".to_string();
         } else {
             mode = "100644".to_string();
         }
-        let content = fs::read(&full_path)?;
+        let content = fs::read(full_path)?;
         let blob = blob::hash_blob(content)?;
         store_object(store_dir, &blob.object_hash, &blob.compressed_content)?;
         result = blob;
@@ -139,11 +206,11 @@ pub fn write_object(
 }
 
 ///Gets the `hash` for a given `file` or `directory`
-pub fn get_hash(store_dir: &Path, work_tree: &Path, full_path: &Path) -> anyhow::Result<String> {
-    let metadata = fs::metadata(&full_path).context("Failed to read file metadata")?;
+pub fn get_hash(store_dir: &Path, work_tree: &Path, full_path: &Path) -> Result<String> {
+    let metadata = fs::symlink_metadata(full_path).context("Failed to read file metadata")?;
 
-    let hash = if metadata.is_file() {
-        let content = fs::read(&full_path)?;
+    let hash = if metadata.is_symlink() || metadata.is_file() {
+        let content = read_worktree_file(full_path)?;
         let res = blob::hash_blob(content)?;
         res.object_hash
     } else if metadata.is_dir() {
@@ -0,0 +1,153 @@
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::io::Cursor;
+
+mod common;
+
+const ORIGINAL: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+
+fn commit_numbers() {
+    common::init_repository();
+    fs::write("numbers.txt", ORIGINAL).unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+}
+
+fn add_patch(paths: &[&str], answers: &str) -> String {
+    let mut output = Vec::new();
+    commands::add_patch_with(
+        None,
+        paths.iter().map(|p| p.to_string()).collect(),
+        &mut Cursor::new(answers.as_bytes()),
+        &mut output,
+    )
+    .unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+fn staged_content(path: &str) -> String {
+    let repo = Repository::open(None).unwrap();
+    let hash = &repo.index.get(path).unwrap().hash;
+    let object = flux_core::utils::read_object(&repo.store_dir, hash).unwrap();
+    String::from_utf8(object.decompressed_content).unwrap()
+}
+
+#[test]
+#[serial]
+fn add_patch_select_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    commit_numbers();
+    fs::write(
+        "numbers.txt",
+        ORIGINAL.replace("one", "ONE").replace("ten", "TEN"),
+    )
+    .unwrap();
+    fs::write("README.md", "changed\n").unwrap();
+
+    let output = add_patch(&["numbers.txt"], "n\ny\n");
+    assert!(output.starts_with("diff --git a/numbers.txt b/numbers.txt\n"));
+    assert!(output.contains("@@ -1,4 +1,4 @@\n-one\n+ONE\n two\n"));
+    assert!(output.contains("Stage this hunk [y,n,e,q,?]? "));
+    assert!(!output.contains("README.md"));
+    assert_eq!(
+        staged_content("numbers.txt"),
+        ORIGINAL.replace("ten", "TEN")
+    );
+
+    // nothing is staged when quitting on the first hunk
+    fs::write("README.md", "changed again\n").unwrap();
+    let readme = staged_content("README.md");
+    add_patch(&[], "q\n");
+    assert_eq!(staged_content("README.md"), readme);
+    assert_eq!(
+        staged_content("numbers.txt"),
+        ORIGINAL.replace("ten", "TEN")
+    );
+}
+
+#[test]
+#[serial]
+fn add_patch_split_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    commit_numbers();
+    let changed = ORIGINAL.replace("two", "2").replace("five", "5");
+    fs::write("numbers.txt", &changed).unwrap();
+
+    let output = add_patch(&["numbers.txt"], "s\nn\ny\n");
+    assert!(output.contains("Stage this hunk [y,n,s,e,q,?]? "));
+    assert!(output.contains("Split into 2 hunks."));
+    assert!(output.contains("@@ -1,4 +1,4 @@\n one\n-two\n+2\n three\n four\n"));
+    assert!(output.contains("@@ -3,6 +3,6 @@\n three\n four\n-five\n+5\n six\n"));
+    assert_eq!(staged_content("numbers.txt"), ORIGINAL.replace("five", "5"));
+
+    // unknown answers print the help and ask again
+    let output = add_patch(&[], "x\ny\n");
+    assert!(output.contains("s - split the hunk into smaller hunks"));
+    assert_eq!(staged_content("numbers.txt"), changed);
+    assert_eq!(add_patch(&[], ""), "No changes.\n");
+}
+
+#[test]
+#[serial]
+fn add_patch_edit_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    commit_numbers();
+    fs::write("numbers.txt", ORIGINAL.replace("three\n", "3\n")).unwrap();
+
+    // the editor keeps the removed line and adds another one instead
+    // SAFETY: the tests of this file run serially and nothing else reads the environment.
+    unsafe {
+        std::env::set_var(
+            "FLUX_EDITOR",
+            "sed -i -e 's/^-three/ three/' -e 's/^+3/+3.0/'",
+        );
+    }
+    add_patch(&["numbers.txt"], "e\n");
+    assert_eq!(
+        staged_content("numbers.txt"),
+        ORIGINAL.replace("three\n", "three\n3.0\n")
+    );
+
+    // a hunk whose old side no longer matches is offered again
+    unsafe {
+        std::env::set_var("FLUX_EDITOR", "sed -i -e 's/^ four/ FOUR/'");
+    }
+    let output = add_patch(&["numbers.txt"], "e\nn\n");
+    assert!(output.contains("Your edited hunk does not apply."));
+    assert_eq!(output.matches("Stage this hunk").count(), 2);
+
+    unsafe {
+        std::env::remove_var("FLUX_EDITOR");
+    }
+}
+
+#[test]
+#[serial]
+fn editor_fallback_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    // empty settings are skipped rather than ending the lookup
+    // SAFETY: the tests of this file run serially and nothing else reads the environment.
+    unsafe {
+        std::env::set_var("FLUX_EDITOR", "");
+        std::env::set_var("VISUAL", " ");
+        std::env::set_var("EDITOR", "nano");
+    }
+    commands::set(None, "core.editor".to_string(), "".to_string()).unwrap();
+    assert_eq!(Repository::open(None).unwrap().config.editor(), "nano");
+
+    commands::set(None, "core.editor".to_string(), "ed".to_string()).unwrap();
+    assert_eq!(Repository::open(None).unwrap().config.editor(), "ed");
+
+    unsafe {
+        std::env::remove_var("FLUX_EDITOR");
+        std::env::remove_var("VISUAL");
+        std::env::remove_var("EDITOR");
+    }
+}
@@ -0,0 +1,97 @@
+use flux_core::objects::commit;
+use flux_core::repo::reflog;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+#[test]
+#[serial]
+fn amend_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let first = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    fs::write("README.md", "read me\n").unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    let second = commands::commit(
+        None,
+        "Fix teh README".to_string(),
+        Some("Other Author <other@example.com>".to_string()),
+    )
+    .unwrap();
+
+    // a new message, the index and the parents and author of the amended commit
+    fs::write("src/lib.rs", "// forgotten\n").unwrap();
+    commands::add(None, vec!["src/lib.rs".to_string()]).unwrap();
+    let amended =
+        commands::commit_amend(None, Some("Fix the README".to_string()), false, None, false)
+            .unwrap();
+    let repo = Repository::open(None).unwrap();
+    let info = commit::parse_commit(&repo.store_dir, &amended).unwrap();
+    assert_eq!(info.parents, std::slice::from_ref(&first));
+    assert_eq!(info.message, "Fix the README");
+    assert_eq!(info.author.name, "Other Author");
+    assert_eq!(info.committer.name, "Test User");
+    assert_eq!(repo.resolve_revision("HEAD").unwrap(), amended);
+    assert_eq!(info.tree, repo.tree_from_index().unwrap());
+
+    let entries = reflog::read(&repo.store_dir, "refs/heads/main").unwrap();
+    let last = entries.last().unwrap();
+    assert_eq!(
+        (last.old.as_str(), last.new.as_str()),
+        (second.as_str(), amended.as_str())
+    );
+    assert_eq!(last.message, "commit (amend): Fix the README");
+
+    // --no-edit --reset-author keeps the message and makes the user the author
+    let reset = commands::commit_amend(None, None, true, None, true).unwrap();
+    let info = commit::parse_commit(&repo.store_dir, &reset).unwrap();
+    assert_eq!(info.message, "Fix the README");
+    assert_eq!(info.author.name, "Test User");
+    assert_eq!(info.parents, [first]);
+}
+
+#[test]
+#[serial]
+fn amend_editor_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    let error = commands::commit_amend(None, None, true, None, false).unwrap_err();
+    assert!(error.to_string().contains("Nothing to amend"));
+
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial comit".to_string(), None).unwrap();
+
+    // SAFETY: the tests of this file run serially and nothing else reads the environment.
+    unsafe {
+        std::env::set_var("FLUX_EDITOR", "sed -i -e 's/comit/commit/'");
+    }
+    let amended = commands::commit_amend(None, None, false, None, false).unwrap();
+    let repo = Repository::open(None).unwrap();
+    let info = commit::parse_commit(&repo.store_dir, &amended).unwrap();
+    assert_eq!(info.message, "Initial commit");
+    assert!(info.parents.is_empty());
+    assert!(!repo.store_dir.join("COMMIT_EDITMSG").exists());
+
+    // emptying the message aborts
+    unsafe {
+        std::env::set_var("FLUX_EDITOR", "sed -i -e '/^[^#]/d'");
+    }
+    let error = commands::commit_amend(None, None, false, None, false).unwrap_err();
+    assert!(error.to_string().contains("empty commit message"));
+    assert_eq!(
+        Repository::open(None)
+            .unwrap()
+            .resolve_revision("HEAD")
+            .unwrap(),
+        amended
+    );
+
+    unsafe {
+        std::env::remove_var("FLUX_EDITOR");
+    }
+}
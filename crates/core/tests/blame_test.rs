@@ -0,0 +1,133 @@
+use common::git;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+fn git_commit(repo: &Path, author: &str, date: &str, message: &str) {
+    git(repo, &["add", "."]);
+    git(
+        repo,
+        &[
+            "commit", "-q", "--author", author, "--date", date, "-m", message,
+        ],
+    );
+}
+
+#[test]
+#[serial]
+fn blame_matches_git_test() {
+    let temp = TempDir::new().unwrap();
+    let repo_path = temp.path().join("project");
+    fs::create_dir_all(&repo_path).unwrap();
+    git(&repo_path, &["init", "-q", "-b", "main"]);
+
+    fs::write(repo_path.join("poem.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+    git_commit(
+        &repo_path,
+        "Alice <alice@example.com>",
+        "2024-01-01T10:00:00+01:00",
+        "Initial poem",
+    );
+
+    git(&repo_path, &["checkout", "-q", "-b", "feature"]);
+    fs::write(repo_path.join("poem.txt"), "one\ntwo\nthree\nFOUR\nfive\n").unwrap();
+    git_commit(
+        &repo_path,
+        "Bob <bob@example.com>",
+        "2024-01-02T10:00:00-05:00",
+        "Shout the ending",
+    );
+
+    git(&repo_path, &["checkout", "-q", "main"]);
+    fs::write(repo_path.join("poem.txt"), "zero\none\ntwo\nthree\nfour\n").unwrap();
+    git_commit(
+        &repo_path,
+        "Carol <carol@example.com>",
+        "2024-01-03T10:00:00+00:00",
+        "Start from zero",
+    );
+    git(
+        &repo_path,
+        &["merge", "-q", "--no-ff", "feature", "-m", "Merge feature"],
+    );
+    fs::write(
+        repo_path.join("poem.txt"),
+        "zero\none\n2\nthree\nFOUR\nfive\n",
+    )
+    .unwrap();
+    git_commit(
+        &repo_path,
+        "Alice <alice@example.com>",
+        "2024-01-04T10:00:00+01:00",
+        "Use a digit",
+    );
+
+    let repo = Repository::open(Some(repo_path.to_string_lossy().into_owned())).unwrap();
+    let blame = repo.blame("poem.txt", None).unwrap();
+    let authors: Vec<&str> = blame
+        .lines
+        .iter()
+        .map(|line| blame.commits[&line.commit].author.name.as_str())
+        .collect();
+    assert_eq!(authors, ["Carol", "Alice", "Alice", "Alice", "Bob", "Bob"]);
+
+    assert_eq!(
+        blame.porcelain().trim_end(),
+        git(&repo_path, &["blame", "--porcelain", "poem.txt"])
+    );
+    assert_eq!(
+        blame.format().trim_end(),
+        git(&repo_path, &["blame", "poem.txt"])
+    );
+    assert_eq!(
+        repo.blame("poem.txt", Some("feature"))
+            .unwrap()
+            .porcelain()
+            .trim_end(),
+        git(
+            &repo_path,
+            &["blame", "--porcelain", "feature", "--", "poem.txt"]
+        )
+    );
+}
+
+#[test]
+#[serial]
+fn blame_flux_history_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    fs::write("notes.txt", "first\nsecond\n").unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let initial = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    fs::write("notes.txt", "first\nsecond, edited\nthird").unwrap();
+    commands::add(None, vec!["notes.txt".to_string()]).unwrap();
+    let edit = commands::commit(None, "Edit notes".to_string(), None).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let blame = repo.blame("notes.txt", None).unwrap();
+    let origins: Vec<(&str, usize)> = blame
+        .lines
+        .iter()
+        .map(|line| (line.commit.as_str(), line.original_line))
+        .collect();
+    assert_eq!(
+        origins,
+        [
+            (initial.as_str(), 1),
+            (edit.as_str(), 2),
+            (edit.as_str(), 3)
+        ]
+    );
+    assert_eq!(blame.lines[2].content, "third");
+
+    let earlier = repo.blame("notes.txt", Some("HEAD~1")).unwrap();
+    assert!(earlier.lines.iter().all(|line| line.commit == initial));
+    assert!(repo.blame("missing.txt", None).is_err());
+    assert!(repo.blame("src", None).is_err());
+}
@@ -0,0 +1,124 @@
+use common::git;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+
+fn commit_file(file: &str, content: &str, message: &str) -> String {
+    fs::write(file, content).unwrap();
+    commands::add(None, vec![file.to_string()]).unwrap();
+    commands::commit(None, message.to_string(), None).unwrap()
+}
+
+#[test]
+#[serial]
+fn bundle_create_read_by_git_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let initial = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    let second = commit_file("notes.txt", "notes\n", "Add notes");
+
+    let out = TempDir::new().unwrap();
+    let full = out.path().join("full.bundle");
+    let repo = Repository::open(None).unwrap();
+    repo.bundle_create(&full, &["HEAD".to_string(), "main".to_string()])
+        .unwrap();
+
+    // git verifies and clones the bundle
+    git(out.path(), &["clone", "-q", "full.bundle", "from-git"]);
+    let git_clone = out.path().join("from-git");
+    assert_eq!(git(&git_clone, &["rev-parse", "HEAD"]), second);
+    git(&git_clone, &["fsck", "--strict"]);
+    assert_eq!(
+        fs::read_to_string(git_clone.join("notes.txt")).unwrap(),
+        "notes\n"
+    );
+
+    // an incremental bundle needs its prerequisite on the receiving side
+    let third = commit_file("notes.txt", "more notes\n", "More notes");
+    let incremental = out.path().join("incremental.bundle");
+    let repo = Repository::open(None).unwrap();
+    repo.bundle_create(&incremental, &["main~1..main".to_string()])
+        .unwrap();
+    assert_eq!(
+        git(
+            &git_clone,
+            &["bundle", "list-heads", "../incremental.bundle"]
+        ),
+        format!("{third} refs/heads/main")
+    );
+    git(
+        &git_clone,
+        &["bundle", "verify", "-q", "../incremental.bundle"],
+    );
+    git(
+        &git_clone,
+        &["fetch", "-q", "../incremental.bundle", "main:incoming"],
+    );
+    assert_eq!(git(&git_clone, &["rev-parse", "incoming"]), third);
+
+    let fresh = TempDir::new().unwrap();
+    let fresh = Repository::init(Some(fresh.path().to_string_lossy().into_owned()), false).unwrap();
+    let error = fresh.unbundle(&incremental).unwrap_err().to_string();
+    assert!(error.contains(&second), "{error}");
+    assert!(repo.bundle_create(&full, &[initial]).is_err());
+}
+
+#[test]
+#[serial]
+fn bundle_clone_and_fetch_test() {
+    let temp = TempDir::new().unwrap();
+    let git_repo = temp.path().join("source");
+    fs::create_dir_all(&git_repo).unwrap();
+    git(&git_repo, &["init", "-q", "-b", "trunk"]);
+    fs::write(git_repo.join("README.md"), "Readme\n").unwrap();
+    git(&git_repo, &["add", "."]);
+    git(&git_repo, &["commit", "-q", "-m", "Initial commit"]);
+    git(&git_repo, &["tag", "-a", "v1.0", "-m", "First release"]);
+    git(&git_repo, &["branch", "feature"]);
+    fs::write(git_repo.join("notes.txt"), "notes\n").unwrap();
+    git(&git_repo, &["add", "."]);
+    git(&git_repo, &["commit", "-q", "-m", "Add notes"]);
+    git(
+        &git_repo,
+        &["bundle", "create", "-q", "../project.bundle", "--all"],
+    );
+
+    let _guard = common::WorkingDirGuard::new(temp.path()).unwrap();
+    let clone = Repository::clone_from("project.bundle", None).unwrap();
+    assert_eq!(clone.head, "refs/heads/trunk");
+    assert_eq!(
+        clone.resolve_revision("origin/feature").unwrap(),
+        git(&git_repo, &["rev-parse", "feature"])
+    );
+    assert_eq!(
+        clone.refs.read("refs/tags/v1.0").unwrap().unwrap(),
+        git(&git_repo, &["rev-parse", "v1.0"])
+    );
+    assert_eq!(fs::read_to_string("project/README.md").unwrap(), "Readme\n");
+
+    // fetching from the bundle remote picks up a newer bundle at the same path
+    fs::write(git_repo.join("README.md"), "Readme, updated\n").unwrap();
+    git(&git_repo, &["commit", "-q", "-am", "Update README"]);
+    git(
+        &git_repo,
+        &[
+            "bundle",
+            "create",
+            "-q",
+            "../project.bundle",
+            "trunk~1..trunk",
+        ],
+    );
+
+    let mut clone = Repository::open(Some(clone.work_tree.to_string_lossy().into_owned())).unwrap();
+    assert_eq!(clone.fetch("origin").unwrap().len(), 1);
+    assert_eq!(
+        clone.resolve_revision("origin/trunk").unwrap(),
+        git(&git_repo, &["rev-parse", "trunk"])
+    );
+}
@@ -0,0 +1,160 @@
+use flux_core::{
+    commands, error::Error, objects::commit, repo::repository::Repository,
+    shared::types::apply_outcome::ApplyOutcome,
+};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+/// `main` gets an initial commit, `feature` then changes `src/lib.rs` and adds `src/new.rs`.
+fn diverged_branches() -> (String, String) {
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    commands::create_branch(None, "feature".to_string()).unwrap();
+    fs::write("src/lib.rs", "pub fn add(a: i64, b: i64) -> i64 { a + b }").unwrap();
+    fs::write("src/new.rs", "pub fn new() {}\n").unwrap();
+    commands::add(None, vec!["src".to_string()]).unwrap();
+    let fix = commands::commit(None, "Fix add types".to_string(), None).unwrap();
+
+    commands::switch_branch(None, "main".to_string(), false).unwrap();
+    fs::write("README.md", "Main README").unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    let main_head = commands::commit(None, "Update README".to_string(), None).unwrap();
+
+    (fix, main_head)
+}
+
+#[test]
+#[serial]
+fn cherry_pick_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (fix, main_head) = diverged_branches();
+
+    // the picked commit keeps its author even if the configured user changed
+    fs::write(
+        ".flux/config",
+        "user_name = \"Someone Else\"\nuser_email = \"else@example.com\"\n",
+    )
+    .unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let ApplyOutcome::Committed(hash) = repo.cherry_pick("feature").unwrap() else {
+        panic!("cherry-pick should apply cleanly");
+    };
+
+    let picked = commit::parse_commit(&repo.store_dir, &hash).unwrap();
+    let original = commit::parse_commit(&repo.store_dir, &fix).unwrap();
+    assert_eq!(picked.parents, vec![main_head]);
+    assert_eq!(picked.message, original.message);
+    assert_eq!(picked.author, original.author);
+    assert_eq!(picked.committer.name, "Someone Else");
+
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "Main README");
+    assert!(project_path.join("src/new.rs").exists());
+    assert_eq!(
+        fs::read_to_string("src/lib.rs").unwrap(),
+        "pub fn add(a: i64, b: i64) -> i64 { a + b }"
+    );
+}
+
+#[test]
+#[serial]
+fn revert_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    diverged_branches();
+
+    let mut repo = Repository::open(None).unwrap();
+    let ApplyOutcome::Committed(hash) = repo.revert("HEAD").unwrap() else {
+        panic!("revert should apply cleanly");
+    };
+
+    let revert = commit::parse_commit(&repo.store_dir, &hash).unwrap();
+    assert!(revert.message.starts_with("Revert \"Update README\""));
+    assert_eq!(
+        fs::read_to_string("README.md").unwrap(),
+        "Read this file before running the project"
+    );
+}
+
+#[test]
+#[serial]
+fn cherry_pick_conflict_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (fix, main_head) = diverged_branches();
+
+    fs::write("src/lib.rs", "pub fn add(a: u8, b: u8) -> u8 { a + b }").unwrap();
+    commands::add(None, vec!["src/lib.rs".to_string()]).unwrap();
+    let conflicting = commands::commit(None, "Use u8".to_string(), None).unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let outcome = repo.cherry_pick(&fix).unwrap();
+    assert_eq!(
+        outcome,
+        ApplyOutcome::Conflicts(vec!["src/lib.rs".to_string()])
+    );
+
+    let content = fs::read_to_string("src/lib.rs").unwrap();
+    assert!(content.starts_with("<<<<<<< HEAD\n"));
+    assert!(project_path.join("src/new.rs").exists());
+
+    // cannot continue before resolving
+    assert!(repo.pick_continue().is_err());
+
+    // abort goes back to the state before the cherry-pick
+    repo.pick_abort().unwrap();
+    assert!(!project_path.join("src/new.rs").exists());
+    assert_eq!(
+        fs::read_to_string("src/lib.rs").unwrap(),
+        "pub fn add(a: u8, b: u8) -> u8 { a + b }"
+    );
+
+    // resolve and continue
+    let mut repo = Repository::open(None).unwrap();
+    repo.cherry_pick(&fix).unwrap();
+    fs::write("src/lib.rs", "pub fn add(a: u64, b: u64) -> u64 { a + b }").unwrap();
+    commands::add(None, vec!["src/lib.rs".to_string()]).unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let hash = repo.pick_continue().unwrap();
+    let picked = commit::parse_commit(&repo.store_dir, &hash).unwrap();
+    assert_eq!(picked.parents, vec![conflicting]);
+    assert_eq!(picked.message, "Fix add types");
+    assert_ne!(picked.parents[0], main_head);
+    assert!(!project_path.join(".flux/CHERRY_PICK_HEAD").exists());
+}
+
+#[test]
+#[serial]
+fn cherry_pick_refusals_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (fix, main_head) = diverged_branches();
+
+    // an untracked file in the way of an incoming one is never overwritten
+    fs::write("src/new.rs", "// mine\n").unwrap();
+    let mut repo = Repository::open(None).unwrap();
+    let err = repo.cherry_pick(&fix).unwrap_err();
+    assert!(matches!(err, Error::DirtyWorkTree(_)), "{err}");
+    assert_eq!(fs::read_to_string("src/new.rs").unwrap(), "// mine\n");
+    assert_eq!(repo.resolve_revision("HEAD").unwrap(), main_head);
+    assert!(!project_path.join(".flux/CHERRY_PICK_HEAD").exists());
+    fs::remove_file("src/new.rs").unwrap();
+
+    // nor can a commit be picked in the middle of a rebase
+    fs::create_dir(".flux/rebase-merge").unwrap();
+    let err = repo.cherry_pick(&fix).unwrap_err();
+    assert!(matches!(err, Error::OperationInProgress(_)), "{err}");
+    assert!(repo.revert("HEAD").is_err());
+    fs::remove_dir(".flux/rebase-merge").unwrap();
+
+    assert!(matches!(
+        repo.cherry_pick(&fix).unwrap(),
+        ApplyOutcome::Committed(_)
+    ));
+}
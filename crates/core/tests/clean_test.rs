@@ -0,0 +1,210 @@
+use flux_core::ignore::IgnoreRules;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+#[test]
+fn ignore_rules_test() {
+    let mut rules = IgnoreRules::default();
+    rules.add(
+        "",
+        "# build outputs\n*.o\n!keep.o\ntarget/\n/docs/*.html\nlogs/**/*.log\n\n",
+    );
+    rules.add("src", "generated.rs\n!/lib.o\n");
+
+    assert!(rules.is_ignored("main.o", false));
+    assert!(rules.is_ignored("src/deep/main.o", false));
+    assert!(!rules.is_ignored("keep.o", false));
+    assert!(!rules.is_ignored("src/lib.o", false));
+
+    // a directory pattern hides everything under the directory, not files of that name
+    assert!(rules.is_ignored("target", true));
+    assert!(rules.is_ignored("src/target/debug/flux", false));
+    assert!(!rules.is_ignored("target", false));
+
+    // patterns with a slash are relative to their file
+    assert!(rules.is_ignored("docs/index.html", false));
+    assert!(!rules.is_ignored("src/docs/index.html", false));
+    assert!(rules.is_ignored("logs/a/b/run.log", false));
+    assert!(rules.is_ignored("logs/run.log", false));
+
+    assert!(rules.is_ignored("src/nested/generated.rs", false));
+    assert!(!rules.is_ignored("generated.rs", false));
+}
+
+#[test]
+#[serial]
+fn clean_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    fs::write(".fluxignore", "*.log\nbuild/\n").unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    fs::write("notes.txt", "").unwrap();
+    fs::write("src/scratch.rs", "").unwrap();
+    fs::write("debug.log", "").unwrap();
+    fs::create_dir_all("out/bin").unwrap();
+    fs::write("out/bin/flux", "").unwrap();
+    fs::write("out/flux.d", "").unwrap();
+    fs::create_dir_all("build").unwrap();
+    fs::write("build/a.o", "").unwrap();
+    fs::create_dir_all("mixed").unwrap();
+    fs::write("mixed/kept.log", "").unwrap();
+    fs::write("mixed/removed.txt", "").unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let all = repo.pathspec(&[]).unwrap();
+    assert_eq!(
+        repo.untracked_paths(&all, false, false).unwrap(),
+        ["notes.txt", "src/scratch.rs"]
+    );
+    // an untracked directory goes as a whole, unless it holds ignored files
+    assert_eq!(
+        repo.untracked_paths(&all, true, false).unwrap(),
+        ["mixed/removed.txt", "notes.txt", "out/", "src/scratch.rs"]
+    );
+    assert_eq!(
+        repo.untracked_paths(&all, true, true).unwrap(),
+        [
+            "build/",
+            "debug.log",
+            "mixed/",
+            "notes.txt",
+            "out/",
+            "src/scratch.rs"
+        ]
+    );
+    let src = repo.pathspec(&["src".to_string()]).unwrap();
+    assert_eq!(
+        repo.untracked_paths(&src, true, true).unwrap(),
+        ["src/scratch.rs"]
+    );
+
+    let error = commands::clean(None, Vec::new(), false, false, false, false).unwrap_err();
+    assert!(error.to_string().contains("clean.requireForce"));
+    commands::clean(None, Vec::new(), true, false, true, false).unwrap();
+    assert!(Path::new("notes.txt").exists());
+
+    commands::clean(None, Vec::new(), false, true, true, false).unwrap();
+    assert!(!Path::new("notes.txt").exists());
+    assert!(!Path::new("src/scratch.rs").exists());
+    assert!(!Path::new("out").exists());
+    assert!(!Path::new("mixed/removed.txt").exists());
+    assert!(Path::new("mixed/kept.log").exists());
+    assert!(Path::new("build/a.o").exists());
+    assert!(Path::new("src/main.rs").exists());
+
+    commands::clean(None, Vec::new(), false, true, true, true).unwrap();
+    assert!(!Path::new("build").exists());
+    assert!(!Path::new("mixed").exists());
+    assert!(!Path::new("debug.log").exists());
+    assert!(Path::new(".fluxignore").exists());
+}
+
+#[test]
+#[serial]
+fn clean_nested_repository_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    fs::create_dir_all("sub").unwrap();
+    common::git(&project_path.join("sub"), &["init", "-q"]);
+    fs::write("sub/file.txt", "nested\n").unwrap();
+    common::git(&project_path.join("sub"), &["add", "file.txt"]);
+    common::git(&project_path.join("sub"), &["commit", "-q", "-m", "Nested"]);
+    fs::create_dir_all("out/inner").unwrap();
+    common::git(&project_path.join("out/inner"), &["init", "-q"]);
+    fs::write("out/stale.o", "").unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let all = repo.pathspec(&[]).unwrap();
+    assert_eq!(
+        repo.untracked_paths(&all, true, true).unwrap(),
+        ["out/stale.o"]
+    );
+
+    commands::clean(None, Vec::new(), false, true, true, true).unwrap();
+    assert!(Path::new("sub/.git/HEAD").is_file());
+    assert!(Path::new("sub/file.txt").is_file());
+    assert!(Path::new("out/inner/.git/HEAD").is_file());
+    assert!(!Path::new("out/stale.o").exists());
+}
+
+#[test]
+#[serial]
+fn clean_empty_directories_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    fs::write(".fluxignore", "*.log\ncache/\n").unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    fs::create_dir("empty").unwrap();
+    fs::create_dir_all("src/gen/deeper").unwrap();
+    fs::create_dir_all("nested/a/b").unwrap();
+    fs::create_dir_all("nested/c").unwrap();
+    fs::create_dir_all("cache/tmp").unwrap();
+    fs::create_dir_all("logs/old").unwrap();
+    fs::write("logs/run.log", "").unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let all = repo.pathspec(&[]).unwrap();
+    assert!(repo.untracked_paths(&all, false, false).unwrap().is_empty());
+    assert_eq!(
+        repo.untracked_paths(&all, true, false).unwrap(),
+        ["empty/", "logs/old/", "nested/", "src/gen/"]
+    );
+    assert_eq!(
+        repo.untracked_paths(&all, true, true).unwrap(),
+        ["cache/", "empty/", "logs/", "nested/", "src/gen/"]
+    );
+
+    commands::clean(None, Vec::new(), false, true, true, false).unwrap();
+    for removed in ["empty", "nested", "src/gen", "logs/old"] {
+        assert!(!Path::new(removed).exists(), "{removed}");
+    }
+    assert!(Path::new("cache/tmp").is_dir());
+    assert!(Path::new("logs/run.log").is_file());
+    assert!(Path::new("src/main.rs").is_file());
+}
+
+#[test]
+#[serial]
+fn ignored_files_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    fs::write(".fluxignore", "target/\n").unwrap();
+    fs::create_dir_all("target").unwrap();
+    fs::write("target/o", "").unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    assert!(repo.index.get("target/o").is_none());
+    fs::write("notes.txt", "").unwrap();
+    let statuses = repo.status(&repo.pathspec(&[]).unwrap()).unwrap();
+    let paths: Vec<&str> = statuses.iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(paths, ["notes.txt"]);
+
+    commands::add_update(None, Vec::new(), true).unwrap();
+    let repo = Repository::open(None).unwrap();
+    assert!(repo.index.get("notes.txt").is_some());
+    assert!(repo.index.get("target/o").is_none());
+    commands::commit(None, "Add notes".to_string(), None).unwrap();
+
+    // stash -u leaves ignored files in place
+    fs::write("scratch.txt", "").unwrap();
+    commands::stash_push(None, None, true).unwrap();
+    assert!(!Path::new("scratch.txt").exists());
+    assert!(Path::new("target/o").exists());
+}
@@ -0,0 +1,63 @@
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+
+#[test]
+#[serial]
+fn clone_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let main_head = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    commands::create_branch(None, "feature".to_string()).unwrap();
+    fs::write("feature.txt", "feature\n").unwrap();
+    commands::add(None, vec!["feature.txt".to_string()]).unwrap();
+    let feature_head = commands::commit(None, "Add feature".to_string(), None).unwrap();
+    commands::switch_branch(None, "main".to_string(), false).unwrap();
+
+    let source = Repository::open(None).unwrap();
+    source.refs.write("refs/tags/v1.0", &main_head).unwrap();
+
+    let clone_temp = TempDir::new().unwrap();
+    let target = clone_temp.path().join("copy");
+    let target_str = target.to_string_lossy().into_owned();
+    let clone = Repository::clone_from(
+        &format!("file://{}", project_path.display()),
+        Some(&target_str),
+    )
+    .unwrap();
+
+    // the default branch is checked out, every source branch is remote-tracking
+    assert_eq!(clone.head, "refs/heads/main");
+    assert_eq!(clone.resolve_revision("main").unwrap(), main_head);
+    assert_eq!(
+        clone.resolve_revision("origin/feature").unwrap(),
+        feature_head
+    );
+    assert_eq!(clone.resolve_revision("v1.0").unwrap(), main_head);
+    assert_eq!(
+        clone.list_refs("refs/heads/", None).unwrap(),
+        vec!["main".to_string()]
+    );
+
+    assert_eq!(
+        fs::read_to_string(target.join("README.md")).unwrap(),
+        "Read this file before running the project"
+    );
+    assert!(!target.join("feature.txt").exists());
+    assert!(clone.index.get("src/main.rs").is_some());
+
+    let config = Repository::open(Some(target_str.clone())).unwrap().config;
+    assert_eq!(
+        config.remotes().get("origin").map(String::as_str),
+        Some(fs::canonicalize(&project_path).unwrap().to_str().unwrap())
+    );
+
+    // a non-empty destination is refused
+    assert!(Repository::clone_from(".", Some(&target_str)).is_err());
+}
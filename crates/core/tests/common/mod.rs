@@ -1,5 +1,12 @@
-use std::{env, fs, path::{Path, PathBuf}, process::Command};
+#![allow(dead_code)]
+
 use anyhow::Context;
+use flux_core::{commands, repo::repository::Repository};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 use tempfile::TempDir;
 
 pub struct WorkingDirGuard {
@@ -23,27 +30,27 @@ impl Drop for WorkingDirGuard {
 pub fn setup_test_project() -> (TempDir, PathBuf) {
     let temp = TempDir::new().unwrap();
     let project_path = temp.path().to_path_buf();
-    
+
     fs::write(
         project_path.join("README.md"),
         "Read this file before running the project",
     )
     .unwrap();
-    
+
     fs::create_dir(project_path.join("src")).unwrap();
-    
+
     fs::write(
         project_path.join("src/main.rs"),
         r#"pub fn main() { println!("{}", add(1, 2)) }"#,
     )
     .unwrap();
-    
+
     fs::write(
         project_path.join("src/lib.rs"),
         "pub fn add(a: i32, b: i32) -> i64 { a + b }",
     )
     .unwrap();
-    
+
     (temp, project_path)
 }
 
@@ -61,4 +68,36 @@ pub fn git_hash_object(path: &str) -> anyhow::Result<String> {
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-}
\ No newline at end of file
+}
+/// Runs git in `dir` with a fixed identity and returns its trimmed standard output.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "user.name=Git User",
+            "-c",
+            "user.email=git@example.com",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+pub fn init_repository() {
+    Repository::init(None, false).unwrap();
+    commands::set(None, "user_name".to_string(), "Test User".to_string()).unwrap();
+    commands::set(
+        None,
+        "user_email".to_string(),
+        "test@example.com".to_string(),
+    )
+    .unwrap();
+}
@@ -0,0 +1,196 @@
+use flux_core::commands;
+use flux_core::error::Error;
+use flux_core::repo::config::{Config, ConfigScope};
+use flux_core::repo::repository::Repository;
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+
+#[test]
+#[serial]
+fn config_set_unset_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.config.set("core.editor", "vi").unwrap();
+    repo.config.set("alias.st", "status --short").unwrap();
+    repo.add_remote("origin", "../origin").unwrap();
+    repo.config.set("branch.main.remote", "origin").unwrap();
+    repo.config
+        .set("branch.main.merge", "refs/heads/main")
+        .unwrap();
+
+    // setting a key again replaces it in place, comments stay where they are
+    let path = project_path.join(".flux/config");
+    let edited = fs::read_to_string(&path)
+        .unwrap()
+        .replace("[core]", "# editor used for commit messages\n[core]");
+    fs::write(&path, edited).unwrap();
+    commands::set(None, "core.editor".to_string(), "nano".to_string()).unwrap();
+    commands::set(None, "user.name".to_string(), "Renamed".to_string()).unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(content.matches("editor =").count(), 1, "{content}");
+    assert!(content.contains("# editor used for commit messages\n[core]\neditor = \"nano\""));
+    assert!(content.starts_with("# Configuration file for flux"));
+
+    let mut repo = Repository::open(None).unwrap();
+    let config = &repo.config;
+    assert_eq!(config.get_str("core.editor"), Some("nano"));
+    assert_eq!(config.get_str("CORE.Editor"), Some("nano"));
+    assert_eq!(config.user_name(), Some("Renamed"));
+    assert_eq!(config.user_email(), Some("test@example.com"));
+    assert_eq!(config.get_str("branch.main.merge"), Some("refs/heads/main"));
+    assert_eq!(config.subsections("branch"), ["main"]);
+    assert_eq!(
+        config.section("alias").get("st").map(String::as_str),
+        Some("status --short")
+    );
+
+    repo.config.unset("core.editor").unwrap();
+    assert!(repo.config.unset("core.editor").is_err());
+    assert_eq!(
+        Repository::open(None)
+            .unwrap()
+            .config
+            .get_str("core.editor"),
+        None
+    );
+
+    // keys outside the known sections are refused
+    assert!(repo.config.set("editor", "vi").is_err());
+    assert!(repo.config.set("colour.ui", "auto").is_err());
+    assert!(repo.config.set("user.nickname", "x").is_err());
+    assert!(repo.config.set("remote.origin.token", "x").is_err());
+}
+
+#[test]
+#[serial]
+fn config_typed_getters_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.config.set("core.filemode", "yes").unwrap();
+    repo.config.set("core.compression", "9").unwrap();
+    repo.config.set("core.bigfilethreshold", "512k").unwrap();
+    repo.config.set("core.pager", "less").unwrap();
+
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.get_bool("core.filemode").unwrap(), Some(true));
+    assert_eq!(config.get_int("core.compression").unwrap(), Some(9));
+    assert_eq!(
+        config.get_int("core.bigfilethreshold").unwrap(),
+        Some(512 * 1024)
+    );
+    assert!(config.get_bool("core.pager").is_err());
+    assert!(config.get_int("core.pager").is_err());
+    let mut repo = Repository::open(None).unwrap();
+    repo.config.set("core.compression", "99999999999g").unwrap();
+    let error = repo.config.get_int("core.compression").unwrap_err();
+    assert!(matches!(error, Error::InvalidConfig(_)));
+    assert_eq!(config.get_bool("core.missing").unwrap(), None);
+
+    // TOML values that are not strings are read as well
+    let path = project_path.join(".flux/config");
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.replace("\"yes\"", "false")).unwrap();
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.get_bool("core.filemode").unwrap(), Some(false));
+
+    // config files from before sections still load
+    fs::write(
+        &path,
+        "user_name = \"Old User\"\n\n[remote.origin]\nurl = \"../o\"\n",
+    )
+    .unwrap();
+    let mut repo = Repository::open(None).unwrap();
+    assert_eq!(repo.config.user_name(), Some("Old User"));
+    repo.config.set("user.name", "New User").unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "[remote.origin]\nurl = \"../o\"\n\n[user]\nname = \"New User\"\n"
+    );
+}
+
+#[test]
+#[serial]
+fn config_layers_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let home = TempDir::new().unwrap();
+    let global = home.path().join(".fluxconfig");
+    let system = home.path().join("system");
+    fs::write(
+        &system,
+        "[user]\nname = \"System User\"\nemail = \"system@example.com\"\n\n[core]\neditor = \"ed\"\n",
+    )
+    .unwrap();
+    fs::write(&global, "# my settings\n[user]\nname = \"Global User\"\n").unwrap();
+
+    // SAFETY: the tests of this file run serially and nothing else reads the environment.
+    unsafe {
+        std::env::set_var("FLUX_CONFIG_SYSTEM", &system);
+        std::env::set_var("FLUX_CONFIG_GLOBAL", &global);
+    }
+
+    Repository::init(None, false).unwrap();
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.user_name(), Some("Global User"));
+    assert_eq!(config.user_email(), Some("system@example.com"));
+    assert_eq!(config.get_str("core.editor"), Some("ed"));
+
+    commands::set(None, "user.name".to_string(), "Repo User".to_string()).unwrap();
+    commands::set_global("core.editor".to_string(), "vim".to_string()).unwrap();
+    assert_eq!(
+        fs::read_to_string(&global).unwrap(),
+        "# my settings\n[user]\nname = \"Global User\"\n\n[core]\neditor = \"vim\"\n"
+    );
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.user_name(), Some("Repo User"));
+    assert_eq!(config.get_str("core.editor"), Some("vim"));
+
+    // environment overrides beat every file
+    unsafe {
+        std::env::set_var("FLUX_CONFIG_COUNT", "1");
+        std::env::set_var("FLUX_CONFIG_KEY_0", "user.name");
+        std::env::set_var("FLUX_CONFIG_VALUE_0", "Env User");
+    }
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.user_name(), Some("Env User"));
+    assert_eq!(Config::global().unwrap().user_name(), Some("Env User"));
+
+    let mut global_config = Config::global().unwrap();
+    global_config
+        .unset_in(ConfigScope::Global, "user.name")
+        .unwrap();
+    assert!(
+        global_config
+            .unset_in(ConfigScope::Global, "user.name")
+            .is_err()
+    );
+    assert!(
+        global_config
+            .set_in(ConfigScope::Override, "user.name", "x")
+            .is_err()
+    );
+
+    unsafe {
+        for name in [
+            "FLUX_CONFIG_SYSTEM",
+            "FLUX_CONFIG_GLOBAL",
+            "FLUX_CONFIG_COUNT",
+            "FLUX_CONFIG_KEY_0",
+            "FLUX_CONFIG_VALUE_0",
+        ] {
+            std::env::remove_var(name);
+        }
+    }
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.user_name(), Some("Repo User"));
+    assert_eq!(config.get_str("core.editor"), None);
+}
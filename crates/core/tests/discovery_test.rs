@@ -0,0 +1,87 @@
+use flux_core::error::Error;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+#[test]
+#[serial]
+fn open_from_subdirectory_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    fs::create_dir_all("src/nested").unwrap();
+    fs::write("src/nested/deep.rs", "// deep").unwrap();
+
+    let _inner = common::WorkingDirGuard::new(&project_path.join("src/nested")).unwrap();
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(repo.work_tree, project_path.canonicalize().unwrap());
+    assert_eq!(repo.prefix, "src/nested");
+
+    // paths are relative to the current directory, `..` included
+    assert_eq!(repo.resolve_path("deep.rs").unwrap(), "src/nested/deep.rs");
+    assert_eq!(repo.resolve_path("../main.rs").unwrap(), "src/main.rs");
+    assert_eq!(repo.resolve_path("./../..").unwrap(), "");
+    assert!(repo.resolve_path("../../..").is_err());
+    let absolute = project_path.join("README.md");
+    assert_eq!(
+        repo.resolve_path(absolute.to_str().unwrap()).unwrap(),
+        "README.md"
+    );
+
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::add(None, vec!["../lib.rs".to_string()]).unwrap();
+    let repo = Repository::open(None).unwrap();
+    let staged: Vec<&String> = repo.index.map.keys().collect();
+    assert_eq!(staged.len(), 2);
+    assert!(repo.index.get("src/nested/deep.rs").is_some());
+    assert!(repo.index.get("src/lib.rs").is_some());
+
+    commands::remove(None, vec!["deep.rs".to_string()]).unwrap();
+    let repo = Repository::open(None).unwrap();
+    assert!(repo.index.get("src/nested/deep.rs").is_none());
+
+    // an explicit path is searched upwards as well, and paths are relative to it
+    let repo = Repository::open(Some(project_path.join("src").display().to_string())).unwrap();
+    assert_eq!(repo.prefix, "src");
+    assert_eq!(repo.resolve_path("lib.rs").unwrap(), "src/lib.rs");
+}
+
+#[test]
+#[serial]
+fn environment_override_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    let outside = tempfile::TempDir::new().unwrap();
+
+    let _inner = common::WorkingDirGuard::new(outside.path()).unwrap();
+    let error = Repository::open(None).err().unwrap();
+    assert!(matches!(error, Error::NotARepository(_)));
+
+    // SAFETY: the tests of this file run serially and nothing else reads the environment.
+    unsafe {
+        std::env::set_var("FLUX_DIR", project_path.join(".flux"));
+    }
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(repo.work_tree, project_path.canonicalize().unwrap());
+    assert_eq!(repo.prefix, "");
+
+    unsafe {
+        std::env::set_var("FLUX_WORK_TREE", outside.path());
+    }
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(repo.work_tree, outside.path().canonicalize().unwrap());
+
+    // an explicit path is not redirected
+    let error = Repository::open(Some(outside.path().display().to_string()))
+        .err()
+        .unwrap();
+    assert!(matches!(error, Error::NotARepository(_)));
+
+    unsafe {
+        std::env::remove_var("FLUX_DIR");
+        std::env::remove_var("FLUX_WORK_TREE");
+    }
+}
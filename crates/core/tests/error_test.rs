@@ -0,0 +1,89 @@
+use flux_core::error::{Context, Error};
+use flux_core::{commands, repo::repository::Repository, utils};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+#[test]
+#[serial]
+fn typed_errors_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+
+    let error = Repository::open(None).err().unwrap();
+    assert!(matches!(error, Error::NotARepository(_)));
+    assert_eq!(error.kind(), "not_a_repository");
+
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    let error = commands::commit(None, "Again".to_string(), None).unwrap_err();
+    assert!(matches!(error, Error::NothingToCommit));
+
+    let mut repo = Repository::open(None).unwrap();
+    let error = repo.new_branch("main").unwrap_err();
+    assert!(matches!(&error, Error::RefExists(name) if name == "refs/heads/main"));
+    assert_eq!(error.to_string(), "Branch 'main' already exists");
+
+    repo.new_branch("feature").unwrap();
+    let error = repo.new_branch("feature/nested").unwrap_err();
+    assert!(
+        matches!(error.root(), Error::RefConflict { existing, .. } if existing == "refs/heads/feature")
+    );
+
+    let error = repo.switch_branch("missing", false).unwrap_err();
+    assert!(matches!(error, Error::RefNotFound(_)));
+    assert_eq!(error.to_string(), "Branch 'missing' does not exist");
+
+    fs::write("README.md", "changed").unwrap();
+    let error = repo.switch_branch("feature", false).unwrap_err();
+    assert_eq!(error.kind(), "dirty_work_tree");
+
+    let error = repo.resolve_revision("nothing-here").unwrap_err();
+    assert!(
+        matches!(error.root(), Error::RevisionNotFound(revision) if revision == "nothing-here")
+    );
+
+    let error = commands::get(None, "core.editor".to_string()).unwrap_err();
+    assert!(matches!(error, Error::ConfigMissing(key) if key == "core.editor"));
+}
+
+#[test]
+#[serial]
+fn object_errors_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let missing = "0123456789abcdef0123456789abcdef01234567";
+    let error = utils::read_object(&repo.store_dir, missing).err().unwrap();
+    assert!(matches!(&error, Error::ObjectNotFound(hash) if hash == missing));
+    assert_eq!(error.kind(), "object_not_found");
+
+    // a loose object that is not zlib data
+    let hash = repo.hash_object("README.md".to_string(), false).unwrap();
+    let path = utils::object_path(&repo.store_dir, &hash);
+    fs::write(&path, "not compressed").unwrap();
+    let error = utils::read_object(&repo.store_dir, &hash).err().unwrap();
+    assert!(matches!(error, Error::CorruptObject { .. }));
+    assert!(
+        error
+            .to_string()
+            .starts_with(&format!("Corrupt object {hash}:"))
+    );
+
+    // errors given context keep their kind, and show the whole chain
+    let error = utils::read_object(&repo.store_dir, missing)
+        .context("Could not read the tree")
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), "object_not_found");
+    assert_eq!(
+        error.full_message(),
+        format!("Could not read the tree: Object {missing} not found")
+    );
+}
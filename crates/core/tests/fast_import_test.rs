@@ -0,0 +1,149 @@
+use common::git;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+mod common;
+
+fn git_fast_import(dir: &Path, stream: &[u8]) {
+    let mut child = Command::new("git")
+        .args(["fast-import", "--quiet"])
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stream).unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+/// A git repository with a merge, a deletion, an executable, a path with spaces
+/// and both kinds of tags.
+fn git_history(repo: &Path) {
+    fs::create_dir_all(repo.join("src")).unwrap();
+    git(repo, &["init", "-q", "-b", "main"]);
+    fs::write(repo.join("README.md"), "Readme\n").unwrap();
+    fs::write(repo.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+    fs::write(repo.join("old notes.txt"), "notes\n").unwrap();
+    git(repo, &["add", "."]);
+    git(repo, &["commit", "-q", "-m", "Initial commit"]);
+    git(repo, &["tag", "-a", "v1.0", "-m", "First release"]);
+
+    git(repo, &["checkout", "-q", "-b", "feature"]);
+    fs::write(repo.join("run.sh"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(repo.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    git(repo, &["add", "."]);
+    git(repo, &["rm", "-q", "old notes.txt"]);
+    git(repo, &["commit", "-q", "-m", "Add script, drop notes"]);
+
+    git(repo, &["checkout", "-q", "main"]);
+    fs::write(repo.join("README.md"), "Readme, updated\n").unwrap();
+    git(repo, &["commit", "-q", "-am", "Update README"]);
+    git(
+        repo,
+        &["merge", "-q", "--no-ff", "feature", "-m", "Merge feature"],
+    );
+    git(repo, &["tag", "light"]);
+}
+
+#[test]
+#[serial]
+fn fast_import_git_stream_test() {
+    let temp = TempDir::new().unwrap();
+    let git_repo = temp.path().join("git");
+    git_history(&git_repo);
+    let stream = Command::new("git")
+        .args(["fast-export", "--all"])
+        .current_dir(&git_repo)
+        .output()
+        .unwrap()
+        .stdout;
+
+    let flux_repo = temp.path().join("flux");
+    let mut repo = Repository::init(Some(flux_repo.to_string_lossy().into_owned()), false).unwrap();
+    let updated = repo.fast_import(&stream).unwrap();
+    assert_eq!(updated.len(), 4);
+
+    // identical objects give identical hashes, down to the annotated tag
+    for name in [
+        "refs/heads/main",
+        "refs/heads/feature",
+        "refs/tags/v1.0",
+        "refs/tags/light",
+    ] {
+        assert_eq!(
+            repo.refs.read(name).unwrap().unwrap(),
+            git(&git_repo, &["rev-parse", name]),
+            "{name}"
+        );
+    }
+    assert_eq!(
+        repo.list_refs("refs/heads/", None).unwrap(),
+        ["feature", "main"]
+    );
+}
+
+#[test]
+#[serial]
+fn fast_import_truncated_data_test() {
+    let temp = TempDir::new().unwrap();
+    let mut repo =
+        Repository::init(Some(temp.path().to_string_lossy().into_owned()), false).unwrap();
+
+    for length in ["64", &usize::MAX.to_string()] {
+        let stream = format!("blob\nmark :1\ndata {length}\nshort\n");
+        let err = repo.fast_import(stream.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("Truncated data block"), "{err}");
+    }
+}
+
+#[test]
+#[serial]
+fn fast_export_to_git_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    commands::create_branch(None, "feature".to_string()).unwrap();
+    fs::write("src/lib.rs", "pub fn add() {}\n").unwrap();
+    fs::remove_file("README.md").unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let mut repo = Repository::open(None).unwrap();
+    repo.index.remove("README.md".to_string()).unwrap();
+    repo.index.flush().unwrap();
+    repo.commit("Change lib, drop README".to_string()).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let stream = repo.fast_export().unwrap();
+
+    let target = TempDir::new().unwrap();
+    git(target.path(), &["init", "-q", "--bare"]);
+    git_fast_import(target.path(), &stream);
+    git(target.path(), &["fsck", "--strict"]);
+    for branch in ["main", "feature"] {
+        assert_eq!(
+            git(target.path(), &["rev-parse", branch]),
+            repo.resolve_revision(branch).unwrap()
+        );
+    }
+    assert_eq!(
+        git(target.path(), &["ls-tree", "-r", "--name-only", "feature"]),
+        "src/lib.rs\nsrc/main.rs"
+    );
+
+    // and back into flux
+    let copy = TempDir::new().unwrap();
+    let mut copy =
+        Repository::init(Some(copy.path().to_string_lossy().into_owned()), false).unwrap();
+    copy.fast_import(&stream).unwrap();
+    assert_eq!(
+        copy.resolve_revision("feature").unwrap(),
+        repo.resolve_revision("feature").unwrap()
+    );
+}
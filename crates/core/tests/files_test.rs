@@ -0,0 +1,170 @@
+use flux_core::error::Error;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+fn staged() -> Vec<String> {
+    Repository::open(None)
+        .unwrap()
+        .index
+        .entries()
+        .into_keys()
+        .collect()
+}
+
+#[test]
+#[serial]
+fn add_update_and_all_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    fs::write("README.md", "changed").unwrap();
+    fs::remove_file("src/lib.rs").unwrap();
+    fs::write("src/new.rs", "// new").unwrap();
+
+    // -u stages modifications and deletions, not new files
+    commands::add_update(None, Vec::new(), false).unwrap();
+    assert_eq!(staged(), ["README.md", "src/main.rs"]);
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(
+        repo.index.get("README.md").unwrap().hash,
+        common::git_hash_object("README.md").unwrap()
+    );
+
+    // -A stages everything
+    commands::add_update(None, Vec::new(), true).unwrap();
+    assert_eq!(staged(), ["README.md", "src/main.rs", "src/new.rs"]);
+
+    // adding a deleted path stages its removal
+    fs::remove_file("src/main.rs").unwrap();
+    commands::add(None, vec!["src/main.rs".to_string()]).unwrap();
+    assert_eq!(staged(), ["README.md", "src/new.rs"]);
+
+    let error = commands::add_update(None, vec!["missing".to_string()], false).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Pathspec 'missing' did not match any file"
+    );
+}
+
+#[test]
+#[serial]
+fn rm_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    // relative paths from a subdirectory
+    let _inner = common::WorkingDirGuard::new(&project_path.join("src")).unwrap();
+    commands::rm(None, vec!["lib.rs".to_string()], true, false).unwrap();
+    assert_eq!(staged(), ["README.md", "src/main.rs"]);
+    assert!(Path::new("lib.rs").exists());
+
+    fs::write("main.rs", "changed").unwrap();
+    let error = commands::rm(None, vec!["main.rs".to_string()], false, false).unwrap_err();
+    assert!(matches!(error, Error::DirtyWorkTree(_)));
+    assert!(error.to_string().contains("local modifications"));
+    commands::rm(None, vec!["main.rs".to_string()], false, true).unwrap();
+    assert!(!Path::new("main.rs").exists());
+
+    // staged content that is neither in HEAD nor in the file needs -f, even with --cached
+    fs::write("../README.md", "staged").unwrap();
+    commands::add(None, vec!["../README.md".to_string()]).unwrap();
+    fs::write("../README.md", "changed again").unwrap();
+    let error = commands::rm(None, vec!["../README.md".to_string()], true, false).unwrap_err();
+    assert!(error.to_string().contains("staged content"));
+
+    let error = commands::rm(None, vec!["nothing".to_string()], false, false).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Pathspec 'nothing' did not match any file"
+    );
+}
+
+#[test]
+#[serial]
+fn mv_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let readme = Repository::open(None)
+        .unwrap()
+        .index
+        .get("README.md")
+        .cloned();
+
+    commands::mv(
+        None,
+        vec!["README.md".to_string()],
+        "docs/README.md".to_string(),
+        false,
+    )
+    .unwrap();
+    assert!(Path::new("docs/README.md").is_file());
+    assert!(!Path::new("README.md").exists());
+    assert_eq!(staged(), ["docs/README.md", "src/lib.rs", "src/main.rs"]);
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(repo.index.get("docs/README.md").cloned(), readme);
+
+    // a directory, moved into an existing directory
+    commands::mv(None, vec!["src".to_string()], "docs".to_string(), false).unwrap();
+    assert!(Path::new("docs/src/main.rs").is_file());
+    assert_eq!(
+        staged(),
+        ["docs/README.md", "docs/src/lib.rs", "docs/src/main.rs"]
+    );
+
+    fs::write("untracked.txt", "").unwrap();
+    let error = commands::mv(
+        None,
+        vec!["untracked.txt".to_string()],
+        "other.txt".to_string(),
+        false,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("not under version control"));
+
+    // nothing is moved when one of the sources fails
+    let error = commands::mv(
+        None,
+        vec!["docs/README.md".to_string(), "untracked.txt".to_string()],
+        "docs/src".to_string(),
+        false,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("not under version control"));
+    assert!(Path::new("docs/README.md").is_file());
+    assert!(!Path::new("docs/src/README.md").exists());
+    assert_eq!(
+        staged(),
+        ["docs/README.md", "docs/src/lib.rs", "docs/src/main.rs"]
+    );
+
+    fs::write("docs/src/taken.rs", "").unwrap();
+    let error = commands::mv(
+        None,
+        vec!["docs/src/lib.rs".to_string()],
+        "docs/src/taken.rs".to_string(),
+        false,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("already exists"));
+
+    let error = commands::mv(
+        None,
+        vec!["docs".to_string()],
+        "docs/src".to_string(),
+        false,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("into itself"));
+}
@@ -0,0 +1,261 @@
+use common::git;
+use flux_core::error::Error;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+fn open(path: &Path) -> Repository {
+    Repository::open(Some(path.to_string_lossy().into_owned())).unwrap()
+}
+
+#[test]
+#[serial]
+fn flux_commits_in_git_repository_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+
+    Repository::init_git(None, false).unwrap();
+    assert!(project_path.join(".git/HEAD").is_file());
+    assert!(!project_path.join(".flux").exists());
+    commands::set(None, "user_name".to_string(), "Test User".to_string()).unwrap();
+    commands::set(None, "user_email".to_string(), "test@example.com".into()).unwrap();
+
+    fs::write("run.sh", "#!/bin/sh\n").unwrap();
+    fs::set_permissions("run.sh", fs::Permissions::from_mode(0o755)).unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let hash = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    // git reads the commit, the trees and the binary index flux wrote
+    git(&project_path, &["fsck", "--strict"]);
+    assert_eq!(git(&project_path, &["rev-parse", "HEAD"]), hash);
+    assert_eq!(
+        git(&project_path, &["log", "--format=%s"]),
+        "Initial commit"
+    );
+    assert_eq!(git(&project_path, &["status", "--porcelain"]), "");
+    assert_eq!(
+        git(&project_path, &["write-tree"]),
+        git(&project_path, &["rev-parse", "HEAD^{tree}"])
+    );
+    assert_eq!(
+        git(&project_path, &["ls-files", "-s", "run.sh"]),
+        format!(
+            "100755 {} 0\trun.sh",
+            git(&project_path, &["hash-object", "run.sh"])
+        )
+    );
+
+    // and flux reads what git wrote, including the index refreshed by git status
+    fs::write("src/lib.rs", "pub fn add() {}\n").unwrap();
+    git(&project_path, &["commit", "-q", "-am", "Change lib"]);
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(
+        repo.resolve_revision("HEAD").unwrap(),
+        git(&project_path, &["rev-parse", "HEAD"])
+    );
+    assert_eq!(
+        repo.index.get("src/lib.rs").unwrap().hash,
+        git(&project_path, &["rev-parse", "HEAD:src/lib.rs"])
+    );
+    assert_eq!(repo.index.get("run.sh").unwrap().mode, "100755");
+}
+
+#[test]
+#[serial]
+fn flux_opens_packed_git_repository_test() {
+    let temp = TempDir::new().unwrap();
+    let git_repo = temp.path().join("project");
+    fs::create_dir_all(git_repo.join("docs")).unwrap();
+    git(&git_repo, &["init", "-q", "-b", "main"]);
+
+    fs::write(git_repo.join("README.md"), "Version 1\n".repeat(50)).unwrap();
+    fs::write(git_repo.join("docs/guide.md"), "Guide\n").unwrap();
+    git(&git_repo, &["add", "."]);
+    git(&git_repo, &["commit", "-q", "-m", "Initial commit"]);
+    git(&git_repo, &["tag", "-a", "v1.0", "-m", "First release"]);
+    fs::write(git_repo.join("README.md"), "Version 2\n".repeat(50)).unwrap();
+    git(&git_repo, &["commit", "-q", "-am", "Update README"]);
+    git(&git_repo, &["branch", "feature"]);
+
+    // move every object into a pack (with deltas) and every ref into packed-refs
+    git(&git_repo, &["gc", "-q", "--aggressive"]);
+    assert!(!git_repo.join(".git/refs/heads/main").exists());
+
+    let mut repo = open(&git_repo);
+    assert_eq!(repo.head, "refs/heads/main");
+    assert_eq!(
+        repo.resolve_revision("main~1").unwrap(),
+        git(&git_repo, &["rev-parse", "main~1"])
+    );
+    assert_eq!(
+        repo.resolve_revision("v1.0").unwrap(),
+        git(&git_repo, &["rev-parse", "v1.0^{commit}"])
+    );
+    let short = git(&git_repo, &["rev-parse", "--short", "HEAD"]);
+    assert_eq!(
+        repo.resolve_revision(&short).unwrap(),
+        git(&git_repo, &["rev-parse", "HEAD"])
+    );
+
+    // switching branches checks out packed blobs, and the result is a state git agrees with
+    repo.switch_branch("feature", false).unwrap();
+    fs::write(git_repo.join("docs/guide.md"), "Updated guide\n").unwrap();
+    let mut repo = open(&git_repo);
+    repo.add(&[git_repo
+        .join("docs/guide.md")
+        .to_string_lossy()
+        .into_owned()])
+        .unwrap();
+    repo.config.set("user.name", "Test User").unwrap();
+    repo.config.set("user.email", "test@example.com").unwrap();
+    let hash = repo.commit("Update guide".to_string()).unwrap();
+
+    git(&git_repo, &["fsck", "--strict"]);
+    assert_eq!(git(&git_repo, &["rev-parse", "feature"]), hash);
+    assert_eq!(
+        git(&git_repo, &["symbolic-ref", "HEAD"]),
+        "refs/heads/feature"
+    );
+    assert_eq!(git(&git_repo, &["status", "--porcelain"]), "");
+    assert_eq!(
+        git(&git_repo, &["log", "--format=%s", "-1"]),
+        "Update guide"
+    );
+
+    // a repack replaces the packs the loaded indexes point to
+    git(&git_repo, &["gc", "-q", "--prune=now"]);
+    assert!(!git_repo.join(".git/objects").join(&hash[..2]).exists());
+    let repo = open(&git_repo);
+    let object = flux_core::utils::read_object(&repo.store_dir, &hash).unwrap();
+    assert!(String::from_utf8_lossy(&object.decompressed_content).contains("Update guide"));
+
+    // a local clone of a packed repository gets all the objects it needs
+    let clone_path = temp.path().join("clone");
+    let clone = Repository::clone_from(
+        &git_repo.to_string_lossy(),
+        Some(&clone_path.to_string_lossy()),
+    )
+    .unwrap();
+    assert_eq!(clone.resolve_revision("origin/feature").unwrap(), hash);
+    assert_eq!(
+        fs::read_to_string(clone_path.join("README.md")).unwrap(),
+        "Version 2\n".repeat(50)
+    );
+}
+
+#[test]
+#[serial]
+fn git_config_identity_and_remotes_test() {
+    let temp = TempDir::new().unwrap();
+    let git_repo = temp.path().join("project");
+    fs::create_dir_all(&git_repo).unwrap();
+    git(&git_repo, &["init", "-q", "-b", "main"]);
+    git(&git_repo, &["config", "user.name", "Git User"]);
+    git(&git_repo, &["config", "user.email", "git@example.com"]);
+    git(&git_repo, &["remote", "add", "origin", "../upstream"]);
+
+    let mut config = fs::read_to_string(git_repo.join(".git/config")).unwrap();
+    config.push_str(
+        "[core] # flux reads these\n\
+         \tpager = less -R ; the pager\n\
+         \teditor = \"vim # not a comment\" # a comment\n\
+         \tautocrlf # no value\n",
+    );
+    fs::write(git_repo.join(".git/config"), config).unwrap();
+
+    let repo = open(&git_repo);
+    assert_eq!(repo.config.user_name(), Some("Git User"));
+    assert_eq!(repo.config.get_str("core.pager"), Some("less -R"));
+    assert_eq!(
+        repo.config.get_str("core.editor"),
+        Some("vim # not a comment")
+    );
+    assert_eq!(repo.config.get_bool("core.autocrlf").unwrap(), Some(true));
+    assert_eq!(
+        repo.list_remotes(),
+        vec![("origin".to_string(), "../upstream".to_string())]
+    );
+
+    // settings made with flux win over git's, and git's own config is left alone
+    let git_config = fs::read_to_string(git_repo.join(".git/config")).unwrap();
+    commands::set(
+        Some(git_repo.to_string_lossy().into_owned()),
+        "user_name".to_string(),
+        "Flux User".to_string(),
+    )
+    .unwrap();
+    assert_eq!(open(&git_repo).config.user_name(), Some("Flux User"));
+    assert_eq!(
+        fs::read_to_string(git_repo.join(".git/config")).unwrap(),
+        git_config
+    );
+}
+
+/// A binary index holding `entry`, with a valid checksum.
+fn binary_index(entry: &[u8]) -> Vec<u8> {
+    let mut data = b"DIRC".to_vec();
+    data.extend(2u32.to_be_bytes());
+    data.extend(1u32.to_be_bytes());
+    data.extend(entry);
+    let checksum = flux_core::utils::hash(&data).unwrap();
+    data.extend((0..20).map(|i| u8::from_str_radix(&checksum[i * 2..i * 2 + 2], 16).unwrap()));
+    data
+}
+
+#[test]
+#[serial]
+fn corrupt_git_index_test() {
+    let temp = TempDir::new().unwrap();
+    let git_repo = temp.path().join("project");
+    fs::create_dir_all(&git_repo).unwrap();
+    git(&git_repo, &["init", "-q", "-b", "main"]);
+
+    // an entry cut short, then one whose name has no terminator
+    let mut header = vec![0u8; 62];
+    header[60..62].copy_from_slice(&3u16.to_be_bytes());
+    for entry in [&header[..30], &[header.clone(), b"abc".to_vec()].concat()] {
+        fs::write(git_repo.join(".git/index"), binary_index(entry)).unwrap();
+        let error = Repository::open(Some(git_repo.to_string_lossy().into_owned()))
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::CorruptObject { .. }), "{error}");
+    }
+}
+
+#[test]
+#[serial]
+fn renamed_git_dir_test() {
+    let temp = TempDir::new().unwrap();
+    let git_repo = temp.path().join("project");
+    fs::create_dir_all(&git_repo).unwrap();
+    git(&git_repo, &["init", "-q", "-b", "main"]);
+    fs::rename(git_repo.join(".git"), git_repo.join("store")).unwrap();
+    fs::write(git_repo.join("README.md"), "read me\n").unwrap();
+
+    let _guard = common::WorkingDirGuard::new(&git_repo).unwrap();
+    // SAFETY: the tests of this file run serially and nothing else reads the environment.
+    unsafe {
+        std::env::set_var("FLUX_DIR", git_repo.join("store"));
+    }
+    let mut repo = Repository::open(None).unwrap();
+    repo.add(&["README.md".to_string()]).unwrap();
+    unsafe {
+        std::env::remove_var("FLUX_DIR");
+    }
+
+    // the index is git's binary one, which git reads
+    assert!(
+        fs::read(git_repo.join("store/index"))
+            .unwrap()
+            .starts_with(b"DIRC")
+    );
+    assert_eq!(
+        git(&git_repo, &["--git-dir=store", "--work-tree=.", "ls-files"]),
+        "README.md"
+    );
+}
@@ -0,0 +1,205 @@
+use flux_core::{objects::commit, repo::repository::Repository};
+use serial_test::serial;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::{fs, thread};
+use tempfile::TempDir;
+
+mod common;
+
+/// Serves every repository below `root` over smart HTTP by handing each request
+/// to `git http-backend`, the CGI program git hosting setups use.
+fn serve(root: PathBuf) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { break };
+            handle(stream, &root);
+        }
+    });
+
+    format!("http://{address}")
+}
+
+fn handle(mut stream: TcpStream, root: &Path) {
+    let mut raw = Vec::new();
+    let header_end = loop {
+        let mut buf = [0; 4096];
+        let read = stream.read(&mut buf).unwrap();
+        raw.extend_from_slice(&buf[..read]);
+        if let Some(end) = raw.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+    };
+
+    let head = String::from_utf8(raw[..header_end].to_vec()).unwrap();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap().split(' ');
+    let method = request_line.next().unwrap();
+    let target = request_line.next().unwrap();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let header = |name: &str| {
+        head.split("\r\n")
+            .skip(1)
+            .filter_map(|line| line.split_once(": "))
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.to_string())
+            .unwrap_or_default()
+    };
+    let content_length: usize = header("Content-Length").parse().unwrap_or(0);
+
+    let mut body = raw[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let mut buf = [0; 4096];
+        let read = stream.read(&mut buf).unwrap();
+        body.extend_from_slice(&buf[..read]);
+    }
+
+    let mut backend = Command::new("git")
+        .arg("http-backend")
+        .env("GIT_PROJECT_ROOT", root)
+        .env("GIT_HTTP_EXPORT_ALL", "1")
+        .env("REQUEST_METHOD", method)
+        .env("PATH_INFO", path)
+        .env("QUERY_STRING", query)
+        .env("CONTENT_TYPE", header("Content-Type"))
+        .env("CONTENT_LENGTH", content_length.to_string())
+        .env("GIT_PROTOCOL", header("Git-Protocol"))
+        .env("REMOTE_ADDR", "127.0.0.1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    backend.stdin.take().unwrap().write_all(&body).unwrap();
+    let output = backend.wait_with_output().unwrap().stdout;
+
+    let cgi_end = output.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let cgi_headers = String::from_utf8_lossy(&output[..cgi_end]).to_string();
+    let status = cgi_headers
+        .lines()
+        .find_map(|line| line.strip_prefix("Status: "))
+        .unwrap_or("200 OK");
+
+    // Answer with a chunked body, like most web servers in front of http-backend do.
+    let mut response = format!("HTTP/1.1 {status}\r\nTransfer-Encoding: chunked\r\n");
+    for line in cgi_headers
+        .lines()
+        .filter(|line| !line.starts_with("Status:"))
+    {
+        response.push_str(&format!("{line}\r\n"));
+    }
+    response.push_str("\r\n");
+    let mut response = response.into_bytes();
+    for chunk in output[cgi_end + 4..].chunks(1000) {
+        response.extend(format!("{:x}\r\n", chunk.len()).into_bytes());
+        response.extend_from_slice(chunk);
+        response.extend_from_slice(b"\r\n");
+    }
+    response.extend_from_slice(b"0\r\n\r\n");
+
+    let _ = stream.write_all(&response);
+}
+
+/// A git repository with a nested directory, an executable, a second branch and an annotated tag.
+fn git_repository(root: &Path) -> PathBuf {
+    let repo = root.join("project");
+    fs::create_dir_all(repo.join("src/bin")).unwrap();
+    common::git(&repo, &["init", "-q", "-b", "main"]);
+
+    fs::write(repo.join("README.md"), "Served over HTTP\n").unwrap();
+    fs::write(repo.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+    fs::write(repo.join("src/bin/run.sh"), "#!/bin/sh\necho run\n").unwrap();
+    Command::new("chmod")
+        .args(["+x", "src/bin/run.sh"])
+        .current_dir(&repo)
+        .status()
+        .unwrap();
+    common::git(&repo, &["add", "."]);
+    common::git(&repo, &["commit", "-q", "-m", "Initial commit"]);
+    common::git(&repo, &["tag", "-a", "v1.0", "-m", "First release"]);
+
+    common::git(&repo, &["checkout", "-q", "-b", "feature"]);
+    fs::write(repo.join("feature.txt"), "feature\n").unwrap();
+    common::git(&repo, &["add", "."]);
+    common::git(&repo, &["commit", "-q", "-m", "Add feature"]);
+    common::git(&repo, &["checkout", "-q", "main"]);
+
+    repo
+}
+
+#[test]
+#[serial]
+fn http_clone_test() {
+    let server_root = TempDir::new().unwrap();
+    let git_repo = git_repository(server_root.path());
+    let url = format!("{}/project", serve(server_root.path().to_path_buf()));
+
+    let clone_root = TempDir::new().unwrap();
+    let target = clone_root.path().join("copy");
+    let clone = Repository::clone_from(&url, Some(&target.to_string_lossy())).unwrap();
+
+    assert_eq!(clone.head, "refs/heads/main");
+    assert_eq!(
+        clone.resolve_revision("main").unwrap(),
+        common::git(&git_repo, &["rev-parse", "main"])
+    );
+    assert_eq!(
+        clone.resolve_revision("origin/feature").unwrap(),
+        common::git(&git_repo, &["rev-parse", "feature"])
+    );
+    // annotated tags are peeled down to their commit
+    assert_eq!(
+        clone.resolve_revision("v1.0").unwrap(),
+        common::git(&git_repo, &["rev-parse", "v1.0^{commit}"])
+    );
+
+    assert_eq!(
+        fs::read_to_string(target.join("README.md")).unwrap(),
+        "Served over HTTP\n"
+    );
+    assert_eq!(
+        fs::read_to_string(target.join("src/lib.rs")).unwrap(),
+        "pub fn lib() {}\n"
+    );
+    assert_eq!(clone.index.get("src/bin/run.sh").unwrap().mode, "100755");
+    assert!(!target.join("feature.txt").exists());
+}
+
+#[test]
+#[serial]
+fn http_fetch_test() {
+    let server_root = TempDir::new().unwrap();
+    let git_repo = git_repository(server_root.path());
+    let url = format!("{}/project", serve(server_root.path().to_path_buf()));
+
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.add_remote("origin", &url).unwrap();
+    let updates = repo.fetch("origin").unwrap();
+    assert_eq!(updates.len(), 3);
+
+    // a second fetch only transfers the new commit
+    fs::write(git_repo.join("README.md"), "Served over HTTP, twice\n").unwrap();
+    common::git(&git_repo, &["commit", "-q", "-am", "Update README"]);
+    let updates = repo.fetch("origin").unwrap();
+    assert_eq!(updates.len(), 1);
+
+    let head = common::git(&git_repo, &["rev-parse", "main"]);
+    assert_eq!(repo.resolve_revision("origin/main").unwrap(), head);
+    let info = commit::parse_commit(&repo.store_dir, &head).unwrap();
+    assert_eq!(info.message, "Update README\n");
+    assert_eq!(
+        info.parents,
+        vec![common::git(&git_repo, &["rev-parse", "main~1"])]
+    );
+
+    assert!(repo.push("origin", "main", false).is_err());
+}
@@ -0,0 +1,162 @@
+use flux_core::error::Error;
+use flux_core::repo::identity::{IdentityError, Role};
+use flux_core::{commands, objects::commit, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+
+const IDENTITY_VARIABLES: [&str; 6] = [
+    "FLUX_AUTHOR_NAME",
+    "FLUX_AUTHOR_EMAIL",
+    "FLUX_AUTHOR_DATE",
+    "FLUX_COMMITTER_NAME",
+    "FLUX_COMMITTER_EMAIL",
+    "FLUX_COMMITTER_DATE",
+];
+
+/// Points the global config at an empty file and ignores the system one, so only the
+/// repository config and the environment can provide an identity.
+fn isolate_config(home: &TempDir) {
+    // SAFETY: the tests of this file run serially and nothing else reads the environment.
+    unsafe {
+        std::env::set_var("FLUX_CONFIG_GLOBAL", home.path().join(".fluxconfig"));
+        std::env::set_var("FLUX_CONFIG_NOSYSTEM", "1");
+        for name in IDENTITY_VARIABLES {
+            std::env::remove_var(name);
+        }
+    }
+}
+
+fn restore_config() {
+    unsafe {
+        std::env::remove_var("FLUX_CONFIG_GLOBAL");
+        std::env::remove_var("FLUX_CONFIG_NOSYSTEM");
+        for name in IDENTITY_VARIABLES {
+            std::env::remove_var(name);
+        }
+    }
+}
+
+#[test]
+#[serial]
+fn identity_fallbacks_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let home = TempDir::new().unwrap();
+    isolate_config(&home);
+
+    Repository::init(None, false).unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+
+    // a fresh repository has no identity: committing fails instead of panicking
+    let error = commands::commit(None, "Initial commit".to_string(), None).unwrap_err();
+    assert!(matches!(
+        error.root(),
+        Error::Identity(IdentityError::MissingName(Role::Committer))
+    ));
+
+    unsafe {
+        std::env::set_var("FLUX_COMMITTER_NAME", "Env Committer");
+        std::env::set_var("FLUX_COMMITTER_EMAIL", "committer@example.com");
+    }
+    let error = commands::commit(None, "Initial commit".to_string(), None).unwrap_err();
+    assert!(matches!(
+        error.root(),
+        Error::Identity(IdentityError::MissingName(Role::Author))
+    ));
+
+    unsafe {
+        std::env::set_var("FLUX_AUTHOR_NAME", "Env Author");
+        std::env::set_var("FLUX_AUTHOR_EMAIL", "author@example.com");
+        std::env::set_var("FLUX_COMMITTER_DATE", "1704196800 +0100");
+    }
+    let hash = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    let repo = Repository::open(None).unwrap();
+    let info = commit::parse_commit(&repo.store_dir, &hash).unwrap();
+    assert_eq!(info.author.name, "Env Author");
+    assert_eq!(info.author.email, "author@example.com");
+    assert_eq!(info.committer.name, "Env Committer");
+    assert_eq!(info.committer.timestamp, 1704196800);
+    assert_eq!(info.committer.timezone, "+0100");
+
+    // the global config is used when the repository sets nothing, the repository
+    // config wins over it and the environment wins over both
+    fs::write(
+        home.path().join(".fluxconfig"),
+        "[user]\nname = \"Global User\"\nemail = \"global@example.com\"\n",
+    )
+    .unwrap();
+    unsafe {
+        std::env::remove_var("FLUX_AUTHOR_NAME");
+        std::env::remove_var("FLUX_COMMITTER_NAME");
+        std::env::remove_var("FLUX_COMMITTER_EMAIL");
+    }
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.committer().unwrap().name, "Global User");
+    let author = config.author(None).unwrap();
+    assert_eq!(author.name, "Global User");
+    assert_eq!(author.email, "author@example.com");
+
+    commands::set(None, "user.name".to_string(), "Repo User".to_string()).unwrap();
+    let config = Repository::open(None).unwrap().config;
+    assert_eq!(config.committer().unwrap().name, "Repo User");
+    assert_eq!(config.committer().unwrap().email, "global@example.com");
+
+    restore_config();
+}
+
+#[test]
+#[serial]
+fn commit_author_override_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let home = TempDir::new().unwrap();
+    isolate_config(&home);
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+
+    unsafe {
+        std::env::set_var("FLUX_AUTHOR_DATE", "2024-01-02T12:00:00+01:00");
+    }
+    let hash = commands::commit(
+        None,
+        "Initial commit".to_string(),
+        Some("Jane Doe <jane@example.com>".to_string()),
+    )
+    .unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let info = commit::parse_commit(&repo.store_dir, &hash).unwrap();
+    assert_eq!(info.author.name, "Jane Doe");
+    assert_eq!(info.author.email, "jane@example.com");
+    assert_eq!(info.author.timestamp, 1704193200);
+    assert_eq!(info.author.timezone, "+0100");
+    assert_eq!(info.committer.name, "Test User");
+    assert_ne!(info.committer.timestamp, info.author.timestamp);
+
+    fs::write("README.md", "changed").unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    let error = commands::commit(
+        None,
+        "Change README".to_string(),
+        Some("Jane Doe".to_string()),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error.root(),
+        Error::Identity(IdentityError::InvalidIdent(ident)) if ident == "Jane Doe"
+    ));
+
+    unsafe {
+        std::env::set_var("FLUX_AUTHOR_DATE", "yesterday");
+    }
+    let error = commands::commit(None, "Change README".to_string(), None).unwrap_err();
+    assert!(matches!(
+        error.root(),
+        Error::Identity(IdentityError::InvalidDate { .. })
+    ));
+
+    restore_config();
+}
@@ -54,8 +54,7 @@ fn set_test() {
     assert!(project_path.join(".flux/config").exists());
 
     let config = fs::read_to_string(".flux/config").unwrap();
-    assert!(config.contains("user_name = \"user\""));
-    assert!(config.contains("user_email = \"user@gmail.com\""));
+    assert!(config.contains("[user]\nname = \"user\"\nemail = \"user@gmail.com\""));
 }
 
 #[test]
@@ -103,22 +102,22 @@ fn commit_test() {
         .join(&readme_blob_hash[2..]);
     assert!(!readme_object_path.exists());
 
-    commands::add(None, "README.md".to_string()).unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
 
     let index = fs::read_to_string(".flux/index").unwrap();
-    assert!(index.contains(&format!("\"./README.md\":\"{}\"", readme_blob_hash)));
+    assert!(index.contains(&format!("\"README.md\":\"{}\"", readme_blob_hash)));
     assert!(readme_object_path.exists());
 
     // check if main and lib are correctly added to index
-    commands::add(None, "src/main.rs".to_string()).unwrap();
-    commands::add(None, "src/lib.rs".to_string()).unwrap();
+    commands::add(None, vec!["src/main.rs".to_string()]).unwrap();
+    commands::add(None, vec!["src/lib.rs".to_string()]).unwrap();
 
     let index = fs::read_to_string(".flux/index").unwrap();
     let main_blob_hash = commands::hash_object(None, "src/main.rs".to_string(), false).unwrap();
     let lib_blob_hash = commands::hash_object(None, "src/lib.rs".to_string(), false).unwrap();
 
-    assert!(index.contains(&format!("\"./src/main.rs\":\"{}\"", main_blob_hash)));
-    assert!(index.contains(&format!("\"./src/lib.rs\":\"{}\"", lib_blob_hash)));
+    assert!(index.contains(&format!("\"src/main.rs\":\"{}\"", main_blob_hash)));
+    assert!(index.contains(&format!("\"src/lib.rs\":\"{}\"", lib_blob_hash)));
 
     let main_object_path = project_path
         .join(".flux/objects")
@@ -127,7 +126,7 @@ fn commit_test() {
     assert!(main_object_path.exists());
 
     // check if commit is created correctly
-    let commit_hash = commands::commit(None, "Initial commit".to_string()).unwrap();
+    let commit_hash = commands::commit(None, "Initial commit".to_string(), None).unwrap();
 
     assert_eq!(commit_hash.len(), 40);
 
@@ -164,9 +163,9 @@ fn commit_test() {
 
     // update README, create second commit and check if parent is set right
     fs::write("README.md", "Updated content for second commit").unwrap();
-    commands::add(None, "README.md".to_string()).unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
 
-    let second_commit_hash = commands::commit(None, "Second commit".to_string()).unwrap();
+    let second_commit_hash = commands::commit(None, "Second commit".to_string(), None).unwrap();
 
     assert_ne!(commit_hash, second_commit_hash);
 
@@ -0,0 +1,131 @@
+use common::git;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+mod common;
+
+/// Commits with both the author and the committer date set to January `day`, 2024.
+fn git_commit_on(repo: &Path, day: u32, args: &[&str]) {
+    let date = format!("2024-01-{day:02}T12:00:00+00:00");
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "user.name=Git User",
+            "-c",
+            "user.email=git@example.com",
+        ])
+        .args(args)
+        .current_dir(repo)
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {args:?} failed");
+}
+
+fn commit_file(repo: &Path, file: &str, message: &str, day: u32) {
+    fs::write(repo.join(file), message).unwrap();
+    git(repo, &["add", "."]);
+    git_commit_on(repo, day, &["commit", "-q", "-m", message]);
+}
+
+/// Drops the abbreviated hashes from a graph, leaving the drawing, decorations and subjects.
+fn without_hashes(graph: &str) -> String {
+    graph
+        .lines()
+        .map(|line| match line.find(|c: char| c.is_ascii_hexdigit()) {
+            Some(start) if line[start..].len() > 7 && line.as_bytes()[start + 7] == b' ' => {
+                format!("{}{}", &line[..start], &line[start + 8..])
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+#[serial]
+fn log_graph_test() {
+    let temp = TempDir::new().unwrap();
+    let repo_path = temp.path().join("project");
+    fs::create_dir_all(&repo_path).unwrap();
+    git(&repo_path, &["init", "-q", "-b", "main"]);
+    commit_file(&repo_path, "a.txt", "A", 1);
+    commit_file(&repo_path, "a.txt", "B", 2);
+    git(&repo_path, &["branch", "feature"]);
+    commit_file(&repo_path, "main.txt", "D", 3);
+    git(&repo_path, &["branch", "topic"]);
+    git(&repo_path, &["checkout", "-q", "feature"]);
+    commit_file(&repo_path, "feature.txt", "E", 4);
+    git(&repo_path, &["checkout", "-q", "topic"]);
+    commit_file(&repo_path, "topic.txt", "H", 5);
+    git(&repo_path, &["checkout", "-q", "feature"]);
+    commit_file(&repo_path, "feature.txt", "F", 6);
+    git(&repo_path, &["checkout", "-q", "main"]);
+    git_commit_on(
+        &repo_path,
+        7,
+        &["merge", "-q", "--no-ff", "feature", "-m", "M"],
+    );
+    commit_file(&repo_path, "main.txt", "G", 8);
+    git(
+        &repo_path,
+        &["tag", "-a", "v1.0", "-m", "First release", "main~1"],
+    );
+    git(&repo_path, &["tag", "light", "main~3"]);
+
+    let repo = Repository::open(Some(repo_path.to_string_lossy().into_owned())).unwrap();
+    let graph = repo.log_graph(&["--all".to_string()]).unwrap();
+    assert_eq!(
+        without_hashes(&graph),
+        [
+            "* (HEAD -> main) G",
+            "* (tag: v1.0) M",
+            "|\\",
+            "| * (feature) F",
+            "| * E",
+            "| | * (topic) H",
+            "|_|/",
+            "* | D",
+            "|/",
+            "* (tag: light) B",
+            "* A",
+        ]
+        .join("\n")
+    );
+    let head = git(&repo_path, &["rev-parse", "--short=7", "HEAD"]);
+    assert!(graph.starts_with(&format!("* {head} ")), "{graph}");
+
+    // without --all only the history of HEAD is drawn
+    let graph = repo.log_graph(&[]).unwrap();
+    assert!(!graph.contains("topic"));
+    assert_eq!(graph.lines().count(), 9);
+
+    let graph = repo.log_graph(&["feature..topic".to_string()]).unwrap();
+    assert_eq!(without_hashes(&graph), "* (topic) H\n* D");
+}
+
+#[test]
+#[serial]
+fn log_graph_flux_history_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    commands::create_branch(None, "feature".to_string()).unwrap();
+    fs::write("feature.txt", "feature\n").unwrap();
+    commands::add(None, vec!["feature.txt".to_string()]).unwrap();
+    commands::commit(None, "Add feature".to_string(), None).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(
+        without_hashes(&repo.log_graph(&["--all".to_string()]).unwrap()),
+        "* (HEAD -> feature) Add feature\n* (main) Initial commit"
+    );
+}
@@ -0,0 +1,155 @@
+use flux_core::diff::{DiffOp, diff_lines, hunks, split_lines};
+use flux_core::merge::merge_lines;
+
+#[test]
+fn diff_identical_test() {
+    let lines = split_lines("a\nb\nc\n");
+    let ops = diff_lines(&lines, &lines);
+    assert!(ops.iter().all(|op| matches!(op, DiffOp::Equal { .. })));
+    assert!(hunks(&ops, 3).is_empty());
+}
+
+#[test]
+fn diff_changes_test() {
+    let old = split_lines("a\nb\nc\nd\n");
+    let new = split_lines("a\nc\nd\ne\n");
+    let ops = diff_lines(&old, &new);
+
+    assert_eq!(
+        ops,
+        vec![
+            DiffOp::Equal { old: 0, new: 0 },
+            DiffOp::Delete { old: 1 },
+            DiffOp::Equal { old: 2, new: 1 },
+            DiffOp::Equal { old: 3, new: 2 },
+            DiffOp::Insert { new: 3 },
+        ]
+    );
+
+    let hunks = hunks(&ops, 0);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!((hunks[0].old_start, hunks[0].old_len), (1, 1));
+    assert_eq!((hunks[1].new_start, hunks[1].new_len), (3, 1));
+}
+
+/// Checks that `ops` turns `old` into `new` and keeps a longest common subsequence.
+fn assert_shortest_script(old: &[u32], new: &[u32], ops: &[DiffOp]) {
+    let (mut x, mut y, mut equal) = (0, 0, 0);
+    for op in ops {
+        match *op {
+            DiffOp::Equal { old: o, new: n } => {
+                assert_eq!((o, n), (x, y));
+                assert_eq!(old[o], new[n]);
+                (x, y, equal) = (x + 1, y + 1, equal + 1);
+            }
+            DiffOp::Delete { old: o } => {
+                assert_eq!(o, x);
+                x += 1;
+            }
+            DiffOp::Insert { new: n } => {
+                assert_eq!(n, y);
+                y += 1;
+            }
+        }
+    }
+    assert_eq!((x, y), (old.len(), new.len()));
+
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    assert_eq!(equal, lcs[0][0], "{old:?} -> {new:?}");
+}
+
+#[test]
+fn diff_shortest_script_test() {
+    // a small linear congruential generator keeps the cases reproducible
+    let mut seed = 12345u64;
+    let mut next = |bound: u64| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) % bound
+    };
+    for _ in 0..500 {
+        let old: Vec<u32> = (0..next(20)).map(|_| next(4) as u32).collect();
+        let new: Vec<u32> = (0..next(20)).map(|_| next(4) as u32).collect();
+        assert_shortest_script(&old, &new, &diff_lines(&old, &new));
+    }
+}
+
+#[test]
+fn diff_large_file_test() {
+    // a fully rewritten file used to take memory quadratic in its length
+    let old: Vec<String> = (0..4_000).map(|i| format!("old {i}\n")).collect();
+    let new: Vec<String> = (0..4_000).map(|i| format!("new {i}\n")).collect();
+    let ops = diff_lines(&old, &new);
+    assert_eq!(ops.len(), 8_000);
+    assert!(matches!(ops[0], DiffOp::Delete { old: 0 }));
+    assert!(matches!(ops[4_000], DiffOp::Insert { new: 0 }));
+
+    // every other line changed
+    let new: Vec<String> = old
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i % 2 == 0 {
+                line.clone()
+            } else {
+                format!("new {i}\n")
+            }
+        })
+        .collect();
+    let equal = diff_lines(&old, &new)
+        .iter()
+        .filter(|op| matches!(op, DiffOp::Equal { .. }))
+        .count();
+    assert_eq!(equal, 2_000);
+}
+
+#[test]
+fn diff_disjoint_test() {
+    let old = split_lines("x\ny\n");
+    let new = split_lines("1\n2\n3\n");
+    let ops = diff_lines(&old, &new);
+    let deletes = ops
+        .iter()
+        .filter(|op| matches!(op, DiffOp::Delete { .. }))
+        .count();
+    let inserts = ops
+        .iter()
+        .filter(|op| matches!(op, DiffOp::Insert { .. }))
+        .count();
+    assert_eq!((deletes, inserts), (2, 3));
+}
+
+#[test]
+fn merge_non_overlapping_test() {
+    let base = "one\ntwo\nthree\nfour\n";
+    let ours = "ONE\ntwo\nthree\nfour\n";
+    let theirs = "one\ntwo\nthree\nFOUR\nfive\n";
+
+    let merged = merge_lines(base, ours, theirs, "ours", "theirs");
+    assert!(!merged.conflicts);
+    assert_eq!(merged.content, "ONE\ntwo\nthree\nFOUR\nfive\n");
+}
+
+#[test]
+fn merge_conflict_test() {
+    let base = "one\ntwo\nthree\n";
+    let ours = "one\n2\nthree\n";
+    let theirs = "one\nTWO\nthree\n";
+
+    let merged = merge_lines(base, ours, theirs, "ours", "theirs");
+    assert!(merged.conflicts);
+    assert_eq!(
+        merged.content,
+        "one\n<<<<<<< ours\n2\n=======\nTWO\n>>>>>>> theirs\nthree\n"
+    );
+}
@@ -0,0 +1,168 @@
+use flux_core::pathspec::Pathspec;
+use flux_core::shared::types::file_status::Change;
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+fn pathspec(patterns: &[&str]) -> Pathspec {
+    let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+    Pathspec::parse(&patterns, |path| {
+        Ok(path.trim_start_matches("./").to_string())
+    })
+    .unwrap()
+}
+
+#[test]
+fn pathspec_matching_test() {
+    let spec = pathspec(&["src"]);
+    assert!(spec.matches("src/main.rs"));
+    assert!(spec.matches("src"));
+    assert!(!spec.matches("srcs/main.rs"));
+
+    let spec = pathspec(&["*.rs"]);
+    assert!(spec.matches("main.rs"));
+    assert!(!spec.matches("src/main.rs"));
+
+    let spec = pathspec(&["src/**/mod.rs"]);
+    assert!(spec.matches("src/mod.rs"));
+    assert!(spec.matches("src/repo/objects/mod.rs"));
+    assert!(!spec.matches("src/repo/lib.rs"));
+
+    let spec = pathspec(&["**/*.[ch]", ":(exclude)vendor", ":!**/test_?.c"]);
+    assert!(spec.matches("lib/a.c"));
+    assert!(spec.matches("b.h"));
+    assert!(!spec.matches("lib/a.rs"));
+    assert!(!spec.matches("vendor/z.c"));
+    assert!(!spec.matches("lib/test_1.c"));
+
+    // only excludes: everything else is selected
+    let spec = pathspec(&[":^*.md"]);
+    assert!(spec.matches("src/main.rs"));
+    assert!(!spec.matches("README.md"));
+
+    let spec = pathspec(&[":(literal)a*b"]);
+    assert!(spec.matches("a*b"));
+    assert!(!spec.matches("axb"));
+
+    assert!(pathspec(&[]).matches("anything"));
+    assert_eq!(
+        pathspec(&["src", ":(literal)a*b", ":!src/lib.rs"]).literal_paths(),
+        Some(vec!["src", "a*b"])
+    );
+    assert_eq!(pathspec(&["src", "*.md"]).literal_paths(), None);
+    assert_eq!(pathspec(&[":!src"]).literal_paths(), None);
+    let patterns = vec![":(icase)x".to_string()];
+    assert!(Pathspec::parse(&patterns, |path| Ok(path.to_string())).is_err());
+}
+
+#[test]
+#[serial]
+fn pathspec_commands_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    fs::create_dir("src/repo").unwrap();
+    fs::write("src/repo/mod.rs", "pub mod a;\n").unwrap();
+    fs::write("notes.md", "notes\n").unwrap();
+
+    commands::add(
+        None,
+        vec![
+            "src/**/*.rs".to_string(),
+            ":(exclude)src/lib.rs".to_string(),
+        ],
+    )
+    .unwrap();
+    let repo = Repository::open(None).unwrap();
+    let staged: Vec<String> = repo.index.entries().into_keys().collect();
+    assert_eq!(staged, ["src/main.rs", "src/repo/mod.rs"]);
+
+    let error = commands::add(None, vec!["*.txt".to_string()]).unwrap_err();
+    assert_eq!(error.to_string(), "Pathspec '*.txt' did not match any file");
+
+    commands::add(None, vec!["*.md".to_string(), "src".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    // status and diff, from a subdirectory
+    let _inner = common::WorkingDirGuard::new(&project_path.join("src")).unwrap();
+    fs::write("main.rs", "fn main() {}\n").unwrap();
+    fs::write("repo/mod.rs", "pub mod a;\npub mod b;\n").unwrap();
+    fs::write("new.rs", "").unwrap();
+    commands::add(None, vec!["repo".to_string()]).unwrap();
+    fs::remove_file("../notes.md").unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let statuses = repo.status(&repo.pathspec(&[]).unwrap()).unwrap();
+    let short: Vec<(String, String)> = statuses
+        .iter()
+        .map(|status| (status.short(), repo.relative_path(&status.path)))
+        .collect();
+    assert_eq!(
+        short,
+        [
+            (" D".to_string(), "../notes.md".to_string()),
+            (" M".to_string(), "main.rs".to_string()),
+            ("??".to_string(), "new.rs".to_string()),
+            ("M ".to_string(), "repo/mod.rs".to_string()),
+        ]
+    );
+
+    let statuses = repo
+        .status(&repo.pathspec(&["*.rs".to_string()]).unwrap())
+        .unwrap();
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[0].unstaged, Some(Change::Modified));
+
+    let diff = repo
+        .diff(true, &repo.pathspec(&["repo".to_string()]).unwrap())
+        .unwrap();
+    assert_eq!(
+        diff,
+        "diff --git a/src/repo/mod.rs b/src/repo/mod.rs\n\
+         --- a/src/repo/mod.rs\n\
+         +++ b/src/repo/mod.rs\n\
+         @@ -1 +1,2 @@\n \
+         pub mod a;\n\
+         +pub mod b;\n"
+    );
+    let diff = repo
+        .diff(false, &repo.pathspec(&[":/notes.md".to_string()]).unwrap())
+        .unwrap();
+    assert!(diff.contains("deleted file mode 100644\n--- a/notes.md\n+++ /dev/null\n"));
+    assert!(diff.ends_with("@@ -1 +0,0 @@\n-notes\n"));
+}
+
+#[test]
+#[serial]
+fn log_paths_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let first = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    fs::write("src/main.rs", "fn main() {}\n").unwrap();
+    commands::add(None, vec!["src".to_string()]).unwrap();
+    let second = commands::commit(None, "Change main".to_string(), None).unwrap();
+    fs::write("README.md", "read me\n").unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    commands::commit(None, "Change README".to_string(), None).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let commits = |patterns: &[&str]| -> Vec<String> {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        repo.log_commits(&[], &repo.pathspec(&patterns).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|info| info.hash)
+            .collect()
+    };
+    assert_eq!(commits(&["src/*.rs"]), [second, first.clone()]);
+    assert_eq!(commits(&["src/lib.rs"]), [first]);
+    assert_eq!(commits(&[":!src"]).len(), 2);
+    assert_eq!(commits(&[]).len(), 3);
+
+    let error = commands::log(None, Vec::new(), vec!["src".to_string()], false, true).unwrap_err();
+    assert!(error.to_string().contains("--graph"));
+}
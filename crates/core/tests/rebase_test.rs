@@ -0,0 +1,175 @@
+use flux_core::{
+    commands, objects::commit, repo::repository::Repository,
+    shared::types::apply_outcome::ApplyOutcome,
+};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+fn commit_file(path: &str, content: &str, message: &str) -> String {
+    fs::write(path, content).unwrap();
+    commands::add(None, vec![path.to_string()]).unwrap();
+    commands::commit(None, message.to_string(), None).unwrap()
+}
+
+/// main: A - B, feature: A - C - D
+fn diverged_history() -> (String, String) {
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "A".to_string(), None).unwrap();
+
+    commands::create_branch(None, "feature".to_string()).unwrap();
+    commit_file("feature.txt", "first\n", "C");
+    let d = commit_file("feature.txt", "first\nsecond\n", "D");
+
+    commands::switch_branch(None, "main".to_string(), false).unwrap();
+    let b = commit_file("README.md", "Main README\n", "B");
+
+    commands::switch_branch(None, "feature".to_string(), false).unwrap();
+    (b, d)
+}
+
+#[test]
+#[serial]
+fn rebase_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (b, d) = diverged_history();
+
+    let mut repo = Repository::open(None).unwrap();
+    let ApplyOutcome::Committed(head) = repo.rebase("main", None).unwrap() else {
+        panic!("rebase should apply cleanly");
+    };
+
+    let new_d = commit::parse_commit(&repo.store_dir, &head).unwrap();
+    let new_c = commit::parse_commit(&repo.store_dir, &new_d.parents[0]).unwrap();
+    assert_eq!(new_d.message, "D");
+    assert_eq!(new_c.message, "C");
+    assert_eq!(new_c.parents, vec![b]);
+    assert_ne!(head, d);
+
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "Main README\n");
+    assert_eq!(
+        fs::read_to_string("feature.txt").unwrap(),
+        "first\nsecond\n"
+    );
+    assert!(!project_path.join(".flux/rebase-merge").exists());
+}
+
+#[test]
+#[serial]
+fn rebase_onto_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (b, _) = diverged_history();
+
+    // only replay D on top of main, dropping C
+    let mut repo = Repository::open(None).unwrap();
+    let ApplyOutcome::Conflicts(paths) = repo.rebase("feature~1", Some("main")).unwrap() else {
+        panic!("D modifies a file that does not exist on main");
+    };
+    assert_eq!(paths, vec!["feature.txt".to_string()]);
+
+    repo.rebase_skip().unwrap();
+    let repo = Repository::open(None).unwrap();
+    let feature = repo.branches.iter().find(|b| b.name == "feature").unwrap();
+    assert_eq!(feature.last_commit_hash.as_deref(), Some(b.as_str()));
+    assert!(!project_path.join("feature.txt").exists());
+}
+
+#[test]
+#[serial]
+fn rebase_conflict_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (_, d) = diverged_history();
+
+    commands::switch_branch(None, "main".to_string(), false).unwrap();
+    commit_file("feature.txt", "main version\n", "E");
+    commands::switch_branch(None, "feature".to_string(), false).unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let outcome = repo.rebase("main", None).unwrap();
+    assert_eq!(
+        outcome,
+        ApplyOutcome::Conflicts(vec!["feature.txt".to_string()])
+    );
+    assert!(repo.rebase("main", None).is_err());
+
+    // abort restores the original branch
+    repo.rebase_abort().unwrap();
+    let main_ref = fs::read_to_string(".flux/refs/heads/feature").unwrap();
+    assert_eq!(main_ref.trim(), d);
+    assert_eq!(
+        fs::read_to_string("feature.txt").unwrap(),
+        "first\nsecond\n"
+    );
+
+    // resolve both conflicting commits and continue
+    let mut repo = Repository::open(None).unwrap();
+    repo.rebase("main", None).unwrap();
+    fs::write("feature.txt", "main version\nfirst\n").unwrap();
+    commands::add(None, vec!["feature.txt".to_string()]).unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let ApplyOutcome::Committed(head) = repo.rebase_continue().unwrap() else {
+        panic!("D applies cleanly on top of the resolved C");
+    };
+
+    let new_d = commit::parse_commit(&repo.store_dir, &head).unwrap();
+    assert_eq!(new_d.message, "D");
+    assert_eq!(
+        fs::read_to_string("feature.txt").unwrap(),
+        "main version\nfirst\nsecond\n"
+    );
+}
+
+#[test]
+#[serial]
+fn rebase_untracked_file_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (b, d) = diverged_history();
+
+    // moving main onto feature would overwrite the untracked feature.txt
+    commands::switch_branch(None, "main".to_string(), false).unwrap();
+    fs::write("feature.txt", "mine\n").unwrap();
+    let mut repo = Repository::open(None).unwrap();
+    assert!(repo.rebase("feature", None).is_err());
+    assert_eq!(fs::read_to_string("feature.txt").unwrap(), "mine\n");
+    assert_eq!(repo.resolve_revision("HEAD").unwrap(), b);
+    assert!(!project_path.join(".flux/rebase-merge").exists());
+    fs::remove_file("feature.txt").unwrap();
+
+    // a commit refused half way through stays in the todo list for --continue
+    commands::switch_branch(None, "feature".to_string(), false).unwrap();
+    commit_file("later.txt", "later\n", "F");
+    commands::switch_branch(None, "main".to_string(), false).unwrap();
+    commit_file("feature.txt", "main version\n", "E");
+    commands::switch_branch(None, "feature".to_string(), false).unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let outcome = repo.rebase("main", None).unwrap();
+    assert_eq!(
+        outcome,
+        ApplyOutcome::Conflicts(vec!["feature.txt".to_string()])
+    );
+    fs::write("feature.txt", "main version\nfirst\nsecond\n").unwrap();
+    commands::add(None, vec!["feature.txt".to_string()]).unwrap();
+    fs::write("later.txt", "mine\n").unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    assert!(repo.rebase_continue().is_err());
+    assert_eq!(fs::read_to_string("later.txt").unwrap(), "mine\n");
+    fs::remove_file("later.txt").unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let ApplyOutcome::Committed(head) = repo.rebase_continue().unwrap() else {
+        panic!("F applies cleanly once later.txt is out of the way");
+    };
+    let new_f = commit::parse_commit(&repo.store_dir, &head).unwrap();
+    assert_eq!(new_f.message, "F");
+    assert_ne!(new_f.parents[0], d);
+    assert_eq!(fs::read_to_string("later.txt").unwrap(), "later\n");
+}
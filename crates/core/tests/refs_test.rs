@@ -0,0 +1,98 @@
+use flux_core::{commands, repo::refs::Refs, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+#[test]
+#[serial]
+fn nested_branch_names_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+
+    common::init_repository();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    let commit_hash = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    commands::create_branch(None, "feature/login".to_string()).unwrap();
+    assert!(
+        project_path
+            .join(".flux/refs/heads/feature/login")
+            .is_file()
+    );
+
+    let repo = Repository::open(None).unwrap();
+    let names: Vec<&str> = repo.branches.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, vec!["feature/login", "main"]);
+
+    let current = repo.branches.iter().find(|b| b.is_current).unwrap();
+    assert_eq!(current.name, "feature/login");
+    assert_eq!(
+        current.last_commit_hash.as_deref(),
+        Some(commit_hash.as_str())
+    );
+
+    let output = repo.show_branches(false, Some("feature/")).unwrap();
+    assert_eq!(output, "(*) feature/login\n");
+
+    // `feature` cannot become a branch while `feature/login` exists
+    assert!(commands::create_branch(None, "feature".to_string()).is_err());
+}
+
+#[test]
+#[serial]
+fn packed_refs_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+
+    common::init_repository();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    let commit_hash = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    let refs = Refs::new(&project_path.join(".flux"));
+    refs.write("refs/tags/v1.0", &commit_hash).unwrap();
+    refs.write("refs/remotes/origin/main", &commit_hash)
+        .unwrap();
+
+    commands::pack_refs(None).unwrap();
+
+    assert!(!project_path.join(".flux/refs/heads/main").exists());
+    assert!(!project_path.join(".flux/refs/remotes/origin").exists());
+    let packed = fs::read_to_string(".flux/packed-refs").unwrap();
+    assert!(packed.contains(&format!("{commit_hash} refs/heads/main")));
+    assert!(packed.contains(&format!("{commit_hash} refs/tags/v1.0")));
+
+    assert_eq!(
+        refs.read("refs/heads/main").unwrap(),
+        Some(commit_hash.clone())
+    );
+
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(repo.branches.len(), 1);
+    assert_eq!(repo.list_refs("refs/tags/", None).unwrap(), vec!["v1.0"]);
+    assert_eq!(
+        repo.list_refs("refs/remotes/", Some("origin/")).unwrap(),
+        vec!["origin/main"]
+    );
+
+    // loose refs shadow their packed counterparts
+    fs::write("README.md", "changed").unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    let second_hash = commands::commit(None, "Second commit".to_string(), None).unwrap();
+    assert_eq!(refs.read("refs/heads/main").unwrap(), Some(second_hash));
+
+    refs.delete("refs/tags/v1.0").unwrap();
+    assert!(refs.iter("refs/tags/").unwrap().is_empty());
+}
+
+#[test]
+fn invalid_ref_names_test() {
+    use flux_core::repo::refs::validate_name;
+
+    assert!(validate_name("refs/heads/feature/login").is_ok());
+    assert!(validate_name("refs/heads/bad..name").is_err());
+    assert!(validate_name("refs/heads/with space").is_err());
+    assert!(validate_name("refs/heads/trailing/").is_err());
+    assert!(validate_name("refs/heads/.hidden").is_err());
+    assert!(validate_name("heads/main").is_err());
+}
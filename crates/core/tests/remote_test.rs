@@ -0,0 +1,157 @@
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+mod common;
+
+/// An `origin` repository with one commit and a clone of it, both with a configured user.
+fn origin_and_clone(project_path: &Path, clone_temp: &TempDir) -> (PathBuf, String) {
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let initial = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    let clone_path = clone_temp.path().join("clone");
+    Repository::clone_from(
+        &project_path.to_string_lossy(),
+        Some(&clone_path.to_string_lossy()),
+    )
+    .unwrap();
+
+    let clone_repo_path = Some(clone_path.to_string_lossy().into_owned());
+    commands::set(
+        clone_repo_path.clone(),
+        "user_name".into(),
+        "Test User".into(),
+    )
+    .unwrap();
+    commands::set(
+        clone_repo_path,
+        "user_email".into(),
+        "test@example.com".into(),
+    )
+    .unwrap();
+
+    (clone_path, initial)
+}
+
+fn commit_in(repo: &Path, file: &str, content: &str, message: &str) -> String {
+    fs::write(repo.join(file), content).unwrap();
+    let repo_path = Some(repo.to_string_lossy().into_owned());
+    let mut repository = Repository::open(repo_path).unwrap();
+    repository
+        .add(&[repo.join(file).to_string_lossy().into_owned()])
+        .unwrap();
+    repository.commit(message.to_string()).unwrap()
+}
+
+#[test]
+#[serial]
+fn remote_add_remove_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    common::init_repository();
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.add_remote("upstream", "file:///srv/upstream").unwrap();
+    repo.add_remote("backup", "../backup").unwrap();
+    assert!(repo.add_remote("backup", "../other").is_err());
+    assert!(repo.add_remote("bad..name", "../other").is_err());
+
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(
+        repo.list_remotes(),
+        vec![
+            ("backup".to_string(), "../backup".to_string()),
+            ("upstream".to_string(), "file:///srv/upstream".to_string()),
+        ]
+    );
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.remove_remote("upstream").unwrap();
+    assert!(repo.remove_remote("upstream").is_err());
+
+    // the other settings survive the removal
+    commands::set(None, "core.editor".to_string(), "vi".to_string()).unwrap();
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(repo.list_remotes().len(), 1);
+    assert_eq!(repo.config.user_name(), Some("Test User"));
+}
+
+#[test]
+#[serial]
+fn fetch_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let clone_temp = TempDir::new().unwrap();
+    let (clone_path, initial) = origin_and_clone(&project_path, &clone_temp);
+
+    let update = commit_in(&project_path, "README.md", "Updated", "Update README");
+    commands::create_branch(None, "feature".to_string()).unwrap();
+
+    let mut clone = Repository::open(Some(clone_path.to_string_lossy().into_owned())).unwrap();
+    let updates = clone.fetch("origin").unwrap();
+    assert_eq!(updates.len(), 2);
+    assert_eq!(clone.resolve_revision("origin/main").unwrap(), update);
+    assert_eq!(clone.resolve_revision("origin/feature").unwrap(), update);
+
+    // the local branch and the working tree are left alone
+    assert_eq!(clone.resolve_revision("main").unwrap(), initial);
+    assert_eq!(
+        fs::read_to_string(clone_path.join("README.md")).unwrap(),
+        "Read this file before running the project"
+    );
+    assert!(clone.fetch("origin").unwrap().is_empty());
+    assert!(clone.fetch("missing").is_err());
+}
+
+#[test]
+#[serial]
+fn push_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let clone_temp = TempDir::new().unwrap();
+    let (clone_path, _) = origin_and_clone(&project_path, &clone_temp);
+
+    let pushed = commit_in(&clone_path, "notes.txt", "notes\n", "Add notes");
+    let mut clone = Repository::open(Some(clone_path.to_string_lossy().into_owned())).unwrap();
+    clone.push("origin", "main", false).unwrap();
+
+    // main is checked out in origin, so its working tree follows
+    let origin = Repository::open(None).unwrap();
+    assert_eq!(origin.resolve_revision("main").unwrap(), pushed);
+    assert_eq!(fs::read_to_string("notes.txt").unwrap(), "notes\n");
+    assert_eq!(clone.resolve_revision("origin/main").unwrap(), pushed);
+    assert_eq!(
+        clone.push("origin", "main", false).unwrap(),
+        "Everything up-to-date"
+    );
+
+    // diverged histories need --force
+    let remote_only = commit_in(&project_path, "README.md", "Remote", "Remote change");
+    let local_only = commit_in(&clone_path, "README.md", "Local", "Local change");
+    let mut clone = Repository::open(Some(clone_path.to_string_lossy().into_owned())).unwrap();
+    assert!(clone.push("origin", "main", false).is_err());
+
+    let origin = Repository::open(None).unwrap();
+    assert_eq!(origin.resolve_revision("main").unwrap(), remote_only);
+
+    let result = clone.push("origin", "main", true).unwrap();
+    assert!(result.starts_with("+ ") && result.ends_with("(forced update)"));
+    let origin = Repository::open(None).unwrap();
+    assert_eq!(origin.resolve_revision("main").unwrap(), local_only);
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "Local");
+
+    // a fast-forward is reported as such, even with --force
+    commit_in(&clone_path, "notes.txt", "notes again\n", "Notes again");
+    let mut clone = Repository::open(Some(clone_path.to_string_lossy().into_owned())).unwrap();
+    let result = clone.push("origin", "main", true).unwrap();
+    assert!(result.contains("..") && !result.contains("...") && !result.contains("forced"));
+
+    // uncommitted changes in the remote working tree block the update
+    fs::write("README.md", "Dirty").unwrap();
+    commit_in(&clone_path, "notes.txt", "more notes\n", "More notes");
+    let mut clone = Repository::open(Some(clone_path.to_string_lossy().into_owned())).unwrap();
+    assert!(clone.push("origin", "main", false).is_err());
+}
@@ -0,0 +1,116 @@
+use flux_core::{commands, repo::repository::Repository, shared::types::reset_mode::ResetMode};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+fn two_commits() -> (String, String) {
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let first = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    fs::write("README.md", "Second version").unwrap();
+    fs::write("src/new.rs", "pub fn new() {}").unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let second = commands::commit(None, "Second commit".to_string(), None).unwrap();
+
+    (first, second)
+}
+
+#[test]
+#[serial]
+fn soft_reset_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (first, _) = two_commits();
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.reset(ResetMode::Soft, Some("HEAD~1")).unwrap();
+
+    let main_ref = fs::read_to_string(".flux/refs/heads/main").unwrap();
+    assert_eq!(main_ref.trim(), first);
+    assert!(repo.index.get("src/new.rs").is_some());
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "Second version");
+
+    // the staged changes can be committed again right away
+    assert!(repo.commit("Redo".to_string()).is_ok());
+}
+
+#[test]
+#[serial]
+fn mixed_reset_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (first, _) = two_commits();
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.reset(ResetMode::Mixed, Some(&first[..8])).unwrap();
+
+    assert!(repo.index.get("src/new.rs").is_none());
+    assert!(project_path.join("src/new.rs").exists());
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "Second version");
+    assert!(repo.commit("Nothing staged".to_string()).is_err());
+}
+
+#[test]
+#[serial]
+fn hard_reset_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (first, second) = two_commits();
+    fs::write("untracked.txt", "keep me").unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    repo.reset(ResetMode::Hard, Some("main^")).unwrap();
+
+    assert_eq!(
+        fs::read_to_string("README.md").unwrap(),
+        "Read this file before running the project"
+    );
+    assert!(!project_path.join("src/new.rs").exists());
+    assert!(project_path.join("untracked.txt").exists());
+    assert_eq!(
+        repo.branches[0].last_commit_hash.as_deref(),
+        Some(first.as_str())
+    );
+
+    repo.reset(ResetMode::Hard, Some(&second)).unwrap();
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "Second version");
+    assert!(project_path.join("src/new.rs").exists());
+}
+
+#[test]
+#[serial]
+fn reset_paths_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let (_, second) = two_commits();
+
+    fs::write("README.md", "Third version").unwrap();
+    fs::write("src/lib.rs", "pub fn lib() {}").unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+
+    commands::reset(None, ResetMode::Mixed, None, vec!["README.md".to_string()]).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let readme = commands::hash_object(None, "README.md".to_string(), false).unwrap();
+    let lib = commands::hash_object(None, "src/lib.rs".to_string(), false).unwrap();
+    assert_ne!(repo.index.get("README.md").unwrap().hash, readme);
+    assert_eq!(repo.index.get("src/lib.rs").unwrap().hash, lib);
+
+    // the branch itself does not move
+    let main_ref = fs::read_to_string(".flux/refs/heads/main").unwrap();
+    assert_eq!(main_ref.trim(), second);
+
+    // unstaging a path that is not part of the revision drops it from the index
+    commands::reset(
+        None,
+        ResetMode::Mixed,
+        Some("HEAD~1".to_string()),
+        vec!["src".to_string()],
+    )
+    .unwrap();
+    let repo = Repository::open(None).unwrap();
+    assert!(repo.index.get("src/new.rs").is_none());
+    assert!(repo.index.get("src/main.rs").is_some());
+}
@@ -0,0 +1,174 @@
+use flux_core::{commands, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+use std::os::unix::fs::{PermissionsExt, symlink};
+use std::path::Path;
+
+mod common;
+
+#[test]
+#[serial]
+fn restore_worktree_from_index_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    fs::write("README.md", "staged").unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    fs::write("README.md", "not staged").unwrap();
+    fs::remove_file("src/lib.rs").unwrap();
+
+    commands::restore(
+        None,
+        None,
+        false,
+        false,
+        vec!["README.md".to_string(), "src".to_string()],
+    )
+    .unwrap();
+
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "staged");
+    assert_eq!(
+        fs::read_to_string("src/lib.rs").unwrap(),
+        "pub fn add(a: i32, b: i32) -> i64 { a + b }"
+    );
+    assert!(commands::restore(None, None, false, false, vec!["missing".to_string()]).is_err());
+}
+
+#[test]
+#[serial]
+fn restore_staged_and_source_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    let first = commands::commit(None, "Initial commit".to_string(), None).unwrap();
+
+    fs::write("src/main.rs", "fn main() {}").unwrap();
+    fs::set_permissions("src/main.rs", fs::Permissions::from_mode(0o755)).unwrap();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Make main executable".to_string(), None).unwrap();
+
+    // --staged restores the index from HEAD without touching the file
+    fs::write("src/main.rs", "fn main() { todo!() }").unwrap();
+    commands::add(None, vec!["src/main.rs".to_string()]).unwrap();
+    commands::restore(None, None, true, false, vec!["src/main.rs".to_string()]).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    let entry = repo.index.get("src/main.rs").unwrap();
+    assert_eq!(entry.mode, "100755");
+    assert_ne!(
+        entry.hash,
+        commands::hash_object(None, "src/main.rs".to_string(), false).unwrap()
+    );
+    assert_eq!(
+        fs::read_to_string("src/main.rs").unwrap(),
+        "fn main() { todo!() }"
+    );
+
+    // --source restores an older version, modes included
+    commands::restore(
+        None,
+        Some(first[..7].to_string()),
+        true,
+        true,
+        vec!["src/main.rs".to_string()],
+    )
+    .unwrap();
+
+    let mode = fs::metadata("src/main.rs").unwrap().permissions().mode();
+    assert_eq!(mode & 0o111, 0);
+    assert_eq!(
+        fs::read_to_string("src/main.rs").unwrap(),
+        r#"pub fn main() { println!("{}", add(1, 2)) }"#
+    );
+    let repo = Repository::open(None).unwrap();
+    assert_eq!(repo.index.get("src/main.rs").unwrap().mode, "100644");
+
+    // files missing from the source are removed
+    fs::write("src/extra.rs", "").unwrap();
+    commands::add(None, vec!["src/extra.rs".to_string()]).unwrap();
+    commands::restore(
+        None,
+        Some("HEAD".to_string()),
+        true,
+        true,
+        vec!["src".to_string()],
+    )
+    .unwrap();
+    assert!(!project_path.join("src/extra.rs").exists());
+    let repo = Repository::open(None).unwrap();
+    assert!(repo.index.get("src/extra.rs").is_none());
+    let main_mode = fs::metadata("src/main.rs").unwrap().permissions().mode();
+    assert_ne!(main_mode & 0o111, 0);
+}
+
+#[test]
+#[serial]
+fn restore_symlink_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap();
+    commands::create_branch(None, "links".to_string()).unwrap();
+
+    let links = [
+        ("readme-link", "README.md"),
+        ("src-link", "src"),
+        ("dangling", "missing"),
+    ];
+    for (link, target) in links {
+        symlink(target, link).unwrap();
+    }
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Add links".to_string(), None).unwrap();
+
+    let repo = Repository::open(None).unwrap();
+    for (link, _) in links {
+        assert_eq!(repo.index.get(link).unwrap().mode, "120000", "{link}");
+    }
+
+    // links come back as links, even where a regular file took their place
+    fs::remove_file("readme-link").unwrap();
+    fs::write("readme-link", "plain file").unwrap();
+    fs::remove_file("src-link").unwrap();
+    fs::remove_file("dangling").unwrap();
+    commands::restore(
+        None,
+        Some("HEAD".to_string()),
+        false,
+        false,
+        links.iter().map(|(link, _)| link.to_string()).collect(),
+    )
+    .unwrap();
+
+    let assert_links = || {
+        for (link, target) in links {
+            assert!(fs::symlink_metadata(link).unwrap().is_symlink(), "{link}");
+            assert_eq!(fs::read_link(link).unwrap(), Path::new(target));
+        }
+        assert_eq!(
+            fs::read_to_string("README.md").unwrap(),
+            "Read this file before running the project"
+        );
+        let repo = Repository::open(None).unwrap();
+        assert!(
+            repo.status(&repo.pathspec(&[]).unwrap())
+                .unwrap()
+                .is_empty()
+        );
+    };
+    assert_links();
+
+    // and through checkout
+    commands::switch_branch(None, "main".to_string(), false).unwrap();
+    assert!(fs::symlink_metadata("readme-link").is_err());
+    commands::switch_branch(None, "links".to_string(), false).unwrap();
+    assert_links();
+}
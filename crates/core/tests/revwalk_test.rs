@@ -0,0 +1,180 @@
+use common::git;
+use flux_core::repo::repository::Repository;
+use flux_core::repo::revwalk::Sort;
+use serial_test::serial;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+mod common;
+
+/// Commits with both the author and the committer date set to January `day`, 2024.
+fn git_commit_on(repo: &Path, day: u32, args: &[&str]) {
+    let date = format!("2024-01-{day:02}T12:00:00+00:00");
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "user.name=Git User",
+            "-c",
+            "user.email=git@example.com",
+        ])
+        .args(args)
+        .current_dir(repo)
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {args:?} failed");
+}
+
+fn commit_file(repo: &Path, file: &str, message: &str, day: u32) {
+    fs::write(repo.join(file), message).unwrap();
+    git(repo, &["add", "."]);
+    git_commit_on(repo, day, &["commit", "-q", "-m", message]);
+}
+
+/// main: A - B - D - M(F) - G, feature: B - E - F, topic: D - H
+/// Commit dates interleave between the branches.
+fn git_history(repo: &Path) {
+    fs::create_dir_all(repo).unwrap();
+    git(repo, &["init", "-q", "-b", "main"]);
+    commit_file(repo, "a.txt", "A", 1);
+    commit_file(repo, "a.txt", "B", 2);
+    git(repo, &["branch", "feature"]);
+    commit_file(repo, "main.txt", "D", 3);
+    git(repo, &["branch", "topic"]);
+    git(repo, &["checkout", "-q", "feature"]);
+    commit_file(repo, "feature.txt", "E", 4);
+    git(repo, &["checkout", "-q", "topic"]);
+    commit_file(repo, "topic.txt", "H", 5);
+    git(repo, &["checkout", "-q", "feature"]);
+    commit_file(repo, "feature.txt", "F", 6);
+    git(repo, &["checkout", "-q", "main"]);
+    git_commit_on(repo, 7, &["merge", "-q", "--no-ff", "feature", "-m", "M"]);
+    commit_file(repo, "main.txt", "G", 8);
+}
+
+fn hashes(repo: &Repository, revisions: &[&str], sort: Sort, reverse: bool) -> String {
+    let revisions: Vec<String> = revisions.iter().map(|r| r.to_string()).collect();
+    let mut walk = repo.revwalk(&revisions).unwrap();
+    walk.sort = sort;
+    walk.reverse = reverse;
+    walk.commits()
+        .unwrap()
+        .into_iter()
+        .map(|info| info.hash)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+#[serial]
+fn revwalk_matches_git_rev_list_test() {
+    let temp = TempDir::new().unwrap();
+    let repo_path = temp.path().join("project");
+    git_history(&repo_path);
+    let repo = Repository::open(Some(repo_path.to_string_lossy().into_owned())).unwrap();
+
+    for (revisions, sort, reverse, git_args) in [
+        (vec![], Sort::Date, false, vec!["--date-order", "HEAD"]),
+        (
+            vec!["main", "topic"],
+            Sort::Date,
+            false,
+            vec!["--date-order", "main", "topic"],
+        ),
+        (
+            vec!["main", "topic"],
+            Sort::Topological,
+            false,
+            vec!["--topo-order", "main", "topic"],
+        ),
+        (
+            vec!["main"],
+            Sort::Topological,
+            true,
+            vec!["--topo-order", "--reverse", "main"],
+        ),
+        (
+            vec!["topic..main"],
+            Sort::Topological,
+            false,
+            vec!["--topo-order", "topic..main"],
+        ),
+        (
+            vec!["main", "^feature"],
+            Sort::Date,
+            false,
+            vec!["--date-order", "main", "^feature"],
+        ),
+        (
+            vec!["topic...feature"],
+            Sort::Date,
+            false,
+            vec!["--date-order", "topic...feature"],
+        ),
+        (
+            vec!["feature.."],
+            Sort::Date,
+            false,
+            vec!["--date-order", "feature.."],
+        ),
+    ] {
+        let mut args = vec!["rev-list"];
+        args.extend(git_args);
+        assert_eq!(
+            hashes(&repo, &revisions, sort, reverse),
+            git(&repo_path, &args),
+            "{revisions:?}"
+        );
+    }
+
+    let mut walk = repo.revwalk(&["main".to_string()]).unwrap();
+    walk.first_parent = true;
+    let first_parent: Vec<String> = walk
+        .commits()
+        .unwrap()
+        .into_iter()
+        .map(|i| i.hash)
+        .collect();
+    assert_eq!(
+        first_parent.join("\n"),
+        git(&repo_path, &["rev-list", "--first-parent", "main"])
+    );
+}
+
+#[test]
+#[serial]
+fn ancestry_queries_test() {
+    let temp = TempDir::new().unwrap();
+    let repo_path = temp.path().join("project");
+    git_history(&repo_path);
+    let repo = Repository::open(Some(repo_path.to_string_lossy().into_owned())).unwrap();
+
+    for (a, b) in [("main", "topic"), ("feature", "topic"), ("main", "feature")] {
+        assert_eq!(
+            repo.merge_base(a, b).unwrap().unwrap(),
+            git(&repo_path, &["merge-base", a, b]),
+            "{a} {b}"
+        );
+
+        let counts = git(
+            &repo_path,
+            &["rev-list", "--left-right", "--count", &format!("{a}...{b}")],
+        );
+        let (ahead, behind) = repo.ahead_behind(a, b).unwrap();
+        assert_eq!(format!("{ahead}\t{behind}"), counts, "{a} {b}");
+    }
+
+    assert!(repo.is_ancestor("feature", "main").unwrap());
+    assert!(repo.is_ancestor("main", "main").unwrap());
+    assert!(!repo.is_ancestor("main", "feature").unwrap());
+    assert!(!repo.is_ancestor("topic", "feature").unwrap());
+
+    // unrelated histories have no merge base
+    git(&repo_path, &["checkout", "-q", "--orphan", "unrelated"]);
+    git_commit_on(&repo_path, 9, &["commit", "-q", "-m", "Unrelated root"]);
+    assert_eq!(repo.merge_base("main", "unrelated").unwrap(), None);
+    assert_eq!(repo.ahead_behind("unrelated", "topic").unwrap(), (1, 4));
+}
@@ -0,0 +1,158 @@
+use flux_core::{commands, error::Error, objects::commit, repo::repository::Repository};
+use serial_test::serial;
+use std::fs;
+
+mod common;
+
+fn initial_commit() -> String {
+    common::init_repository();
+    commands::add(None, vec![".".to_string()]).unwrap();
+    commands::commit(None, "Initial commit".to_string(), None).unwrap()
+}
+
+#[test]
+#[serial]
+fn stash_push_pop_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    let head = initial_commit();
+
+    fs::write("README.md", "Work in progress").unwrap();
+    fs::write("src/new.rs", "pub fn new() {}\n").unwrap();
+    commands::add(None, vec!["src/new.rs".to_string()]).unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let message = repo.stash_push(None, false).unwrap();
+    assert!(message.starts_with("WIP on main: "));
+
+    // the working tree and the index are back at HEAD
+    assert_eq!(
+        fs::read_to_string("README.md").unwrap(),
+        "Read this file before running the project"
+    );
+    assert!(!project_path.join("src/new.rs").exists());
+    assert!(repo.stash_push(None, false).is_err());
+
+    let stash = repo.resolve_revision("stash").unwrap();
+    let info = commit::parse_commit(&repo.store_dir, &stash).unwrap();
+    assert_eq!(info.parents.len(), 2);
+    assert_eq!(info.parents[0], head);
+    assert_eq!(repo.stash_show(0).unwrap(), "M\tREADME.md\nA\tsrc/new.rs\n");
+
+    assert!(repo.stash_pop(0).unwrap().is_empty());
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "Work in progress");
+    assert!(project_path.join("src/new.rs").exists());
+    assert!(repo.index.get("src/new.rs").is_some());
+    assert!(repo.stash_list().unwrap().is_empty());
+    assert!(!project_path.join(".flux/refs/stash").exists());
+}
+
+#[test]
+#[serial]
+fn stash_stack_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    initial_commit();
+
+    let mut repo = Repository::open(None).unwrap();
+    fs::write("README.md", "first").unwrap();
+    repo.stash_push(Some("first"), false).unwrap();
+    fs::write("README.md", "second").unwrap();
+    repo.stash_push(Some("second"), false).unwrap();
+
+    assert_eq!(
+        repo.stash_list().unwrap(),
+        vec!["stash@{0}: On main: second", "stash@{1}: On main: first"]
+    );
+
+    // dropping the older stash keeps refs/stash on the newest one
+    let newest = repo.resolve_revision("stash").unwrap();
+    repo.stash_drop(1).unwrap();
+    assert_eq!(
+        repo.stash_list().unwrap(),
+        vec!["stash@{0}: On main: second"]
+    );
+    assert_eq!(repo.resolve_revision("stash").unwrap(), newest);
+    assert!(repo.stash_drop(1).is_err());
+
+    repo.stash_apply(0).unwrap();
+    assert_eq!(fs::read_to_string("README.md").unwrap(), "second");
+    assert_eq!(repo.stash_list().unwrap().len(), 1);
+}
+
+#[test]
+#[serial]
+fn stash_untracked_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    initial_commit();
+
+    fs::create_dir("notes").unwrap();
+    fs::write("notes/todo.txt", "write tests\n").unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    assert!(repo.stash_push(None, false).is_err());
+    repo.stash_push(None, true).unwrap();
+    assert!(!project_path.join("notes").exists());
+
+    // an untracked file in the way stops the stash from being applied
+    fs::create_dir("notes").unwrap();
+    fs::write("notes/todo.txt", "other\n").unwrap();
+    assert!(repo.stash_pop(0).is_err());
+    fs::remove_dir_all("notes").unwrap();
+
+    repo.stash_pop(0).unwrap();
+    assert_eq!(
+        fs::read_to_string("notes/todo.txt").unwrap(),
+        "write tests\n"
+    );
+    assert!(repo.index.get("notes/todo.txt").is_none());
+}
+
+#[test]
+#[serial]
+fn stash_conflict_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    initial_commit();
+
+    let mut repo = Repository::open(None).unwrap();
+    fs::write("README.md", "Stashed README\n").unwrap();
+    repo.stash_push(None, false).unwrap();
+
+    fs::write("README.md", "Committed README\n").unwrap();
+    commands::add(None, vec!["README.md".to_string()]).unwrap();
+    commands::commit(None, "Update README".to_string(), None).unwrap();
+
+    let mut repo = Repository::open(None).unwrap();
+    let conflicts = repo.stash_pop(0).unwrap();
+    assert_eq!(conflicts, vec!["README.md".to_string()]);
+
+    let content = fs::read_to_string("README.md").unwrap();
+    assert!(content.starts_with("<<<<<<< HEAD\n"));
+    assert!(content.contains(">>>>>>> Stashed changes\n"));
+    assert_eq!(repo.stash_list().unwrap().len(), 1);
+}
+
+#[test]
+#[serial]
+fn stash_apply_untracked_collision_test() {
+    let (_temp, project_path) = common::setup_test_project();
+    let _guard = common::WorkingDirGuard::new(&project_path).unwrap();
+    initial_commit();
+
+    fs::write("notes.txt", "stashed\n").unwrap();
+    commands::add(None, vec!["notes.txt".to_string()]).unwrap();
+    let mut repo = Repository::open(None).unwrap();
+    repo.stash_push(None, false).unwrap();
+    assert!(!project_path.join("notes.txt").exists());
+
+    fs::write("notes.txt", "mine\n").unwrap();
+    let err = repo.stash_apply(0).unwrap_err();
+    assert!(matches!(err, Error::DirtyWorkTree(_)), "{err}");
+    assert_eq!(fs::read_to_string("notes.txt").unwrap(), "mine\n");
+    fs::remove_file("notes.txt").unwrap();
+
+    repo.stash_apply(0).unwrap();
+    assert_eq!(fs::read_to_string("notes.txt").unwrap(), "stashed\n");
+}
@@ -1,10 +1,11 @@
+use flux_core::error::Error;
 use flux_core::repo::repository::Repository;
 
 use crate::models::RepositoryInfo;
 
 #[tauri::command]
-pub fn open_repository(path: String) -> Result<RepositoryInfo, String> {
-    let repo = Repository::open(Some(path)).map_err(|err| err.to_string())?;
+pub fn open_repository(path: String) -> Result<RepositoryInfo, Error> {
+    let repo = Repository::open(Some(path))?;
 
-    Ok(RepositoryInfo::from_repo(&repo))
+    RepositoryInfo::from_repo(&repo)
 }
@@ -1,3 +1,4 @@
+use flux_core::error::Error;
 use flux_core::repo::repository::Repository;
 use serde::Serialize;
 
@@ -5,23 +6,29 @@ use serde::Serialize;
 pub struct RepositoryInfo {
     pub path: String,
     pub branches: Vec<BranchInfo>,
+    pub tags: Vec<String>,
+    pub remote_branches: Vec<String>,
     pub head: String,
     pub index: Vec<String>,
     pub uncommited: Vec<String>,
 }
 
 impl RepositoryInfo {
-    pub fn from_repo(repo: &Repository) -> Self {
-        Self {
+    pub fn from_repo(repo: &Repository) -> Result<Self, Error> {
+        Ok(Self {
             path: repo.work_tree.to_string_lossy().to_string(),
             head: repo.head.clone(),
 
             branches: repo.branches.iter().map(BranchInfo::from).collect(),
 
+            tags: repo.list_refs("refs/tags/", None)?,
+
+            remote_branches: repo.list_refs("refs/remotes/", None)?,
+
             index: repo.index.map.keys().cloned().collect(),
 
             uncommited: Vec::new(),
-        }
+        })
     }
 }
 
@@ -9,6 +9,7 @@ import { RepositoryAction } from "../models/RepositoryAction";
 import { RepositoryState } from "../models/RepositoryState";
 import { invoke } from "@tauri-apps/api/core";
 import { Repository } from "../models/Repository";
+import { isFluxError } from "../models/FluxError";
 import { open } from "@tauri-apps/plugin-dialog";
 
 interface RepositoryContextType extends RepositoryState {
@@ -87,6 +88,16 @@ const RepositoryContext = createContext<RepositoryContextType | undefined>(
 );
 const STORAGE_KEY = "flux_last_repository";
 
+function describeError(err: unknown, path?: string): string {
+  if (isFluxError(err)) {
+    if (err.kind === "not_a_repository" && path) {
+      return `${path} is not a Flux or Git repository`;
+    }
+    return err.message;
+  }
+  return err instanceof Error ? err.message : String(err);
+}
+
 const initialState: RepositoryState = {
   repository: null,
   isLoading: true,
@@ -125,8 +136,7 @@ export function RepositoryProvider({ children }: { children: ReactNode }) {
       dispatch({ type: "LOADING_SUCCESS", payload: repo });
       localStorage.setItem(STORAGE_KEY, path);
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      dispatch({ type: "LOADING_ERROR", payload: errorMessage });
+      dispatch({ type: "LOADING_ERROR", payload: describeError(err, path) });
       throw err;
     }
   };
@@ -143,8 +153,7 @@ export function RepositoryProvider({ children }: { children: ReactNode }) {
         await loadRepository(selected);
       }
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      dispatch({ type: "LOADING_ERROR", payload: errorMessage });
+      dispatch({ type: "LOADING_ERROR", payload: describeError(err) });
     }
   };
 
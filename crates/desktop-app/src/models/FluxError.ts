@@ -0,0 +1,27 @@
+export type FluxErrorKind =
+  | "not_a_repository"
+  | "object_not_found"
+  | "corrupt_object"
+  | "revision_not_found"
+  | "ref_not_found"
+  | "ref_exists"
+  | "ref_conflict"
+  | "dirty_work_tree"
+  | "nothing_to_commit"
+  | "conflict"
+  | "operation_in_progress"
+  | "config_missing"
+  | "invalid_config"
+  | "identity"
+  | "remote"
+  | "io"
+  | "other";
+
+export interface FluxError {
+  kind: FluxErrorKind;
+  message: string;
+}
+
+export function isFluxError(err: unknown): err is FluxError {
+  return typeof err === "object" && err !== null && "kind" in err && "message" in err;
+}
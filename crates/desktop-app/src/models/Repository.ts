@@ -3,6 +3,8 @@ import { Branch } from "./Branch";
 export interface Repository {
     path: string,
     branches: Branch[],
+    tags: string[],
+    remote_branches: string[],
     head: string,
     index: string[],
     uncommited: string[]